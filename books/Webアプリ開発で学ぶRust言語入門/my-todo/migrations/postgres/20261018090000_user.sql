@@ -0,0 +1,5 @@
+CREATE TABLE users
+(
+    id      SERIAL  PRIMARY KEY,
+    name    TEXT    NOT NULL UNIQUE
+);
@@ -0,0 +1,8 @@
+-- 既存のユーザーはパスワードが未設定のため、ログインできない
+ALTER TABLE users ADD COLUMN password_hash TEXT NOT NULL DEFAULT '';
+
+CREATE TABLE sessions
+(
+    token   TEXT    PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users (id)
+);
@@ -0,0 +1,4 @@
+-- 日本語の分かち書きはしないため、英数字の単語単位で索引を作る
+ALTER TABLE todo ADD COLUMN search TSVECTOR GENERATED ALWAYS AS (to_tsvector('simple', text)) STORED;
+
+CREATE INDEX todo_search_idx ON todo USING GIN (search);
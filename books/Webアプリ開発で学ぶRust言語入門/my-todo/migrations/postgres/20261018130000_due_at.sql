@@ -0,0 +1,3 @@
+ALTER TABLE todo ADD COLUMN due_at TIMESTAMPTZ;
+
+CREATE INDEX todo_due_at_idx ON todo (user_id, due_at);
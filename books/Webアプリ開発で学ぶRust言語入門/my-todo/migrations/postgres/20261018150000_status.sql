@@ -0,0 +1,6 @@
+CREATE TYPE todo_status AS ENUM ('backlog', 'in_progress', 'review', 'done');
+
+-- 完了済みのTodoは完了に、それ以外は未着手にする
+ALTER TABLE todo ADD COLUMN status todo_status NOT NULL DEFAULT 'backlog';
+UPDATE todo SET status = 'done' WHERE completed;
+ALTER TABLE todo DROP COLUMN completed;
@@ -0,0 +1,7 @@
+-- ボード上の並び順のキー。バイト順で比較するためCロケールで照合する
+ALTER TABLE todo ADD COLUMN position TEXT COLLATE "C" NOT NULL DEFAULT 'a0';
+
+-- 既存のTodoはIDの順に、新しく作るTodoのキー (`a1`以降) より前に並べる
+UPDATE todo SET position = 'a0' || LPAD(id::TEXT, 10, '0') || 'V';
+
+CREATE INDEX todo_position_idx ON todo (user_id, status, position);
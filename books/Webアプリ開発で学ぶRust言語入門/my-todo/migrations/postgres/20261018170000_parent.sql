@@ -0,0 +1,4 @@
+-- サブタスクの親のTodo。親を削除するときはサブタスクもまとめて削除する
+ALTER TABLE todo ADD COLUMN parent_id INTEGER REFERENCES todo (id);
+
+CREATE INDEX todo_parent_id_idx ON todo (parent_id);
@@ -0,0 +1,7 @@
+-- 宣言した順に並び替えられるため、`p0`が最も優先度が高い
+CREATE TYPE todo_priority AS ENUM ('p0', 'p1', 'p2', 'p3');
+
+ALTER TABLE todo ADD COLUMN priority todo_priority NOT NULL DEFAULT 'p2';
+ALTER TABLE todo ADD COLUMN estimate INTEGER CHECK (estimate BETWEEN 1 AND 1000);
+
+CREATE INDEX todo_priority_idx ON todo (user_id, priority, due_at);
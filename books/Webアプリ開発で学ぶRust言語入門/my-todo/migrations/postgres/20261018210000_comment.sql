@@ -0,0 +1,12 @@
+-- Todoを削除するときはコメントもまとめて削除する
+CREATE TABLE comments
+(
+    id          SERIAL      PRIMARY KEY,
+    todo_id     INTEGER     NOT NULL REFERENCES todo (id) ON DELETE CASCADE,
+    author_id   INTEGER     NOT NULL REFERENCES users (id),
+    body        TEXT        NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL,
+    updated_at  TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX comments_todo_id_idx ON comments (todo_id);
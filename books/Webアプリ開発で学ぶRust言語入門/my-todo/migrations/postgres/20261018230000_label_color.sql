@@ -0,0 +1,2 @@
+-- `#rrggbb`形式の色。未設定の場合はクライアントが決める
+ALTER TABLE label ADD COLUMN color TEXT;
@@ -0,0 +1,4 @@
+-- ラベル名の重複を判定するための値。前後の空白を除き、NFKC正規化して大文字小文字を畳み込んだ値をアプリケーションが求める。
+-- 既存のラベルの値は、起動時に`normalize_names`で設定する。
+-- 移行前から重複していたラベルは最も古いもの以外をNULLのまま残し、統合や名前の変更を利用者に任せる
+ALTER TABLE label ADD COLUMN normalized_name TEXT;
@@ -0,0 +1,6 @@
+CREATE TABLE todo
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    text        TEXT    NOT NULL,
+    completed   BOOLEAN NOT NULL DEFAULT false
+);
@@ -0,0 +1,12 @@
+CREATE TABLE label
+(
+    id      INTEGER PRIMARY KEY AUTOINCREMENT,
+    name    TEXT    NOT NULL
+);
+
+CREATE TABLE todo_labels
+(
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    todo_id     INTEGER NOT NULL REFERENCES todo    (id) DEFERRABLE INITIALLY DEFERRED,
+    label_id    INTEGER NOT NULL REFERENCES label   (id) DEFERRABLE INITIALLY DEFERRED
+);
@@ -0,0 +1,3 @@
+-- 既存のTodoとラベルは所有者がいないため、どのユーザーからも参照できない
+ALTER TABLE todo ADD COLUMN user_id INTEGER REFERENCES users (id);
+ALTER TABLE label ADD COLUMN user_id INTEGER REFERENCES users (id);
@@ -0,0 +1,22 @@
+-- PostgreSQLのtsvectorの代わりにFTS5の外部コンテンツテーブルで索引を作る
+CREATE VIRTUAL TABLE todo_search USING fts5(text, content = 'todo', content_rowid = 'id');
+
+INSERT INTO todo_search (rowid, text)
+SELECT id, text
+FROM todo;
+
+CREATE TRIGGER todo_search_insert AFTER INSERT ON todo
+BEGIN
+    INSERT INTO todo_search (rowid, text) VALUES (new.id, new.text);
+END;
+
+CREATE TRIGGER todo_search_delete AFTER DELETE ON todo
+BEGIN
+    INSERT INTO todo_search (todo_search, rowid, text) VALUES ('delete', old.id, old.text);
+END;
+
+CREATE TRIGGER todo_search_update AFTER UPDATE OF text ON todo
+BEGIN
+    INSERT INTO todo_search (todo_search, rowid, text) VALUES ('delete', old.id, old.text);
+    INSERT INTO todo_search (rowid, text) VALUES (new.id, new.text);
+END;
@@ -0,0 +1,4 @@
+-- UTCのRFC 3339形式の文字列で保存するため、文字列の比較で日時の前後を判定できる
+ALTER TABLE todo ADD COLUMN due_at DATETIME;
+
+CREATE INDEX todo_due_at_idx ON todo (user_id, due_at);
@@ -0,0 +1,7 @@
+-- 通知したときの期限を記録し、同じ期限で二度通知しない。期限が変われば再び通知する
+CREATE TABLE reminders
+(
+    todo_id INTEGER  PRIMARY KEY REFERENCES todo (id) ON DELETE CASCADE,
+    due_at  DATETIME NOT NULL,
+    sent_at DATETIME NOT NULL
+);
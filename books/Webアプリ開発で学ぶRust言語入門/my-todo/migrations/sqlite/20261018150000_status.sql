@@ -0,0 +1,5 @@
+-- 完了済みのTodoは完了に、それ以外は未着手にする
+ALTER TABLE todo ADD COLUMN status TEXT NOT NULL DEFAULT 'backlog'
+    CHECK (status IN ('backlog', 'in_progress', 'review', 'done'));
+UPDATE todo SET status = 'done' WHERE completed;
+ALTER TABLE todo DROP COLUMN completed;
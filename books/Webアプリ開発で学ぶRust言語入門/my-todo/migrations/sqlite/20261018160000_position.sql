@@ -0,0 +1,7 @@
+-- ボード上の並び順のキー。バイト順 (BINARY) で比較する
+ALTER TABLE todo ADD COLUMN position TEXT NOT NULL DEFAULT 'a0';
+
+-- 既存のTodoはIDの順に、新しく作るTodoのキー (`a1`以降) より前に並べる
+UPDATE todo SET position = 'a0' || printf('%010d', id) || 'V';
+
+CREATE INDEX todo_position_idx ON todo (user_id, status, position);
@@ -0,0 +1,17 @@
+-- `todo_id`のTodoは`blocker_id`のTodoが完了するまでブロックされる
+CREATE TABLE todo_dependencies
+(
+    todo_id     INTEGER NOT NULL REFERENCES todo (id) ON DELETE CASCADE,
+    blocker_id  INTEGER NOT NULL REFERENCES todo (id) ON DELETE CASCADE,
+    PRIMARY KEY (todo_id, blocker_id),
+    CHECK (todo_id <> blocker_id)
+);
+
+CREATE INDEX todo_dependencies_blocker_id_idx ON todo_dependencies (blocker_id);
+
+-- 完了していないTodoにブロックされているTodo
+CREATE VIEW blocked_todo AS
+SELECT DISTINCT todo_dependencies.todo_id AS id
+FROM todo_dependencies
+    INNER JOIN todo ON todo.id = todo_dependencies.blocker_id
+WHERE todo.status <> 'done';
@@ -0,0 +1,2 @@
+-- 繰り返しの規則 (`Recurrence`をJSONで保存する)
+ALTER TABLE todo ADD COLUMN recurrence TEXT;
@@ -0,0 +1,14 @@
+-- 内容は`digest`をキーにして別に保存する。同じ内容を複数のTodoに添付しても一度だけ保存するため、
+-- Todoを削除しても内容は残る
+CREATE TABLE attachments
+(
+    id              INTEGER  PRIMARY KEY AUTOINCREMENT,
+    todo_id         INTEGER  NOT NULL REFERENCES todo (id) ON DELETE CASCADE,
+    filename        TEXT     NOT NULL,
+    content_type    TEXT     NOT NULL,
+    size            INTEGER  NOT NULL,
+    digest          TEXT     NOT NULL,
+    created_at      DATETIME NOT NULL
+);
+
+CREATE INDEX attachments_todo_id_idx ON attachments (todo_id);
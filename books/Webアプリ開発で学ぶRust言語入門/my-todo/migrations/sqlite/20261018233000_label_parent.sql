@@ -0,0 +1,4 @@
+-- ラベルの親。ラベルを削除するときは子のラベルを削除したラベルの親に付け替える
+ALTER TABLE label ADD COLUMN parent_id INTEGER REFERENCES label (id) DEFERRABLE INITIALLY DEFERRED;
+
+CREATE INDEX label_parent_id_idx ON label (parent_id);
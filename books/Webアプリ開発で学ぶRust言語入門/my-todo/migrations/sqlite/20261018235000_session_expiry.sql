@@ -0,0 +1,10 @@
+-- トークンはSHA-256のハッシュだけを保存し、有効期限を持たせる。
+-- 既存のセッションは平文のトークンを保存しているため破棄し、再ログインを求める
+DROP TABLE sessions;
+
+CREATE TABLE sessions
+(
+    token_hash  TEXT        PRIMARY KEY,
+    user_id     INTEGER     NOT NULL REFERENCES users (id),
+    expires_at  DATETIME    NOT NULL
+);
@@ -0,0 +1,2 @@
+-- 繰り返すTodoを完了にしたときに作った次のTodo。完了にし直しても次のTodoを重ねて作らないようにする
+ALTER TABLE todo ADD COLUMN next_occurrence_id INTEGER REFERENCES todo (id) ON DELETE SET NULL;
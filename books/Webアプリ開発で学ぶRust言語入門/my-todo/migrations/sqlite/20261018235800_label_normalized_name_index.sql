@@ -0,0 +1,3 @@
+-- ラベル名の重複を防ぐ一意インデックス。既存のラベルの`normalized_name`はNULLのため作成は失敗せず、
+-- 起動時の`normalize_names`は重複しない値だけを設定する
+CREATE UNIQUE INDEX IF NOT EXISTS label_user_id_normalized_name_idx ON label (user_id, normalized_name);
@@ -0,0 +1,57 @@
+//! 添付ファイルの内容の保存先
+//!
+//! 内容はSHA-256のハッシュをキーにして保存し、同じ内容のファイルは一度だけ保存する。
+//! ファイル名などのメタデータは`AttachmentRepository`に保存する
+
+mod local;
+mod memory;
+
+use axum::async_trait;
+use sha2::{Digest, Sha256};
+
+pub use local::LocalAttachmentStore;
+pub use memory::MemoryAttachmentStore;
+
+#[async_trait]
+pub trait AttachmentStore: Send + Sync + 'static {
+    /// 内容を保存し、そのハッシュを返す
+    async fn put(&self, data: &[u8]) -> anyhow::Result<String>;
+    /// ハッシュに対応する内容を取得する
+    async fn get(&self, digest: &str) -> anyhow::Result<Vec<u8>>;
+}
+
+/// 内容のSHA-256のハッシュを16進表記で返す
+pub fn digest(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// 保存先と、1ファイルあたりの大きさの上限
+#[derive(Debug)]
+pub struct Attachments<S: AttachmentStore> {
+    store: S,
+    max_size: usize,
+}
+
+impl<S: AttachmentStore> Attachments<S> {
+    /// 既定の上限は10MiB
+    const DEFAULT_MAX_SIZE: usize = 10 * 1024 * 1024;
+
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            max_size: Self::DEFAULT_MAX_SIZE,
+        }
+    }
+
+    pub fn with_max_size(self, max_size: usize) -> Self {
+        Self { max_size, ..self }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+}
@@ -0,0 +1,106 @@
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use anyhow::{bail, Context};
+use axum::async_trait;
+
+use super::{digest, AttachmentStore};
+
+/// `root`以下に、ハッシュの先頭2文字のディレクトリに分けて保存する
+#[derive(Debug)]
+pub struct LocalAttachmentStore {
+    root: PathBuf,
+    /// 書き込み途中の一時ファイルの名前が重ならないようにする
+    sequence: AtomicU64,
+}
+
+impl LocalAttachmentStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            sequence: AtomicU64::new(0),
+        }
+    }
+
+    /// ハッシュ以外の文字列でディレクトリの外を指さないよう、SHA-256の16進表記だけを受け付ける
+    fn path(&self, digest: &str) -> anyhow::Result<PathBuf> {
+        if digest.len() != 64
+            || !digest
+                .bytes()
+                .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+        {
+            bail!("invalid digest: {digest}");
+        }
+        Ok(self.root.join(&digest[..2]).join(digest))
+    }
+}
+
+#[async_trait]
+impl AttachmentStore for LocalAttachmentStore {
+    /// 一時ファイルに書き込んでから名前を変えるため、書き込み途中の内容が読まれることはない
+    async fn put(&self, data: &[u8]) -> anyhow::Result<String> {
+        let digest = digest(data);
+        let path = self.path(&digest)?;
+        if tokio::fs::try_exists(&path).await? {
+            return Ok(digest);
+        }
+
+        let dir = path.parent().unwrap();
+        tokio::fs::create_dir_all(dir)
+            .await
+            .with_context(|| format!("fail create {}", dir.display()))?;
+        let sequence = self.sequence.fetch_add(1, Ordering::Relaxed);
+        let temp = dir.join(format!(".{digest}.{}.{sequence}", std::process::id()));
+        tokio::fs::write(&temp, data)
+            .await
+            .with_context(|| format!("fail write {}", temp.display()))?;
+        if let Err(error) = tokio::fs::rename(&temp, &path).await {
+            let _ = tokio::fs::remove_file(&temp).await;
+            return Err(error).with_context(|| format!("fail rename to {}", path.display()));
+        }
+        Ok(digest)
+    }
+
+    async fn get(&self, digest: &str) -> anyhow::Result<Vec<u8>> {
+        let path = self.path(digest)?;
+        tokio::fs::read(&path)
+            .await
+            .with_context(|| format!("fail read {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn should_store_content_by_digest() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let root = std::env::temp_dir().join(format!("my-todo-attachments-{nanos}"));
+        let store = LocalAttachmentStore::new(root.clone());
+
+        let digest = store.put(b"hello").await.expect("fail put");
+        assert_eq!(
+            digest,
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+        assert_eq!(store.put(b"hello").await.unwrap(), digest);
+        assert_eq!(store.get(&digest).await.unwrap(), b"hello");
+
+        // 同じ内容は一度だけ保存する
+        let files = std::fs::read_dir(root.join("2c")).unwrap().count();
+        assert_eq!(files, 1);
+
+        assert!(store.get("../../etc/passwd").await.is_err());
+        assert!(store.get(&"0".repeat(64)).await.is_err());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
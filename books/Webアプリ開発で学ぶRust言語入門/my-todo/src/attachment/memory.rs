@@ -0,0 +1,43 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::Context;
+use axum::async_trait;
+
+use super::{digest, AttachmentStore};
+
+/// 内容をメモリ上に保存する
+#[derive(Debug, Clone, Default)]
+pub struct MemoryAttachmentStore {
+    blobs: Arc<RwLock<HashMap<String, Vec<u8>>>>,
+}
+
+impl MemoryAttachmentStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl AttachmentStore for MemoryAttachmentStore {
+    async fn put(&self, data: &[u8]) -> anyhow::Result<String> {
+        let digest = digest(data);
+        self.blobs
+            .write()
+            .unwrap()
+            .entry(digest.clone())
+            .or_insert_with(|| data.to_vec());
+        Ok(digest)
+    }
+
+    async fn get(&self, digest: &str) -> anyhow::Result<Vec<u8>> {
+        self.blobs
+            .read()
+            .unwrap()
+            .get(digest)
+            .cloned()
+            .with_context(|| format!("no attachment content for {digest}"))
+    }
+}
@@ -1,63 +1,142 @@
 use std::sync::Arc;
 
 use axum::{
-    http::StatusCode,
-    routing::{delete, get, post},
+    extract::DefaultBodyLimit,
+    routing::{get, patch, post},
     Extension, Router,
 };
 
-use crate::repository::{label::LabelRepository, todo::TodoRepository, RepositoryError};
+use crate::{
+    attachment::{AttachmentStore, Attachments},
+    repository::{
+        attachment::AttachmentRepository, comment::CommentRepository, label::LabelRepository,
+        session::SessionRepository, todo::TodoRepository, user::UserRepository,
+    },
+};
 
 use self::{
-    label::{all_label, create_label, delete_label},
-    todo::{all_todo, create_todo, delete_todo, find_todo, update_todo},
-    user::create_user,
+    attachment::{create_attachment, find_attachment},
+    auth::{login, logout, SessionStore},
+    comment::{all_comment, create_comment, delete_comment, update_comment},
+    label::{
+        all_label, create_label, delete_label, find_label, merge_label, subtree_label, update_label,
+    },
+    todo::{
+        add_dependency, all_todo, board, children_todo, create_todo, delete_todo, find_todo,
+        move_todo, search_todo, update_todo,
+    },
+    user::{all_user, create_user, find_user},
 };
 
+mod attachment;
+mod auth;
+mod comment;
+mod error;
+mod extract;
 mod label;
 mod todo;
 mod user;
 
-pub fn create_app<Todo: TodoRepository, Label: LabelRepository>(
+pub use self::{auth::AuthUser, error::ApiError};
+
+/// 添付ファイル以外のフィールドやmultipartの区切りのために、ファイルの上限に加えて受け付ける大きさ
+const MULTIPART_OVERHEAD: usize = 64 * 1024;
+
+pub fn create_app<
+    Todo: TodoRepository,
+    Comment: CommentRepository,
+    Attachment: AttachmentRepository,
+    Store: AttachmentStore,
+    Label: LabelRepository,
+    User: UserRepository,
+    Session: SessionRepository,
+>(
     todo_repository: Todo,
+    comment_repository: Comment,
+    attachment_repository: Attachment,
+    attachments: Attachments<Store>,
     label_repository: Label,
+    user_repository: User,
+    session_repository: Session,
 ) -> Router {
+    let sessions: SessionStore = Arc::new(session_repository);
+    let body_limit = attachments.max_size() + MULTIPART_OVERHEAD;
+
     Router::new()
-        .route("/", get(root))
-        .route("/users", post(create_user))
+        .route(
+            "/todos/:id/attachments",
+            post(create_attachment::<Todo, Attachment, Store>)
+                .layer(DefaultBodyLimit::max(body_limit)),
+        )
+        .route(
+            "/attachments/:id",
+            get(find_attachment::<Attachment, Store>),
+        )
+        .layer(Extension(Arc::new(attachment_repository)))
+        .layer(Extension(Arc::new(attachments)))
         .route("/todos", get(all_todo::<Todo>).post(create_todo::<Todo>))
+        .route("/todos/search", get(search_todo::<Todo>))
         .route(
             "/todos/:id",
             get(find_todo::<Todo>)
                 .patch(update_todo::<Todo>)
                 .delete(delete_todo::<Todo>),
         )
+        .route("/todos/:id/children", get(children_todo::<Todo>))
+        .route("/todos/:id/move", post(move_todo::<Todo>))
+        .route("/todos/:id/dependencies", post(add_dependency::<Todo>))
+        .route("/board", get(board::<Todo>))
         .layer(Extension(Arc::new(todo_repository)))
+        .route(
+            "/todos/:id/comments",
+            get(all_comment::<Comment>).post(create_comment::<Comment>),
+        )
+        .route(
+            "/todos/:id/comments/:comment_id",
+            patch(update_comment::<Comment>).delete(delete_comment::<Comment>),
+        )
+        .layer(Extension(Arc::new(comment_repository)))
         .route(
             "/labels",
             get(all_label::<Label>).post(create_label::<Label>),
         )
-        .route("/label/:id", delete(delete_label::<Label>))
+        .route(
+            "/labels/:id",
+            get(find_label::<Label>)
+                .patch(update_label::<Label>)
+                .delete(delete_label::<Label>),
+        )
+        .route("/labels/:id/subtree", get(subtree_label::<Label>))
+        .route("/labels/:id/merge", post(merge_label::<Label>))
         .layer(Extension(Arc::new(label_repository)))
+        .route("/", get(root))
+        .route("/users", get(all_user::<User>).post(create_user::<User>))
+        .route("/users/:id", get(find_user::<User>))
+        .route("/login", post(login::<User>))
+        .route("/logout", post(logout))
+        .layer(Extension(Arc::new(user_repository)))
+        .layer(Extension(sessions))
 }
 
 async fn root() -> &'static str {
     "Hello, world!"
 }
 
-fn handle_error(error: RepositoryError) -> StatusCode {
-    match error {
-        RepositoryError::NotFound(_id) => StatusCode::NOT_FOUND,
-        RepositoryError::Duplicate(_id) => StatusCode::BAD_REQUEST,
-        RepositoryError::Unexpected(_) => StatusCode::INTERNAL_SERVER_ERROR,
-    }
-}
-
 #[cfg(test)]
 mod tests {
-    use crate::repository::{
-        label::LabelRepositoryForMemory,
-        todo::{CreateTodo, Todo, TodoRepository, TodoRepositoryForMemory},
+    use crate::{
+        attachment::MemoryAttachmentStore,
+        repository::{
+            attachment::AttachmentRepositoryForMemory,
+            comment::CommentRepositoryForMemory,
+            label::{CreateLabel, LabelRepository, LabelRepositoryForMemory},
+            session::{Session, SessionRepositoryForMemory},
+            todo::{
+                CreateTodo, Todo, TodoRepository, TodoRepositoryForMemory, TodoSearchHit,
+                TodoStatus,
+            },
+            user::{CreateUser, User, UserRepository, UserRepositoryForMemory},
+        },
     };
     use axum::{
         body::Body,
@@ -65,207 +144,1709 @@ mod tests {
         response::Response,
     };
     use tower::ServiceExt;
-    use user::User;
 
     use super::*;
 
-    async fn res_to_todo(res: Response) -> Todo {
+    /// メモリ上のリポジトリで構成したアプリケーション
+    struct TestApp {
+        todo_repository: TodoRepositoryForMemory,
+        comment_repository: CommentRepositoryForMemory,
+        attachment_repository: AttachmentRepositoryForMemory,
+        attachment_store: MemoryAttachmentStore,
+        label_repository: LabelRepositoryForMemory,
+        user_repository: UserRepositoryForMemory,
+        session_repository: SessionRepositoryForMemory,
+    }
+
+    impl TestApp {
+        const MAX_ATTACHMENT_SIZE: usize = 1024;
+
+        fn new() -> Self {
+            let label_repository = LabelRepositoryForMemory::new();
+            let todo_repository = TodoRepositoryForMemory::new(label_repository.clone());
+            Self {
+                comment_repository: CommentRepositoryForMemory::new(todo_repository.clone()),
+                attachment_repository: AttachmentRepositoryForMemory::new(todo_repository.clone()),
+                attachment_store: MemoryAttachmentStore::new(),
+                todo_repository,
+                label_repository,
+                user_repository: UserRepositoryForMemory::new(),
+                session_repository: SessionRepositoryForMemory::new(),
+            }
+        }
+
+        async fn request(&self, req: Request<Body>) -> Response {
+            create_app(
+                self.todo_repository.clone(),
+                self.comment_repository.clone(),
+                self.attachment_repository.clone(),
+                Attachments::new(self.attachment_store.clone())
+                    .with_max_size(Self::MAX_ATTACHMENT_SIZE),
+                self.label_repository.clone(),
+                self.user_repository.clone(),
+                self.session_repository.clone(),
+            )
+            .oneshot(req)
+            .await
+            .unwrap()
+        }
+
+        /// ユーザーを登録してログインし、ユーザーIDと`Authorization`ヘッダーの値を返す
+        async fn login(&self, name: &str) -> (i32, String) {
+            let user = self
+                .user_repository
+                .create(CreateUser::new(name.to_string(), "password".to_string()))
+                .await
+                .unwrap();
+            let session = self.session_repository.create(user.id()).await.unwrap();
+            (user.id(), format!("Bearer {}", session.token()))
+        }
+    }
+
+    async fn res_to_string(res: Response) -> String {
         let bytes = hyper::body::to_bytes(res.into_body()).await.unwrap();
-        let body = String::from_utf8(bytes.to_vec()).unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    async fn res_to_todo(res: Response) -> Todo {
+        let body = res_to_string(res).await;
         let todo: Todo = serde_json::from_str(&body)
             .unwrap_or_else(|_| panic!("cannot convert Todo instance. body: {body}"));
         todo
     }
 
+    async fn res_to_problem(res: Response) -> serde_json::Value {
+        assert_eq!(
+            res.headers()[header::CONTENT_TYPE],
+            "application/problem+json"
+        );
+        let status = res.status();
+        let body = res_to_string(res).await;
+        let problem: serde_json::Value = serde_json::from_str(&body)
+            .unwrap_or_else(|_| panic!("cannot convert problem details. body: {body}"));
+        assert_eq!(problem["status"], status.as_u16());
+        problem
+    }
+
     #[tokio::test]
     async fn should_not_found() {
-        let todo_repository = TodoRepositoryForMemory::new();
-        let label_repository = LabelRepositoryForMemory::new();
+        let app = TestApp::new();
 
         let req = Request::builder()
             .uri("/not-exist")
             .body(Body::empty())
             .unwrap();
-        let res = create_app(todo_repository, label_repository)
-            .oneshot(req)
-            .await
-            .unwrap();
+        let res = app.request(req).await;
 
         assert_eq!(res.status(), StatusCode::NOT_FOUND);
     }
 
     #[tokio::test]
     async fn should_return_hello_world() {
-        let todo_repository = TodoRepositoryForMemory::new();
-        let label_repository = LabelRepositoryForMemory::new();
+        let app = TestApp::new();
 
         let req = Request::builder().uri("/").body(Body::empty()).unwrap();
-        let res = create_app(todo_repository, label_repository)
-            .oneshot(req)
-            .await
-            .unwrap();
+        let res = app.request(req).await;
         assert_eq!(res.status(), StatusCode::OK);
 
-        let bytes = hyper::body::to_bytes(res.into_body()).await.unwrap();
-        let body = String::from_utf8(bytes.to_vec()).unwrap();
+        let body = res_to_string(res).await;
 
         assert_eq!(body, "Hello, world!");
     }
 
     #[tokio::test]
-    async fn should_return_user_data() {
-        let todo_repository = TodoRepositoryForMemory::new();
-        let label_repository = LabelRepositoryForMemory::new();
+    async fn should_created_user() {
+        let app = TestApp::new();
 
         let req = Request::builder()
             .uri("/users")
             .method(Method::POST)
             .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
-            .body(Body::from(r#"{ "username": "田中 太郎" }"#))
+            .body(Body::from(
+                r#"{ "username": "田中 太郎", "password": "password" }"#,
+            ))
             .unwrap();
-        let res = create_app(todo_repository, label_repository)
-            .oneshot(req)
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+
+        let body = res_to_string(res).await;
+        assert!(!body.contains("password"), "password is exposed: {body}");
+        let user: User = serde_json::from_str(&body).expect("cannot convert User instance.");
+
+        assert_eq!(user, User::new(1, "田中 太郎".to_string()));
+    }
+
+    #[tokio::test]
+    async fn should_reject_invalid_user() {
+        let app = TestApp::new();
+
+        let too_long = "a".repeat(51);
+        for (username, password, field) in [
+            ("", "password", "username"),
+            ("   ", "password", "username"),
+            (too_long.as_str(), "password", "username"),
+            ("田中 太郎", "short", "password"),
+        ] {
+            let req = Request::builder()
+                .uri("/users")
+                .method(Method::POST)
+                .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::json!({ "username": username, "password": password }).to_string(),
+                ))
+                .unwrap();
+            let res = app.request(req).await;
+            assert_eq!(
+                res.status(),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "username: {username:?}, password: {password:?}"
+            );
+
+            let problem = res_to_problem(res).await;
+            assert_eq!(problem["type"], "/problems/validation");
+            assert_eq!(problem["invalid-params"][0]["name"], field);
+        }
+    }
+
+    #[tokio::test]
+    async fn should_not_created_duplicate_user() {
+        let app = TestApp::new();
+
+        app.user_repository
+            .create(CreateUser::new(
+                "田中 太郎".to_string(),
+                "password".to_string(),
+            ))
             .await
             .unwrap();
-        assert_eq!(res.status(), StatusCode::CREATED);
 
-        let bytes = hyper::body::to_bytes(res.into_body()).await.unwrap();
-        let body = String::from_utf8(bytes.to_vec()).unwrap();
+        let req = Request::builder()
+            .uri("/users")
+            .method(Method::POST)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(
+                r#"{ "username": "田中 太郎", "password": "password" }"#,
+            ))
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let problem = res_to_problem(res).await;
+        assert_eq!(problem["type"], "/problems/duplicate");
+        assert_eq!(problem["id"], 1);
+    }
+
+    #[tokio::test]
+    async fn should_find_user() {
+        let app = TestApp::new();
+
+        let (_, authorization) = app.login("should_find_user").await;
+
+        let req = Request::builder()
+            .uri("/users/1")
+            .method(Method::GET)
+            .header(header::AUTHORIZATION, &authorization)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = res_to_string(res).await;
         let user: User = serde_json::from_str(&body).expect("cannot convert User instance.");
 
-        assert_eq!(user, User::new(1337, "田中 太郎".to_string()));
+        assert_eq!(user, User::new(1, "should_find_user".to_string()));
+    }
+
+    #[tokio::test]
+    async fn should_all_users() {
+        let app = TestApp::new();
+
+        let (_, authorization) = app.login("should_all_users").await;
+
+        let req = Request::builder()
+            .uri("/users")
+            .method(Method::GET)
+            .header(header::AUTHORIZATION, &authorization)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = res_to_string(res).await;
+        let users: Vec<User> = serde_json::from_str(&body).expect("cannot convert User instance.");
+
+        assert_eq!(users, vec![User::new(1, "should_all_users".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn should_login() {
+        let app = TestApp::new();
+
+        let user = app
+            .user_repository
+            .create(CreateUser::new(
+                "should_login".to_string(),
+                "password".to_string(),
+            ))
+            .await
+            .unwrap();
+
+        let req = Request::builder()
+            .uri("/login")
+            .method(Method::POST)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(
+                r#"{ "username": "should_login", "password": "password" }"#,
+            ))
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = res_to_string(res).await;
+        let session: Session =
+            serde_json::from_str(&body).expect("cannot convert Session instance.");
+        assert_eq!(session.user_id(), user.id());
+
+        let req = Request::builder()
+            .uri("/todos")
+            .method(Method::GET)
+            .header(header::AUTHORIZATION, format!("Bearer {}", session.token()))
+            .body(Body::empty())
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn should_not_login_with_wrong_password() {
+        let app = TestApp::new();
+
+        app.user_repository
+            .create(CreateUser::new(
+                "should_not_login".to_string(),
+                "password".to_string(),
+            ))
+            .await
+            .unwrap();
+
+        let req = Request::builder()
+            .uri("/login")
+            .method(Method::POST)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(
+                r#"{ "username": "should_not_login", "password": "wrong" }"#,
+            ))
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn should_logout() {
+        let app = TestApp::new();
+        let (_, authorization) = app.login("test_user").await;
+
+        let req = Request::builder()
+            .uri("/logout")
+            .method(Method::POST)
+            .header(header::AUTHORIZATION, &authorization)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        for uri in ["/todos", "/logout"] {
+            let req = Request::builder()
+                .uri(uri)
+                .method(if uri == "/todos" {
+                    Method::GET
+                } else {
+                    Method::POST
+                })
+                .header(header::AUTHORIZATION, &authorization)
+                .body(Body::empty())
+                .unwrap();
+            let res = app.request(req).await;
+            assert_eq!(res.status(), StatusCode::UNAUTHORIZED, "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn should_reject_unauthenticated_requests() {
+        let app = TestApp::new();
+
+        for uri in ["/todos", "/labels", "/users", "/users/1"] {
+            let req = Request::builder()
+                .uri(uri)
+                .method(Method::GET)
+                .body(Body::empty())
+                .unwrap();
+            let res = app.request(req).await;
+            assert_eq!(res.status(), StatusCode::UNAUTHORIZED, "GET {uri}");
+            assert_eq!(res.headers()[header::WWW_AUTHENTICATE], "Bearer");
+            let problem = res_to_problem(res).await;
+            assert_eq!(problem["type"], "/problems/unauthorized");
+
+            let req = Request::builder()
+                .uri(uri)
+                .method(Method::GET)
+                .header(header::AUTHORIZATION, "Bearer unknown")
+                .body(Body::empty())
+                .unwrap();
+            let res = app.request(req).await;
+            assert_eq!(res.status(), StatusCode::UNAUTHORIZED, "GET {uri}");
+        }
     }
 
     #[tokio::test]
     async fn should_created_todo() {
-        let todo_repository = TodoRepositoryForMemory::new();
-        let label_repository = LabelRepositoryForMemory::new();
+        let app = TestApp::new();
+        let (_, authorization) = app.login("test_user").await;
 
         let req = Request::builder()
             .uri("/todos")
             .method(Method::POST)
+            .header(header::AUTHORIZATION, &authorization)
             .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
             .body(Body::from(r#"{ "text": "should_return_created_todo" }"#))
             .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
 
-        let res = create_app(todo_repository, label_repository)
-            .oneshot(req)
+        let todo = res_to_todo(res).await;
+        let expected = Todo::new(1, "should_return_created_todo".to_string(), vec![]);
+        assert_eq!(todo, expected);
+    }
+
+    #[tokio::test]
+    async fn should_created_todo_with_labels() {
+        let app = TestApp::new();
+        let (user_id, authorization) = app.login("test_user").await;
+
+        let label = app
+            .label_repository
+            .create(
+                user_id,
+                CreateLabel::new("should_created_todo_with_labels".to_string()),
+            )
             .await
             .unwrap();
+
+        let req = Request::builder()
+            .uri("/todos")
+            .method(Method::POST)
+            .header(header::AUTHORIZATION, &authorization)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(
+                r#"{ "text": "should_created_todo_with_labels", "labels": [1] }"#,
+            ))
+            .unwrap();
+        let res = app.request(req).await;
         assert_eq!(res.status(), StatusCode::CREATED);
 
         let todo = res_to_todo(res).await;
-        let expected = Todo::new(1, "should_return_created_todo".to_string());
+        let expected = Todo::new(
+            1,
+            "should_created_todo_with_labels".to_string(),
+            vec![label],
+        );
         assert_eq!(todo, expected);
     }
 
     #[tokio::test]
     async fn should_find_todo() {
-        let repository = TodoRepositoryForMemory::new();
-        let label_repository = LabelRepositoryForMemory::new();
+        let app = TestApp::new();
+        let (user_id, authorization) = app.login("test_user").await;
 
-        repository
-            .create(CreateTodo::new("should_find_todo".to_string()))
+        app.todo_repository
+            .create(
+                user_id,
+                CreateTodo::new("should_find_todo".to_string(), vec![]),
+            )
             .await
             .unwrap();
 
         let req = Request::builder()
             .uri("/todos/1")
             .method(Method::GET)
+            .header(header::AUTHORIZATION, &authorization)
             .body(Body::empty())
             .unwrap();
-        let res = create_app(repository, label_repository)
-            .oneshot(req)
-            .await
-            .unwrap();
+        let res = app.request(req).await;
         assert_eq!(res.status(), StatusCode::OK);
 
         let todo = res_to_todo(res).await;
-        let expected = Todo::new(1, "should_find_todo".to_string());
+        let expected = Todo::new(1, "should_find_todo".to_string(), vec![]);
         assert_eq!(todo, expected);
     }
 
     #[tokio::test]
     async fn should_all_todos() {
-        let repository = TodoRepositoryForMemory::new();
-        let label_repository = LabelRepositoryForMemory::new();
+        let app = TestApp::new();
+        let (user_id, authorization) = app.login("test_user").await;
 
-        repository
-            .create(CreateTodo::new("should_all_todos".to_string()))
+        app.todo_repository
+            .create(
+                user_id,
+                CreateTodo::new("should_all_todos".to_string(), vec![]),
+            )
             .await
             .unwrap();
 
         let req = Request::builder()
             .uri("/todos")
             .method(Method::GET)
+            .header(header::AUTHORIZATION, &authorization)
             .body(Body::empty())
             .unwrap();
-        let res = create_app(repository, label_repository)
-            .oneshot(req)
-            .await
-            .unwrap();
+        let res = app.request(req).await;
         assert_eq!(res.status(), StatusCode::OK);
 
-        let bytes = hyper::body::to_bytes(res.into_body()).await.unwrap();
-        let body = String::from_utf8(bytes.to_vec()).unwrap();
+        let body = res_to_string(res).await;
         let todos: Vec<Todo> = serde_json::from_str(&body)
             .unwrap_or_else(|_| panic!("cannot convert Todo instance. body: {body}"));
-        let expected = Todo::new(1, "should_all_todos".to_string());
+        let expected = Todo::new(1, "should_all_todos".to_string(), vec![]);
         assert_eq!(todos, vec![expected]);
     }
 
     #[tokio::test]
     async fn should_update_todo() {
-        let repository = TodoRepositoryForMemory::new();
-        let label_repository = LabelRepositoryForMemory::new();
+        let app = TestApp::new();
+        let (user_id, authorization) = app.login("test_user").await;
 
-        repository
-            .create(CreateTodo::new("before_update_todo".to_string()))
+        app.todo_repository
+            .create(
+                user_id,
+                CreateTodo::new("before_update_todo".to_string(), vec![]),
+            )
             .await
             .unwrap();
 
         let req = Request::builder()
             .uri("/todos/1")
             .method(Method::PATCH)
+            .header(header::AUTHORIZATION, &authorization)
             .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
             .body(Body::from(
                 r#"
                 {
                     "text": "should_update_todo",
-                    "completed": false
+                    "status": "in_progress"
                 }"#,
             ))
             .unwrap();
-        let res = create_app(repository, label_repository)
-            .oneshot(req)
-            .await
-            .unwrap();
+        let res = app.request(req).await;
         assert_eq!(res.status(), StatusCode::CREATED);
 
         let todo = res_to_todo(res).await;
-        let expected = Todo::new(1, "should_update_todo".to_string());
-        assert_eq!(todo, expected);
+        assert_eq!(todo.id(), 1);
+        assert_eq!(todo.status(), TodoStatus::InProgress);
+        let body = serde_json::to_value(&todo).unwrap();
+        assert_eq!(body["text"], "should_update_todo");
+        assert_eq!(body["status"], "in_progress");
     }
 
     #[tokio::test]
-    async fn should_delete_todo() {
-        let repository = TodoRepositoryForMemory::new();
-        let label_repository = LabelRepositoryForMemory::new();
+    async fn should_reject_invalid_status_transition() {
+        let app = TestApp::new();
+        let (user_id, authorization) = app.login("test_user").await;
 
-        repository
-            .create(CreateTodo::new("should_delete_todo".to_string()))
+        app.todo_repository
+            .create(user_id, CreateTodo::new("todo".to_string(), vec![]))
             .await
             .unwrap();
 
         let req = Request::builder()
             .uri("/todos/1")
-            .method(Method::DELETE)
+            .method(Method::PATCH)
+            .header(header::AUTHORIZATION, &authorization)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(r#"{ "status": "done" }"#))
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+
+        let problem = res_to_problem(res).await;
+        assert_eq!(problem["type"], "/problems/invalid-transition");
+        assert_eq!(problem["id"], 1);
+        assert_eq!(
+            problem["detail"],
+            "status can not change from backlog to done"
+        );
+    }
+
+    #[tokio::test]
+    async fn should_move_todo_on_board() {
+        let app = TestApp::new();
+        let (user_id, authorization) = app.login("test_user").await;
+
+        for text in ["first", "second"] {
+            app.todo_repository
+                .create(user_id, CreateTodo::new(text.to_string(), vec![]))
+                .await
+                .unwrap();
+        }
+
+        let req = Request::builder()
+            .uri("/todos/2/move")
+            .method(Method::POST)
+            .header(header::AUTHORIZATION, &authorization)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(r#"{ "status": "in_progress" }"#))
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let todo = res_to_todo(res).await;
+        assert_eq!(todo.status(), TodoStatus::InProgress);
+
+        let req = Request::builder()
+            .uri("/board")
+            .method(Method::GET)
+            .header(header::AUTHORIZATION, &authorization)
             .body(Body::empty())
             .unwrap();
-        let res = create_app(repository, label_repository)
-            .oneshot(req)
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_str(&res_to_string(res).await).unwrap();
+        let columns: Vec<(&str, Vec<u64>)> = body["columns"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|column| {
+                let ids = column["todos"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|todo| todo["id"].as_u64().unwrap())
+                    .collect();
+                (column["status"].as_str().unwrap(), ids)
+            })
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                ("backlog", vec![1]),
+                ("in_progress", vec![2]),
+                ("review", vec![]),
+                ("done", vec![]),
+            ]
+        );
+
+        let req = Request::builder()
+            .uri("/todos/1/move")
+            .method(Method::POST)
+            .header(header::AUTHORIZATION, &authorization)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(r#"{ "status": "done" }"#))
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        let problem = res_to_problem(res).await;
+        assert_eq!(problem["type"], "/problems/invalid-transition");
+    }
+
+    #[tokio::test]
+    async fn should_list_children_and_block_completion() {
+        let app = TestApp::new();
+        let (user_id, authorization) = app.login("test_user").await;
+
+        app.todo_repository
+            .create(user_id, CreateTodo::new("parent".to_string(), vec![]))
             .await
             .unwrap();
-        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        let req = Request::builder()
+            .uri("/todos")
+            .method(Method::POST)
+            .header(header::AUTHORIZATION, &authorization)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(
+                r#"{ "text": "child", "labels": [], "parent_id": 1 }"#,
+            ))
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let child = res_to_todo(res).await;
+        assert_eq!(child.parent_id(), Some(1));
+
+        let req = Request::builder()
+            .uri("/todos/1/children")
+            .method(Method::GET)
+            .header(header::AUTHORIZATION, &authorization)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res_to_string(res).await;
+        let children: Vec<Todo> = serde_json::from_str(&body).unwrap();
+        assert_eq!(children, vec![child]);
+
+        let mut res = None;
+        for status in ["in_progress", "review", "done"] {
+            let req = Request::builder()
+                .uri("/todos/1/move")
+                .method(Method::POST)
+                .header(header::AUTHORIZATION, &authorization)
+                .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(format!(r#"{{ "status": "{status}" }}"#)))
+                .unwrap();
+            res = Some(app.request(req).await);
+        }
+        let res = res.unwrap();
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        let problem = res_to_problem(res).await;
+        assert_eq!(problem["type"], "/problems/open-subtasks");
+        assert_eq!(problem["id"], 1);
+    }
+
+    #[tokio::test]
+    async fn should_add_dependency_and_reject_cycle() {
+        let app = TestApp::new();
+        let (user_id, authorization) = app.login("test_user").await;
+
+        for text in ["blocked", "blocker"] {
+            app.todo_repository
+                .create(user_id, CreateTodo::new(text.to_string(), vec![]))
+                .await
+                .unwrap();
+        }
+
+        let req = Request::builder()
+            .uri("/todos/1/dependencies")
+            .method(Method::POST)
+            .header(header::AUTHORIZATION, &authorization)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(r#"{ "blocked_by": 2 }"#))
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let todo = res_to_todo(res).await;
+        assert!(todo.is_blocked());
+        let body = serde_json::to_value(&todo).unwrap();
+        assert_eq!(body["blocked"], true);
+
+        let req = Request::builder()
+            .uri("/todos/2/dependencies")
+            .method(Method::POST)
+            .header(header::AUTHORIZATION, &authorization)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(r#"{ "blocked_by": 1 }"#))
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        let problem = res_to_problem(res).await;
+        assert_eq!(problem["type"], "/problems/dependency-cycle");
+        assert_eq!(problem["id"], 2);
+    }
+
+    #[tokio::test]
+    async fn should_handle_comments() {
+        let app = TestApp::new();
+        let (user_id, authorization) = app.login("test_user").await;
+        let (_, other) = app.login("other_user").await;
+        app.todo_repository
+            .create(
+                user_id,
+                CreateTodo::new("should_handle_comments".to_string(), vec![]),
+            )
+            .await
+            .unwrap();
+
+        let req = Request::builder()
+            .uri("/todos/1/comments")
+            .method(Method::POST)
+            .header(header::AUTHORIZATION, &authorization)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(r#"{ "body": "first comment" }"#))
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let comment: serde_json::Value = serde_json::from_str(&res_to_string(res).await).unwrap();
+        assert_eq!(comment["id"], 1);
+        assert_eq!(comment["todo_id"], 1);
+        assert_eq!(comment["author_id"], user_id);
+        assert_eq!(comment["body"], "first comment");
+
+        let req = Request::builder()
+            .uri("/todos/1/comments/1")
+            .method(Method::PATCH)
+            .header(header::AUTHORIZATION, &authorization)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(r#"{ "body": "edited comment" }"#))
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let req = Request::builder()
+            .uri("/todos/1/comments")
+            .method(Method::GET)
+            .header(header::AUTHORIZATION, &authorization)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let comments: serde_json::Value = serde_json::from_str(&res_to_string(res).await).unwrap();
+        assert_eq!(comments.as_array().unwrap().len(), 1);
+        assert_eq!(comments[0]["body"], "edited comment");
+
+        let req = Request::builder()
+            .uri("/todos/1")
+            .method(Method::GET)
+            .header(header::AUTHORIZATION, &authorization)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.request(req).await;
+        let todo = res_to_todo(res).await;
+        assert_eq!(todo.comment_count(), 1);
+
+        // 空のコメントは投稿できない
+        let req = Request::builder()
+            .uri("/todos/1/comments")
+            .method(Method::POST)
+            .header(header::AUTHORIZATION, &authorization)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(r#"{ "body": "  " }"#))
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        // 他のユーザーからはTodoもコメントも見えない
+        for (method, uri) in [
+            (Method::GET, "/todos/1/comments"),
+            (Method::DELETE, "/todos/1/comments/1"),
+        ] {
+            let req = Request::builder()
+                .uri(uri)
+                .method(method)
+                .header(header::AUTHORIZATION, &other)
+                .body(Body::empty())
+                .unwrap();
+            let res = app.request(req).await;
+            assert_eq!(res.status(), StatusCode::NOT_FOUND, "uri: {uri}");
+        }
+
+        let req = Request::builder()
+            .uri("/todos/1/comments/1")
+            .method(Method::DELETE)
+            .header(header::AUTHORIZATION, &authorization)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+
+    /// `file`フィールドにファイルを1つ含む`multipart/form-data`のリクエスト
+    fn upload_request(
+        uri: &str,
+        authorization: &str,
+        filename: &str,
+        data: &[u8],
+    ) -> Request<Body> {
+        let mut body = format!(
+            "--boundary\r\nContent-Disposition: form-data; name=\"file\"; filename=\"{filename}\"\r\nContent-Type: text/plain\r\n\r\n"
+        )
+        .into_bytes();
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\r\n--boundary--\r\n");
+        Request::builder()
+            .uri(uri)
+            .method(Method::POST)
+            .header(header::AUTHORIZATION, authorization)
+            .header(
+                header::CONTENT_TYPE,
+                "multipart/form-data; boundary=boundary",
+            )
+            .body(Body::from(body))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn should_upload_and_download_attachment() {
+        let app = TestApp::new();
+        let (user_id, authorization) = app.login("test_user").await;
+        let (_, other) = app.login("other_user").await;
+        app.todo_repository
+            .create(
+                user_id,
+                CreateTodo::new("should_upload".to_string(), vec![]),
+            )
+            .await
+            .unwrap();
+
+        let req = upload_request("/todos/1/attachments", &authorization, "メモ.txt", b"hello");
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let attachment: serde_json::Value =
+            serde_json::from_str(&res_to_string(res).await).unwrap();
+        assert_eq!(attachment["todo_id"], 1);
+        assert_eq!(attachment["filename"], "メモ.txt");
+        assert_eq!(attachment["content_type"], "text/plain");
+        assert_eq!(attachment["size"], 5);
+        assert_eq!(
+            attachment["digest"],
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+        );
+
+        let req = Request::builder()
+            .uri(format!("/attachments/{}", attachment["id"]))
+            .method(Method::GET)
+            .header(header::AUTHORIZATION, &authorization)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "text/plain");
+        assert_eq!(
+            res.headers()[header::CONTENT_DISPOSITION],
+            "attachment; filename*=UTF-8''%E3%83%A1%E3%83%A2.txt"
+        );
+        assert_eq!(res_to_string(res).await, "hello");
+
+        // 上限を超えるファイルは保存しない
+        let data = vec![b'a'; TestApp::MAX_ATTACHMENT_SIZE + 1];
+        let req = upload_request("/todos/1/attachments", &authorization, "large.txt", &data);
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let problem = res_to_problem(res).await;
+        assert_eq!(problem["type"], "/problems/payload-too-large");
+
+        // 他のユーザーのTodoには添付できず、添付ファイルもダウンロードできない
+        let req = upload_request("/todos/1/attachments", &other, "other.txt", b"other");
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        let req = Request::builder()
+            .uri(format!("/attachments/{}", attachment["id"]))
+            .method(Method::GET)
+            .header(header::AUTHORIZATION, &other)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        // `file`フィールドがなければ400にする
+        let req = Request::builder()
+            .uri("/todos/1/attachments")
+            .method(Method::POST)
+            .header(header::AUTHORIZATION, &authorization)
+            .header(
+                header::CONTENT_TYPE,
+                "multipart/form-data; boundary=boundary",
+            )
+            .body(Body::from("--boundary--\r\n"))
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn should_delete_todo() {
+        let app = TestApp::new();
+        let (user_id, authorization) = app.login("test_user").await;
+
+        app.todo_repository
+            .create(
+                user_id,
+                CreateTodo::new("should_delete_todo".to_string(), vec![]),
+            )
+            .await
+            .unwrap();
+
+        let req = Request::builder()
+            .uri("/todos/1")
+            .method(Method::DELETE)
+            .header(header::AUTHORIZATION, &authorization)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn should_not_access_other_users_todo() {
+        let app = TestApp::new();
+        let (owner, _) = app.login("owner").await;
+        let (_, authorization) = app.login("other").await;
+
+        app.todo_repository
+            .create(
+                owner,
+                CreateTodo::new("should_not_access_other_users_todo".to_string(), vec![]),
+            )
+            .await
+            .unwrap();
+
+        let req = Request::builder()
+            .uri("/todos")
+            .method(Method::GET)
+            .header(header::AUTHORIZATION, &authorization)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = res_to_string(res).await;
+        let todos: Vec<Todo> = serde_json::from_str(&body)
+            .unwrap_or_else(|_| panic!("cannot convert Todo instance. body: {body}"));
+        assert!(todos.is_empty());
+
+        for method in [Method::GET, Method::DELETE] {
+            let req = Request::builder()
+                .uri("/todos/1")
+                .method(method.clone())
+                .header(header::AUTHORIZATION, &authorization)
+                .body(Body::empty())
+                .unwrap();
+            let res = app.request(req).await;
+            assert_eq!(res.status(), StatusCode::NOT_FOUND, "{method} /todos/1");
+        }
+
+        let req = Request::builder()
+            .uri("/todos/1")
+            .method(Method::PATCH)
+            .header(header::AUTHORIZATION, &authorization)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(r#"{ "status": "in_progress" }"#))
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn should_return_problem_for_missing_todo() {
+        let app = TestApp::new();
+        let (_, authorization) = app.login("test_user").await;
+
+        let req = Request::builder()
+            .uri("/todos/99")
+            .method(Method::GET)
+            .header(header::AUTHORIZATION, &authorization)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+        let problem = res_to_problem(res).await;
+        assert_eq!(problem["type"], "/problems/not-found");
+        assert_eq!(problem["id"], 99);
+    }
+
+    #[tokio::test]
+    async fn should_return_problem_for_invalid_json() {
+        let app = TestApp::new();
+        let (_, authorization) = app.login("test_user").await;
+
+        for (body, status) in [
+            (r#"{ "text": "#, StatusCode::BAD_REQUEST),
+            (r#"{ "txt": "typo" }"#, StatusCode::UNPROCESSABLE_ENTITY),
+        ] {
+            let req = Request::builder()
+                .uri("/todos")
+                .method(Method::POST)
+                .header(header::AUTHORIZATION, &authorization)
+                .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(body))
+                .unwrap();
+            let res = app.request(req).await;
+            assert_eq!(res.status(), status, "body: {body}");
+
+            let problem = res_to_problem(res).await;
+            assert_eq!(problem["type"], "/problems/invalid-json");
+            assert!(problem["detail"].is_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn should_return_problem_for_invalid_path() {
+        let app = TestApp::new();
+        let (_, authorization) = app.login("test_user").await;
+
+        let req = Request::builder()
+            .uri("/todos/abc")
+            .method(Method::GET)
+            .header(header::AUTHORIZATION, &authorization)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+        let problem = res_to_problem(res).await;
+        assert_eq!(problem["type"], "/problems/invalid-path");
+    }
+
+    #[tokio::test]
+    async fn should_reject_unknown_labels() {
+        let app = TestApp::new();
+        let (_, authorization) = app.login("test_user").await;
+
+        let req = Request::builder()
+            .uri("/todos")
+            .method(Method::POST)
+            .header(header::AUTHORIZATION, &authorization)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(
+                r#"{ "text": "unknown labels", "labels": [99, 98] }"#,
+            ))
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let problem = res_to_problem(res).await;
+        assert_eq!(problem["type"], "/problems/unknown-labels");
+        assert_eq!(
+            problem["invalid-params"],
+            serde_json::json!([
+                { "name": "labels", "reason": "label 98 does not exist" },
+                { "name": "labels", "reason": "label 99 does not exist" },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn should_reject_invalid_todo() {
+        let app = TestApp::new();
+        let (_, authorization) = app.login("test_user").await;
+
+        let too_long = "a".repeat(101);
+        for text in ["", "   ", too_long.as_str()] {
+            let req = Request::builder()
+                .uri("/todos")
+                .method(Method::POST)
+                .header(header::AUTHORIZATION, &authorization)
+                .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(serde_json::json!({ "text": text }).to_string()))
+                .unwrap();
+            let res = app.request(req).await;
+            assert_eq!(
+                res.status(),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "text: {text:?}"
+            );
+
+            let problem = res_to_problem(res).await;
+            assert_eq!(problem["type"], "/problems/validation");
+            assert_eq!(problem["invalid-params"][0]["name"], "text");
+        }
+
+        let req = Request::builder()
+            .uri("/todos")
+            .method(Method::GET)
+            .header(header::AUTHORIZATION, &authorization)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.request(req).await;
+        let body = res_to_string(res).await;
+        assert_eq!(body, "[]", "invalid todo must not be created");
+    }
+
+    #[tokio::test]
+    async fn should_reject_invalid_todo_update() {
+        let app = TestApp::new();
+        let (user_id, authorization) = app.login("test_user").await;
+
+        app.todo_repository
+            .create(
+                user_id,
+                CreateTodo::new("should_reject_invalid_todo_update".to_string(), vec![]),
+            )
+            .await
+            .unwrap();
+
+        let req = Request::builder()
+            .uri("/todos/1")
+            .method(Method::PATCH)
+            .header(header::AUTHORIZATION, &authorization)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(r#"{ "text": " ", "status": "in_progress" }"#))
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let todo = app.todo_repository.find(user_id, 1).await.unwrap();
+        let expected = Todo::new(1, "should_reject_invalid_todo_update".to_string(), vec![]);
+        assert_eq!(todo, expected);
+    }
+
+    #[tokio::test]
+    async fn should_validate_recurrence() {
+        let app = TestApp::new();
+        let (_, authorization) = app.login("test_user").await;
+
+        let req = Request::builder()
+            .uri("/todos")
+            .method(Method::POST)
+            .header(header::AUTHORIZATION, &authorization)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(
+                r#"{
+                    "text": "recurring",
+                    "recurrence": { "freq": "weekly", "by_weekday": ["mon", "thu"] }
+                }"#,
+            ))
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let body: serde_json::Value = serde_json::from_str(&res_to_string(res).await).unwrap();
+        assert_eq!(
+            body["recurrence"],
+            serde_json::json!({ "freq": "weekly", "interval": 1, "by_weekday": ["Mon", "Thu"] })
+        );
+
+        for (method, uri) in [(Method::POST, "/todos"), (Method::PATCH, "/todos/1")] {
+            for recurrence in [
+                serde_json::json!({ "freq": "daily", "interval": 0 }),
+                serde_json::json!({ "freq": "monthly", "by_weekday": ["mon"] }),
+            ] {
+                let req = Request::builder()
+                    .uri(uri)
+                    .method(method.clone())
+                    .header(header::AUTHORIZATION, &authorization)
+                    .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(
+                        serde_json::json!({ "text": "recurring", "recurrence": recurrence })
+                            .to_string(),
+                    ))
+                    .unwrap();
+                let res = app.request(req).await;
+                assert_eq!(
+                    res.status(),
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "{method} {recurrence}"
+                );
+
+                let problem = res_to_problem(res).await;
+                assert_eq!(problem["invalid-params"][0]["name"], "recurrence");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn should_reject_invalid_label() {
+        let app = TestApp::new();
+        let (_, authorization) = app.login("test_user").await;
+
+        let too_long = "a".repeat(51);
+        for name in ["", "\t", too_long.as_str()] {
+            let req = Request::builder()
+                .uri("/labels")
+                .method(Method::POST)
+                .header(header::AUTHORIZATION, &authorization)
+                .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(serde_json::json!({ "name": name }).to_string()))
+                .unwrap();
+            let res = app.request(req).await;
+            assert_eq!(
+                res.status(),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "name: {name:?}"
+            );
+
+            let problem = res_to_problem(res).await;
+            assert_eq!(problem["invalid-params"][0]["name"], "name");
+        }
+    }
+
+    #[tokio::test]
+    async fn should_manage_label() {
+        let app = TestApp::new();
+        let (_, authorization) = app.login("test_user").await;
+        let json_request = |method: Method, uri: &str, body: serde_json::Value| {
+            Request::builder()
+                .uri(uri)
+                .method(method)
+                .header(header::AUTHORIZATION, &authorization)
+                .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+
+        for name in ["bug", "feature"] {
+            let req = json_request(
+                Method::POST,
+                "/labels",
+                serde_json::json!({ "name": name, "color": "#d73a4a" }),
+            );
+            let res = app.request(req).await;
+            assert_eq!(res.status(), StatusCode::CREATED);
+        }
+
+        let req = json_request(
+            Method::PATCH,
+            "/labels/1",
+            serde_json::json!({ "name": "defect", "color": null }),
+        );
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let label: serde_json::Value = serde_json::from_str(&res_to_string(res).await).unwrap();
+        assert_eq!(
+            label,
+            serde_json::json!({ "id": 1, "name": "defect", "color": null, "parent_id": null })
+        );
+
+        for color in ["red", "#12345", "#12345g", "1e90ff"] {
+            let req = json_request(
+                Method::PATCH,
+                "/labels/1",
+                serde_json::json!({ "color": color }),
+            );
+            let res = app.request(req).await;
+            assert_eq!(
+                res.status(),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "color: {color}"
+            );
+            let problem = res_to_problem(res).await;
+            assert_eq!(problem["invalid-params"][0]["name"], "color");
+        }
+
+        let req = json_request(
+            Method::PATCH,
+            "/labels/1",
+            serde_json::json!({ "name": "feature" }),
+        );
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let problem = res_to_problem(res).await;
+        assert_eq!(problem["type"], "/problems/duplicate");
+        assert_eq!(problem["id"], 2);
+
+        let req = Request::builder()
+            .uri("/labels/1")
+            .method(Method::DELETE)
+            .header(header::AUTHORIZATION, &authorization)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        let req = Request::builder()
+            .uri("/labels/1")
+            .method(Method::GET)
+            .header(header::AUTHORIZATION, &authorization)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn should_refuse_deleting_label_in_use() {
+        let app = TestApp::new();
+        let (user_id, authorization) = app.login("test_user").await;
+        let label = app
+            .label_repository
+            .create(user_id, CreateLabel::new("bug".to_string()))
+            .await
+            .unwrap();
+        app.todo_repository
+            .create(
+                user_id,
+                CreateTodo::new("fix".to_string(), vec![label.id()]),
+            )
+            .await
+            .unwrap();
+        let request = |method: Method, uri: &str| {
+            Request::builder()
+                .uri(uri)
+                .method(method)
+                .header(header::AUTHORIZATION, &authorization)
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let res = app
+            .request(request(Method::GET, "/labels?usage=true"))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let labels: serde_json::Value = serde_json::from_str(&res_to_string(res).await).unwrap();
+        assert_eq!(
+            labels,
+            serde_json::json!([{
+                "id": label.id(),
+                "name": "bug",
+                "color": null,
+                "parent_id": null,
+                "open_todos": 1,
+                "closed_todos": 0,
+            }])
+        );
+
+        let res = app.request(request(Method::DELETE, "/labels/1")).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        let problem = res_to_problem(res).await;
+        assert_eq!(problem["type"], "/problems/label-in-use");
+        assert_eq!(problem["id"], 1);
+
+        let res = app
+            .request(request(Method::DELETE, "/labels/1?force=true"))
+            .await;
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+        let res = app.request(request(Method::GET, "/todos/1")).await;
+        let todo = res_to_todo(res).await;
+        assert_eq!(todo, Todo::new(1, "fix".to_string(), vec![]));
+    }
+
+    #[tokio::test]
+    async fn should_merge_labels() {
+        let app = TestApp::new();
+        let (user_id, authorization) = app.login("test_user").await;
+        let mut labels = Vec::new();
+        for name in ["bug", "bugs"] {
+            let label = app
+                .label_repository
+                .create(user_id, CreateLabel::new(name.to_string()))
+                .await
+                .unwrap();
+            labels.push(label);
+        }
+        app.todo_repository
+            .create(
+                user_id,
+                CreateTodo::new("fix".to_string(), vec![labels[1].id()]),
+            )
+            .await
+            .unwrap();
+        let merge = |uri: &str, source_id: i32| {
+            Request::builder()
+                .uri(uri)
+                .method(Method::POST)
+                .header(header::AUTHORIZATION, &authorization)
+                .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::json!({ "source_id": source_id }).to_string(),
+                ))
+                .unwrap()
+        };
+
+        let res = app.request(merge("/labels/1/merge", 1)).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        let problem = res_to_problem(res).await;
+        assert_eq!(problem["type"], "/problems/merge-into-self");
+
+        let res = app.request(merge("/labels/1/merge", 2)).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let label: serde_json::Value = serde_json::from_str(&res_to_string(res).await).unwrap();
+        assert_eq!(
+            label,
+            serde_json::json!({ "id": 1, "name": "bug", "color": null, "parent_id": null })
+        );
+        let todo = app.todo_repository.find(user_id, 1).await.unwrap();
+        assert_eq!(
+            todo,
+            Todo::new(1, "fix".to_string(), vec![labels[0].clone()])
+        );
+
+        let res = app.request(merge("/labels/1/merge", 2)).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn should_list_label_subtree() {
+        let app = TestApp::new();
+        let (user_id, authorization) = app.login("test_user").await;
+        let area = app
+            .label_repository
+            .create(user_id, CreateLabel::new("area".to_string()))
+            .await
+            .unwrap();
+        app.label_repository
+            .create(
+                user_id,
+                CreateLabel::new("area/backend".to_string()).with_parent(area.id()),
+            )
+            .await
+            .unwrap();
+        let request = |method: Method, uri: &str, body: Body| {
+            Request::builder()
+                .uri(uri)
+                .method(method)
+                .header(header::AUTHORIZATION, &authorization)
+                .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(body)
+                .unwrap()
+        };
+
+        let res = app
+            .request(request(Method::GET, "/labels/1/subtree", Body::empty()))
+            .await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let labels: serde_json::Value = serde_json::from_str(&res_to_string(res).await).unwrap();
+        assert_eq!(
+            labels,
+            serde_json::json!([
+                { "id": 1, "name": "area", "color": null, "parent_id": null },
+                { "id": 2, "name": "area/backend", "color": null, "parent_id": 1 },
+            ])
+        );
+
+        let body = Body::from(serde_json::json!({ "parent_id": 2 }).to_string());
+        let res = app.request(request(Method::PATCH, "/labels/1", body)).await;
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+        let problem = res_to_problem(res).await;
+        assert_eq!(problem["type"], "/problems/label-cycle");
+        assert_eq!(problem["id"], 1);
+
+        let body = Body::from(serde_json::json!({ "name": "area/missing/db" }).to_string());
+        let res = app.request(request(Method::POST, "/labels", body)).await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let problem = res_to_problem(res).await;
+        assert_eq!(problem["type"], "/problems/invalid-label-path");
+    }
+
+    #[tokio::test]
+    async fn should_paginate_todos() {
+        let app = TestApp::new();
+        let (user_id, authorization) = app.login("test_user").await;
+
+        for text in ["first", "second", "third"] {
+            app.todo_repository
+                .create(user_id, CreateTodo::new(text.to_string(), vec![]))
+                .await
+                .unwrap();
+        }
+
+        let req = Request::builder()
+            .uri("/todos?order=asc&limit=2")
+            .method(Method::GET)
+            .header(header::AUTHORIZATION, &authorization)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(
+            res.headers()[header::LINK],
+            r#"</todos?order=asc&limit=2&offset=2>; rel="next""#
+        );
+        let body = res_to_string(res).await;
+        let todos: Vec<Todo> = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            todos,
+            vec![
+                Todo::new(1, "first".to_string(), vec![]),
+                Todo::new(2, "second".to_string(), vec![]),
+            ]
+        );
+
+        let req = Request::builder()
+            .uri("/todos?order=asc&limit=2&offset=2")
+            .method(Method::GET)
+            .header(header::AUTHORIZATION, &authorization)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(res.headers().get(header::LINK).is_none());
+        let body = res_to_string(res).await;
+        let todos: Vec<Todo> = serde_json::from_str(&body).unwrap();
+        assert_eq!(todos, vec![Todo::new(3, "third".to_string(), vec![])]);
+    }
+
+    #[tokio::test]
+    async fn should_handle_due_dates() {
+        let app = TestApp::new();
+        let (_, authorization) = app.login("test_user").await;
+
+        let req = Request::builder()
+            .uri("/todos")
+            .method(Method::POST)
+            .header(header::AUTHORIZATION, &authorization)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(
+                r#"{ "text": "should_handle_due_dates", "due_at": "2000-01-01T09:00:00+09:00" }"#,
+            ))
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let body: serde_json::Value = serde_json::from_str(&res_to_string(res).await).unwrap();
+        assert_eq!(body["due_at"], "2000-01-01T00:00:00Z");
+
+        let all = |query: &str| {
+            let req = Request::builder()
+                .uri(format!("/todos?{query}"))
+                .method(Method::GET)
+                .header(header::AUTHORIZATION, &authorization)
+                .body(Body::empty())
+                .unwrap();
+            async {
+                let res = app.request(req).await;
+                assert_eq!(res.status(), StatusCode::OK);
+                let todos: Vec<Todo> = serde_json::from_str(&res_to_string(res).await).unwrap();
+                todos.len()
+            }
+        };
+        assert_eq!(all("overdue=true").await, 1);
+        assert_eq!(all("overdue=false").await, 0);
+        assert_eq!(all("due_before=2000-01-01T09:00:01%2B09:00").await, 1);
+        assert_eq!(all("due_before=2000-01-01T09:00:00%2B09:00").await, 0);
+
+        let req = Request::builder()
+            .uri("/todos/1")
+            .method(Method::PATCH)
+            .header(header::AUTHORIZATION, &authorization)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(r#"{ "due_at": null }"#))
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::CREATED);
+        let body: serde_json::Value = serde_json::from_str(&res_to_string(res).await).unwrap();
+        assert_eq!(body["due_at"], serde_json::Value::Null);
+        assert_eq!(all("overdue=true").await, 0);
+    }
+
+    #[tokio::test]
+    async fn should_sort_todos_by_priority_then_due_date() {
+        let app = TestApp::new();
+        let (_, authorization) = app.login("test_user").await;
+
+        for body in [
+            r#"{ "text": "later", "priority": "p1", "due_at": "2030-01-02T00:00:00Z" }"#,
+            r#"{ "text": "undated", "priority": "p0", "estimate": 3 }"#,
+            r#"{ "text": "sooner", "priority": "p1", "due_at": "2030-01-01T00:00:00Z" }"#,
+            r#"{ "text": "normal" }"#,
+        ] {
+            let req = Request::builder()
+                .uri("/todos")
+                .method(Method::POST)
+                .header(header::AUTHORIZATION, &authorization)
+                .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(body))
+                .unwrap();
+            let res = app.request(req).await;
+            assert_eq!(res.status(), StatusCode::CREATED, "body: {body}");
+        }
+
+        let req = Request::builder()
+            .uri("/todos?sort=priority,due_at")
+            .method(Method::GET)
+            .header(header::AUTHORIZATION, &authorization)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_str(&res_to_string(res).await).unwrap();
+        let todos: Vec<(&str, &str)> = body
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|todo| {
+                (
+                    todo["text"].as_str().unwrap(),
+                    todo["priority"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            todos,
+            vec![
+                ("undated", "p0"),
+                ("sooner", "p1"),
+                ("later", "p1"),
+                ("normal", "p2"),
+            ]
+        );
+
+        for body in [
+            r#"{ "text": "invalid", "estimate": 0 }"#,
+            r#"{ "text": "invalid", "priority": "p4" }"#,
+        ] {
+            let req = Request::builder()
+                .uri("/todos")
+                .method(Method::POST)
+                .header(header::AUTHORIZATION, &authorization)
+                .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(body))
+                .unwrap();
+            let res = app.request(req).await;
+            assert_eq!(
+                res.status(),
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "body: {body}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn should_reject_invalid_todo_query() {
+        let app = TestApp::new();
+        let (_, authorization) = app.login("test_user").await;
+
+        for (query, status, kind) in [
+            (
+                "status=finished",
+                StatusCode::BAD_REQUEST,
+                "/problems/invalid-query",
+            ),
+            (
+                "labels=1,x",
+                StatusCode::BAD_REQUEST,
+                "/problems/invalid-query",
+            ),
+            (
+                "sort=unknown",
+                StatusCode::BAD_REQUEST,
+                "/problems/invalid-query",
+            ),
+            (
+                "sort=priority,unknown",
+                StatusCode::BAD_REQUEST,
+                "/problems/invalid-query",
+            ),
+            (
+                "limit=0",
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "/problems/validation",
+            ),
+        ] {
+            let req = Request::builder()
+                .uri(format!("/todos?{query}"))
+                .method(Method::GET)
+                .header(header::AUTHORIZATION, &authorization)
+                .body(Body::empty())
+                .unwrap();
+            let res = app.request(req).await;
+            assert_eq!(res.status(), status, "query: {query}");
+
+            let problem = res_to_problem(res).await;
+            assert_eq!(problem["type"], kind, "query: {query}");
+        }
+    }
+
+    #[tokio::test]
+    async fn should_search_todos() {
+        let app = TestApp::new();
+        let (user_id, authorization) = app.login("test_user").await;
+
+        for text in ["Buy milk", "bread"] {
+            app.todo_repository
+                .create(user_id, CreateTodo::new(text.to_string(), vec![]))
+                .await
+                .unwrap();
+        }
+
+        let req = Request::builder()
+            .uri("/todos/search?q=milk")
+            .method(Method::GET)
+            .header(header::AUTHORIZATION, &authorization)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let body = res_to_string(res).await;
+        let hits: Vec<TodoSearchHit> = serde_json::from_str(&body)
+            .unwrap_or_else(|_| panic!("cannot convert TodoSearchHit instance. body: {body}"));
+        assert_eq!(hits.len(), 1);
+        assert_eq!(
+            hits[0].todo(),
+            &Todo::new(1, "Buy milk".to_string(), vec![])
+        );
+        assert_eq!(hits[0].snippet(), "Buy <mark>milk</mark>");
+
+        let req = Request::builder()
+            .uri("/todos/search?q=%20")
+            .method(Method::GET)
+            .header(header::AUTHORIZATION, &authorization)
+            .body(Body::empty())
+            .unwrap();
+        let res = app.request(req).await;
+        assert_eq!(res.status(), StatusCode::UNPROCESSABLE_ENTITY);
     }
 }
@@ -0,0 +1,138 @@
+use std::sync::Arc;
+
+use axum::{
+    http::{header, HeaderValue, StatusCode},
+    response::IntoResponse,
+    Extension,
+};
+
+use crate::{
+    attachment::{AttachmentStore, Attachments},
+    repository::{
+        attachment::{AttachmentRepository, CreateAttachment},
+        todo::TodoRepository,
+    },
+};
+
+use super::{
+    extract::{Json, Multipart, Path},
+    ApiError, AuthUser,
+};
+
+/// ファイル名の長さの上限 (文字数)
+const MAX_FILENAME_LENGTH: usize = 255;
+
+/// `file`フィールドのファイルを添付する。上限を超えるファイルは読み切る前に413にする
+pub async fn create_attachment<T: TodoRepository, R: AttachmentRepository, S: AttachmentStore>(
+    user: AuthUser,
+    Path(todo_id): Path<u32>,
+    Extension(todos): Extension<Arc<T>>,
+    Extension(repository): Extension<Arc<R>>,
+    Extension(attachments): Extension<Arc<Attachments<S>>>,
+    Multipart(mut multipart): Multipart,
+) -> Result<impl IntoResponse, ApiError> {
+    // 添付できないTodoのファイルは受け取らない
+    todos.find(user.id(), todo_id).await?;
+
+    let mut field = loop {
+        match multipart.next_field().await? {
+            Some(field) if field.name() == Some("file") => break field,
+            Some(_) => continue,
+            None => return Err(ApiError::invalid_attachment("missing `file` field")),
+        }
+    };
+    let filename = field
+        .file_name()
+        .map(file_basename)
+        .filter(|filename| !filename.is_empty())
+        .ok_or_else(|| ApiError::invalid_attachment("missing file name"))?
+        .to_string();
+    if filename.chars().count() > MAX_FILENAME_LENGTH {
+        return Err(ApiError::invalid_attachment("too long file name"));
+    }
+    let content_type = field
+        .content_type()
+        .and_then(|content_type| content_type.parse::<mime::Mime>().ok())
+        .unwrap_or(mime::APPLICATION_OCTET_STREAM)
+        .to_string();
+
+    let mut data = Vec::new();
+    while let Some(chunk) = field.chunk().await? {
+        if data.len() + chunk.len() > attachments.max_size() {
+            return Err(ApiError::payload_too_large(attachments.max_size()));
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    let digest = attachments
+        .store()
+        .put(&data)
+        .await
+        .map_err(handle_store_error)?;
+    let attachment = repository
+        .create(
+            user.id(),
+            todo_id,
+            CreateAttachment::new(filename, content_type, data.len() as i64, digest),
+        )
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(attachment)))
+}
+
+/// 保存したときの`Content-Type`で返す。ブラウザーで開かれないよう、`Content-Disposition`は`attachment`にする
+pub async fn find_attachment<R: AttachmentRepository, S: AttachmentStore>(
+    user: AuthUser,
+    Path(id): Path<i32>,
+    Extension(repository): Extension<Arc<R>>,
+    Extension(attachments): Extension<Arc<Attachments<S>>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let attachment = repository.find(user.id(), id).await?;
+    let data = attachments
+        .store()
+        .get(attachment.digest())
+        .await
+        .map_err(handle_store_error)?;
+
+    let content_type = HeaderValue::from_str(attachment.content_type())
+        .unwrap_or(HeaderValue::from_static("application/octet-stream"));
+    let content_disposition = HeaderValue::try_from(content_disposition(attachment.filename()))
+        .map_err(|_| ApiError::internal())?;
+    let headers = [
+        (header::CONTENT_TYPE, content_type),
+        (header::CONTENT_DISPOSITION, content_disposition),
+        (
+            header::X_CONTENT_TYPE_OPTIONS,
+            HeaderValue::from_static("nosniff"),
+        ),
+    ];
+
+    Ok((StatusCode::OK, headers, data))
+}
+
+/// ブラウザーによってはパスを含めて送るため、最後の要素だけをファイル名にする
+fn file_basename(filename: &str) -> &str {
+    filename
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or_default()
+        .trim()
+}
+
+/// ファイル名はRFC 5987の形式でパーセントエンコードする
+fn content_disposition(filename: &str) -> String {
+    let mut encoded = String::with_capacity(filename.len());
+    for byte in filename.bytes() {
+        if byte.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    format!("attachment; filename*=UTF-8''{encoded}")
+}
+
+fn handle_store_error(error: anyhow::Error) -> ApiError {
+    tracing::error!("unexpected attachment store error: {error:#}");
+    ApiError::internal()
+}
@@ -0,0 +1,90 @@
+use std::sync::Arc;
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderMap, StatusCode},
+    response::IntoResponse,
+    Extension,
+};
+
+use crate::repository::{
+    session::SessionRepository,
+    user::{Credentials, UserRepository},
+    RepositoryError,
+};
+
+use super::{extract::Json, ApiError};
+
+/// 抽出時にリポジトリの型を知る必要がないよう、セッションは型消去して共有する
+pub type SessionStore = Arc<dyn SessionRepository>;
+
+/// `Authorization: Bearer <token>` ヘッダーで認証されたユーザー
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthUser {
+    id: i32,
+}
+
+impl AuthUser {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Extension(sessions) = Extension::<SessionStore>::from_request_parts(parts, state)
+            .await
+            .map_err(|rejection| {
+                tracing::error!("session store is not configured: {rejection}");
+                ApiError::internal()
+            })?;
+
+        let token = bearer_token(&parts.headers)?;
+        let session = sessions.find(token).await.map_err(invalid_session)?;
+
+        Ok(Self {
+            id: session.user_id(),
+        })
+    }
+}
+
+pub async fn login<R: UserRepository>(
+    Extension(repository): Extension<Arc<R>>,
+    Extension(sessions): Extension<SessionStore>,
+    Json(payload): Json<Credentials>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = repository.authenticate(payload).await?;
+    let session = sessions.create(user.id()).await?;
+
+    Ok((StatusCode::OK, Json(session)))
+}
+
+/// リクエストのセッションを削除する。以降は同じトークンで認証できない
+pub async fn logout(
+    Extension(sessions): Extension<SessionStore>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    let token = bearer_token(&headers)?;
+    sessions.delete(token).await.map_err(invalid_session)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn bearer_token(headers: &HeaderMap) -> Result<&str, ApiError> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(|| ApiError::unauthorized("missing bearer token"))
+}
+
+fn invalid_session(error: RepositoryError) -> ApiError {
+    match error {
+        RepositoryError::Unauthorized => ApiError::unauthorized("invalid session token"),
+        error => ApiError::from(error),
+    }
+}
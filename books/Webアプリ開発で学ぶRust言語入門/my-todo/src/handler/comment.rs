@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use axum::{http::StatusCode, response::IntoResponse, Extension};
+
+use crate::repository::comment::{CommentRepository, CreateComment, UpdateComment};
+
+use super::{
+    extract::{Json, Path, ValidatedJson},
+    ApiError, AuthUser,
+};
+
+pub async fn create_comment<R: CommentRepository>(
+    user: AuthUser,
+    Path(todo_id): Path<u32>,
+    Extension(repository): Extension<Arc<R>>,
+    ValidatedJson(payload): ValidatedJson<CreateComment>,
+) -> Result<impl IntoResponse, ApiError> {
+    let comment = repository.create(user.id(), todo_id, payload).await?;
+
+    Ok((StatusCode::CREATED, Json(comment)))
+}
+
+pub async fn all_comment<R: CommentRepository>(
+    user: AuthUser,
+    Path(todo_id): Path<u32>,
+    Extension(repository): Extension<Arc<R>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let comments = repository.all(user.id(), todo_id).await?;
+
+    Ok((StatusCode::OK, Json(comments)))
+}
+
+pub async fn update_comment<R: CommentRepository>(
+    user: AuthUser,
+    Path((todo_id, id)): Path<(u32, i32)>,
+    Extension(repository): Extension<Arc<R>>,
+    ValidatedJson(payload): ValidatedJson<UpdateComment>,
+) -> Result<impl IntoResponse, ApiError> {
+    let comment = repository.update(user.id(), todo_id, id, payload).await?;
+
+    Ok((StatusCode::OK, Json(comment)))
+}
+
+pub async fn delete_comment<R: CommentRepository>(
+    user: AuthUser,
+    Path((todo_id, id)): Path<(u32, i32)>,
+    Extension(repository): Extension<Arc<R>>,
+) -> Result<impl IntoResponse, ApiError> {
+    repository.delete(user.id(), todo_id, id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
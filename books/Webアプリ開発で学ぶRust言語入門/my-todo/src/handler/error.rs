@@ -0,0 +1,338 @@
+//! RFC 7807 (`application/problem+json`) 形式のエラーレスポンス
+
+use axum::{
+    extract::{
+        multipart::{MultipartError, MultipartRejection},
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+    },
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::{Serialize, Serializer};
+use validator::ValidationErrors;
+
+use crate::repository::{todo::TodoStatus, RepositoryError};
+
+const APPLICATION_PROBLEM_JSON: &str = "application/problem+json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApiError {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    title: &'static str,
+    #[serde(serialize_with = "serialize_status")]
+    status: StatusCode,
+    detail: String,
+    /// エラーの原因になったリソースのID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<i64>,
+    #[serde(rename = "invalid-params", skip_serializing_if = "Vec::is_empty")]
+    invalid_params: Vec<InvalidParam>,
+}
+
+/// 検証に失敗したフィールドとその理由
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InvalidParam {
+    name: String,
+    reason: String,
+}
+
+impl ApiError {
+    fn new(kind: &'static str, title: &'static str, status: StatusCode, detail: String) -> Self {
+        Self {
+            kind,
+            title,
+            status,
+            detail,
+            id: None,
+            invalid_params: Vec::new(),
+        }
+    }
+
+    fn with_id(mut self, id: impl Into<i64>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    pub fn not_found(id: u32) -> Self {
+        Self::new(
+            "/problems/not-found",
+            "Resource not found",
+            StatusCode::NOT_FOUND,
+            format!("resource with id {id} does not exist"),
+        )
+        .with_id(id)
+    }
+
+    pub fn duplicate(id: i32) -> Self {
+        Self::new(
+            "/problems/duplicate",
+            "Duplicate resource",
+            StatusCode::BAD_REQUEST,
+            format!("resource already exists with id {id}"),
+        )
+        .with_id(id)
+    }
+
+    pub fn invalid_transition(id: u32, from: TodoStatus, to: TodoStatus) -> Self {
+        Self::new(
+            "/problems/invalid-transition",
+            "Invalid status transition",
+            StatusCode::CONFLICT,
+            format!("status can not change from {from} to {to}"),
+        )
+        .with_id(id)
+    }
+
+    pub fn open_subtasks(id: u32) -> Self {
+        Self::new(
+            "/problems/open-subtasks",
+            "Open subtasks",
+            StatusCode::CONFLICT,
+            format!("todo {id} has subtasks that are not done"),
+        )
+        .with_id(id)
+    }
+
+    pub fn dependency_cycle(id: u32, blocked_by: u32) -> Self {
+        Self::new(
+            "/problems/dependency-cycle",
+            "Dependency cycle",
+            StatusCode::CONFLICT,
+            format!(
+                "todo {id} can not be blocked by todo {blocked_by} because it would create a cycle"
+            ),
+        )
+        .with_id(id)
+    }
+
+    pub fn label_in_use(id: i32, todos: i64) -> Self {
+        Self::new(
+            "/problems/label-in-use",
+            "Label in use",
+            StatusCode::CONFLICT,
+            format!("label {id} is attached to {todos} todos; delete with force=true to detach it"),
+        )
+        .with_id(id)
+    }
+
+    pub fn label_cycle(id: i32, parent_id: i32) -> Self {
+        Self::new(
+            "/problems/label-cycle",
+            "Label cycle",
+            StatusCode::CONFLICT,
+            format!("label {id} can not be placed under label {parent_id} because it would create a cycle"),
+        )
+        .with_id(id)
+    }
+
+    pub fn merge_into_self(id: i32) -> Self {
+        Self::new(
+            "/problems/merge-into-self",
+            "Merge into self",
+            StatusCode::CONFLICT,
+            format!("label {id} can not be merged into itself"),
+        )
+        .with_id(id)
+    }
+
+    /// 存在しないラベルはIDごとに`invalid-params`の`labels`として返す
+    pub fn unknown_labels(ids: Vec<i32>) -> Self {
+        Self {
+            invalid_params: ids
+                .iter()
+                .map(|id| InvalidParam {
+                    name: "labels".to_string(),
+                    reason: format!("label {id} does not exist"),
+                })
+                .collect(),
+            ..Self::new(
+                "/problems/unknown-labels",
+                "Unknown labels",
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("{} of the labels do not exist", ids.len()),
+            )
+        }
+    }
+
+    pub fn invalid_label_path(name: String) -> Self {
+        Self::new(
+            "/problems/invalid-label-path",
+            "Invalid label path",
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("label {name:?} must be placed under the label named by the part before the last '/'"),
+        )
+    }
+
+    pub fn payload_too_large(max_size: usize) -> Self {
+        Self::new(
+            "/problems/payload-too-large",
+            "Payload too large",
+            StatusCode::PAYLOAD_TOO_LARGE,
+            format!("file must be at most {max_size} bytes"),
+        )
+    }
+
+    pub fn invalid_attachment(detail: impl Into<String>) -> Self {
+        Self::new(
+            "/problems/invalid-attachment",
+            "Invalid attachment",
+            StatusCode::BAD_REQUEST,
+            detail.into(),
+        )
+    }
+
+    pub fn unauthorized(detail: impl Into<String>) -> Self {
+        Self::new(
+            "/problems/unauthorized",
+            "Unauthorized",
+            StatusCode::UNAUTHORIZED,
+            detail.into(),
+        )
+    }
+
+    pub fn internal() -> Self {
+        Self::new(
+            "/problems/internal",
+            "Internal server error",
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "unexpected error occurred".to_string(),
+        )
+    }
+
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+}
+
+impl From<RepositoryError> for ApiError {
+    fn from(error: RepositoryError) -> Self {
+        match error {
+            RepositoryError::NotFound(id) => Self::not_found(id),
+            RepositoryError::Duplicate(id) => Self::duplicate(id),
+            RepositoryError::Unauthorized => Self::unauthorized("invalid credentials"),
+            RepositoryError::InvalidTransition { id, from, to } => {
+                Self::invalid_transition(id, from, to)
+            }
+            RepositoryError::OpenSubtasks(id) => Self::open_subtasks(id),
+            RepositoryError::DependencyCycle { id, blocked_by } => {
+                Self::dependency_cycle(id, blocked_by)
+            }
+            RepositoryError::LabelInUse { id, todos } => Self::label_in_use(id, todos),
+            RepositoryError::LabelCycle { id, parent_id } => Self::label_cycle(id, parent_id),
+            RepositoryError::MergeIntoSelf(id) => Self::merge_into_self(id),
+            RepositoryError::UnknownLabels(ids) => Self::unknown_labels(ids),
+            RepositoryError::InvalidLabelPath(name) => Self::invalid_label_path(name),
+            RepositoryError::Unexpected(error) => {
+                tracing::error!("unexpected repository error: {error}");
+                Self::internal()
+            }
+        }
+    }
+}
+
+impl From<ValidationErrors> for ApiError {
+    fn from(errors: ValidationErrors) -> Self {
+        let mut invalid_params: Vec<InvalidParam> = errors
+            .field_errors()
+            .into_iter()
+            .flat_map(|(name, errors)| {
+                errors.iter().map(move |error| InvalidParam {
+                    name: name.to_string(),
+                    reason: error
+                        .message
+                        .as_ref()
+                        .map(|message| message.to_string())
+                        .unwrap_or_else(|| error.code.to_string()),
+                })
+            })
+            .collect();
+        invalid_params.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Self {
+            invalid_params,
+            ..Self::new(
+                "/problems/validation",
+                "Validation failed",
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "request has invalid fields".to_string(),
+            )
+        }
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::new(
+            "/problems/invalid-json",
+            "Invalid JSON body",
+            rejection.status(),
+            rejection.body_text(),
+        )
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::new(
+            "/problems/invalid-query",
+            "Invalid query string",
+            rejection.status(),
+            rejection.body_text(),
+        )
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> Self {
+        Self::new(
+            "/problems/invalid-path",
+            "Invalid path parameter",
+            rejection.status(),
+            rejection.body_text(),
+        )
+    }
+}
+
+impl From<MultipartRejection> for ApiError {
+    fn from(rejection: MultipartRejection) -> Self {
+        Self::new(
+            "/problems/invalid-multipart",
+            "Invalid multipart body",
+            rejection.status(),
+            rejection.body_text(),
+        )
+    }
+}
+
+/// ボディ全体の大きさの上限を超えた場合も413にする
+impl From<MultipartError> for ApiError {
+    fn from(error: MultipartError) -> Self {
+        let (kind, title) = match error.status() {
+            StatusCode::PAYLOAD_TOO_LARGE => ("/problems/payload-too-large", "Payload too large"),
+            _ => ("/problems/invalid-multipart", "Invalid multipart body"),
+        };
+        Self::new(kind, title, error.status(), error.body_text())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = self.status;
+        let mut response = (status, axum::Json(self)).into_response();
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(APPLICATION_PROBLEM_JSON),
+        );
+        if status == StatusCode::UNAUTHORIZED {
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+        }
+        response
+    }
+}
+
+fn serialize_status<S: Serializer>(status: &StatusCode, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u16(status.as_u16())
+}
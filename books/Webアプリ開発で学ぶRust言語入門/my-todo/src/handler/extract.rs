@@ -0,0 +1,122 @@
+//! 失敗時に`ApiError`を返すエクストラクター
+
+use axum::{
+    async_trait,
+    body::HttpBody,
+    extract::{FromRequest, FromRequestParts},
+    http::{request::Parts, Request},
+    response::{IntoResponse, Response},
+    BoxError,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use validator::Validate;
+
+use super::ApiError;
+
+/// `axum::Json`と同様に扱えるが、リクエストの解析に失敗した場合は`ApiError`を返す
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Json<T>(pub T);
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for Json<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::Json(value) = axum::Json::<T>::from_request(req, state).await?;
+        Ok(Self(value))
+    }
+}
+
+impl<T: Serialize> IntoResponse for Json<T> {
+    fn into_response(self) -> Response {
+        axum::Json(self.0).into_response()
+    }
+}
+
+/// JSONとして解析した後に検証し、検証に失敗した場合は422を返す
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidatedJson<T>(pub T);
+
+#[async_trait]
+impl<T, S, B> FromRequest<S, B> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state).await?;
+        value.validate()?;
+        Ok(Self(value))
+    }
+}
+
+/// クエリ文字列として解析した後に検証し、検証に失敗した場合は422を返す
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ValidatedQuery<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ValidatedQuery<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Query(value) =
+            axum::extract::Query::<T>::from_request_parts(parts, state).await?;
+        value.validate()?;
+        Ok(Self(value))
+    }
+}
+
+/// `axum::extract::Multipart`と同様に扱えるが、`boundary`が不正な場合は`ApiError`を返す
+#[derive(Debug)]
+pub struct Multipart(pub axum::extract::Multipart);
+
+#[async_trait]
+impl<S, B> FromRequest<S, B> for Multipart
+where
+    S: Send + Sync,
+    B: HttpBody + Send + 'static,
+    B::Data: Into<axum::body::Bytes>,
+    B::Error: Into<BoxError>,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request<B>, state: &S) -> Result<Self, Self::Rejection> {
+        let multipart = axum::extract::Multipart::from_request(req, state).await?;
+        Ok(Self(multipart))
+    }
+}
+
+/// `axum::extract::Path`と同様に扱えるが、パスパラメーターの解析に失敗した場合は`ApiError`を返す
+#[derive(Debug, Clone, Copy)]
+pub struct Path<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for Path<T>
+where
+    T: DeserializeOwned + Send,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Path(value) =
+            axum::extract::Path::<T>::from_request_parts(parts, state).await?;
+        Ok(Self(value))
+    }
+}
@@ -1,34 +1,95 @@
 use std::sync::Arc;
 
-use axum::{extract::Path, http::StatusCode, response::IntoResponse, Extension, Json};
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension,
+};
 
 use crate::{
-    handler::handle_error,
-    repository::label::{CreateLabel, LabelRepository},
+    handler::{
+        extract::{Json, Path, ValidatedJson, ValidatedQuery},
+        ApiError, AuthUser,
+    },
+    repository::label::{
+        CreateLabel, DeleteLabelQuery, LabelQuery, LabelRepository, MergeLabel, UpdateLabel,
+    },
 };
 
 pub async fn create_label<T: LabelRepository>(
+    user: AuthUser,
     Extension(repository): Extension<Arc<T>>,
-    Json(payload): Json<CreateLabel>,
-) -> Result<impl IntoResponse, StatusCode> {
-    let label = repository.create(payload).await.map_err(handle_error)?;
+    ValidatedJson(payload): ValidatedJson<CreateLabel>,
+) -> Result<impl IntoResponse, ApiError> {
+    let label = repository.create(user.id(), payload).await?;
 
     Ok((StatusCode::CREATED, Json(label)))
 }
 
+/// `usage=true`の場合はラベル毎のTodoの件数も返す
 pub async fn all_label<T: LabelRepository>(
+    user: AuthUser,
+    Extension(repository): Extension<Arc<T>>,
+    ValidatedQuery(query): ValidatedQuery<LabelQuery>,
+) -> Result<Response, ApiError> {
+    if query.usage() {
+        let labels = repository.usage(user.id()).await?;
+        return Ok((StatusCode::OK, Json(labels)).into_response());
+    }
+    let labels = repository.all(user.id()).await?;
+
+    Ok((StatusCode::OK, Json(labels)).into_response())
+}
+
+pub async fn find_label<T: LabelRepository>(
+    user: AuthUser,
+    Path(id): Path<i32>,
+    Extension(repository): Extension<Arc<T>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let label = repository.find(user.id(), id).await?;
+
+    Ok((StatusCode::OK, Json(label)))
+}
+
+pub async fn subtree_label<T: LabelRepository>(
+    user: AuthUser,
+    Path(id): Path<i32>,
     Extension(repository): Extension<Arc<T>>,
-) -> Result<impl IntoResponse, StatusCode> {
-    let labels = repository.all().await.map_err(handle_error)?;
+) -> Result<impl IntoResponse, ApiError> {
+    let labels = repository.subtree(user.id(), id).await?;
 
     Ok((StatusCode::OK, Json(labels)))
 }
 
+pub async fn update_label<T: LabelRepository>(
+    user: AuthUser,
+    Path(id): Path<i32>,
+    Extension(repository): Extension<Arc<T>>,
+    ValidatedJson(payload): ValidatedJson<UpdateLabel>,
+) -> Result<impl IntoResponse, ApiError> {
+    let label = repository.update(user.id(), id, payload).await?;
+
+    Ok((StatusCode::OK, Json(label)))
+}
+
+pub async fn merge_label<T: LabelRepository>(
+    user: AuthUser,
+    Path(id): Path<i32>,
+    Extension(repository): Extension<Arc<T>>,
+    Json(payload): Json<MergeLabel>,
+) -> Result<impl IntoResponse, ApiError> {
+    let label = repository.merge(user.id(), id, payload).await?;
+
+    Ok((StatusCode::OK, Json(label)))
+}
+
 pub async fn delete_label<T: LabelRepository>(
+    user: AuthUser,
     Path(id): Path<i32>,
     Extension(repository): Extension<Arc<T>>,
-) -> Result<impl IntoResponse, StatusCode> {
-    repository.delete(id).await.map_err(handle_error)?;
+    ValidatedQuery(query): ValidatedQuery<DeleteLabelQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    repository.delete(user.id(), id, query.force()).await?;
 
     Ok(StatusCode::NO_CONTENT)
 }
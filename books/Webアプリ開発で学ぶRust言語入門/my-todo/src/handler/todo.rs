@@ -1,49 +1,140 @@
 use std::sync::Arc;
 
-use axum::{extract::Path, http::StatusCode, response::IntoResponse, Extension, Json};
+use axum::{
+    extract::OriginalUri,
+    http::{header, HeaderMap, HeaderValue, StatusCode, Uri},
+    response::IntoResponse,
+    Extension,
+};
 
-use crate::repository::todo::{CreateTodo, TodoRepository, UpdateTodo};
+use crate::repository::todo::{
+    CreateDependency, CreateTodo, MoveTodo, TodoQuery, TodoRepository, TodoSearch, UpdateTodo,
+};
 
-use super::handle_error;
+use super::{
+    extract::{Json, Path, ValidatedJson, ValidatedQuery},
+    ApiError, AuthUser,
+};
 
 pub async fn create_todo<R: TodoRepository>(
+    user: AuthUser,
     Extension(repository): Extension<Arc<R>>,
-    Json(payload): Json<CreateTodo>,
-) -> Result<impl IntoResponse, StatusCode> {
-    let todo = repository.create(payload).await.map_err(handle_error)?;
+    ValidatedJson(payload): ValidatedJson<CreateTodo>,
+) -> Result<impl IntoResponse, ApiError> {
+    let todo = repository.create(user.id(), payload).await?;
 
     Ok((StatusCode::CREATED, Json(todo)))
 }
 
+/// 次のページがある場合は`Link`ヘッダーでそのURIを返す
 pub async fn all_todo<R: TodoRepository>(
+    user: AuthUser,
+    OriginalUri(uri): OriginalUri,
     Extension(repository): Extension<Arc<R>>,
-) -> Result<impl IntoResponse, StatusCode> {
-    let todo = repository.all().await.map_err(handle_error)?;
-    Ok((StatusCode::OK, Json(todo)))
+    ValidatedQuery(query): ValidatedQuery<TodoQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let page = repository.all(user.id(), &query).await?;
+
+    let mut headers = HeaderMap::new();
+    if let Some(offset) = page.next_offset() {
+        let link = format!(r#"<{}>; rel="next""#, next_page_uri(&uri, offset));
+        if let Ok(link) = HeaderValue::try_from(link) {
+            headers.insert(header::LINK, link);
+        }
+    }
+
+    Ok((StatusCode::OK, headers, Json(page.into_todos())))
+}
+
+/// 元のクエリ文字列の`offset`だけを差し替えたURI
+fn next_page_uri(uri: &Uri, offset: u32) -> String {
+    let mut params: Vec<&str> = uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|param| !param.is_empty() && !param.starts_with("offset="))
+        .collect();
+    let offset = format!("offset={offset}");
+    params.push(&offset);
+    format!("{}?{}", uri.path(), params.join("&"))
+}
+
+pub async fn search_todo<R: TodoRepository>(
+    user: AuthUser,
+    Extension(repository): Extension<Arc<R>>,
+    ValidatedQuery(search): ValidatedQuery<TodoSearch>,
+) -> Result<impl IntoResponse, ApiError> {
+    let hits = repository.search(user.id(), &search).await?;
+
+    Ok((StatusCode::OK, Json(hits)))
 }
 
 pub async fn find_todo<R: TodoRepository>(
+    user: AuthUser,
     Path(id): Path<u32>,
     Extension(repository): Extension<Arc<R>>,
-) -> Result<impl IntoResponse, StatusCode> {
-    let todo = repository.find(id).await.map_err(handle_error)?;
+) -> Result<impl IntoResponse, ApiError> {
+    let todo = repository.find(user.id(), id).await?;
 
     Ok((StatusCode::OK, Json(todo)))
 }
 
 pub async fn update_todo<R: TodoRepository>(
+    user: AuthUser,
     Path(id): Path<u32>,
     Extension(repository): Extension<Arc<R>>,
-    Json(payload): Json<UpdateTodo>,
-) -> Result<impl IntoResponse, StatusCode> {
-    let todo = repository.update(id, payload).await.map_err(handle_error)?;
+    ValidatedJson(payload): ValidatedJson<UpdateTodo>,
+) -> Result<impl IntoResponse, ApiError> {
+    let todo = repository.update(user.id(), id, payload).await?;
     Ok((StatusCode::CREATED, Json(todo)))
 }
 
 pub async fn delete_todo<R: TodoRepository>(
+    user: AuthUser,
     Path(id): Path<u32>,
     Extension(repository): Extension<Arc<R>>,
-) -> Result<impl IntoResponse, StatusCode> {
-    repository.delete(id).await.map_err(handle_error)?;
+) -> Result<impl IntoResponse, ApiError> {
+    repository.delete(user.id(), id).await?;
     Ok(StatusCode::NO_CONTENT)
 }
+
+pub async fn children_todo<R: TodoRepository>(
+    user: AuthUser,
+    Path(id): Path<u32>,
+    Extension(repository): Extension<Arc<R>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let children = repository.children(user.id(), id).await?;
+
+    Ok((StatusCode::OK, Json(children)))
+}
+
+pub async fn add_dependency<R: TodoRepository>(
+    user: AuthUser,
+    Path(id): Path<u32>,
+    Extension(repository): Extension<Arc<R>>,
+    Json(payload): Json<CreateDependency>,
+) -> Result<impl IntoResponse, ApiError> {
+    let todo = repository.add_dependency(user.id(), id, payload).await?;
+
+    Ok((StatusCode::CREATED, Json(todo)))
+}
+
+pub async fn board<R: TodoRepository>(
+    user: AuthUser,
+    Extension(repository): Extension<Arc<R>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let board = repository.board(user.id()).await?;
+
+    Ok((StatusCode::OK, Json(board)))
+}
+
+pub async fn move_todo<R: TodoRepository>(
+    user: AuthUser,
+    Path(id): Path<u32>,
+    Extension(repository): Extension<Arc<R>>,
+    Json(payload): Json<MoveTodo>,
+) -> Result<impl IntoResponse, ApiError> {
+    let todo = repository.move_todo(user.id(), id, payload).await?;
+
+    Ok((StatusCode::OK, Json(todo)))
+}
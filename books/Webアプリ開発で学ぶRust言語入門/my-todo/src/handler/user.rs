@@ -1,25 +1,38 @@
-use axum::{http::StatusCode, response::IntoResponse, Json};
-use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
-pub struct CreateUser {
-    username: String,
-}
+use axum::{http::StatusCode, response::IntoResponse, Extension};
+
+use crate::repository::user::{CreateUser, UserRepository};
+
+use super::{
+    extract::{Json, Path, ValidatedJson},
+    ApiError, AuthUser,
+};
 
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
-pub struct User {
-    id: u64,
-    name: String,
+pub async fn create_user<R: UserRepository>(
+    Extension(repository): Extension<Arc<R>>,
+    ValidatedJson(payload): ValidatedJson<CreateUser>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = repository.create(payload).await?;
+
+    Ok((StatusCode::CREATED, Json(user)))
 }
 
-impl User {
-    pub fn new(id: u64, name: String) -> Self {
-        Self { id, name }
-    }
+pub async fn all_user<R: UserRepository>(
+    _user: AuthUser,
+    Extension(repository): Extension<Arc<R>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let users = repository.all().await?;
+
+    Ok((StatusCode::OK, Json(users)))
 }
 
-pub async fn create_user(Json(payload): Json<CreateUser>) -> impl IntoResponse {
-    let user = User::new(1337, payload.username);
+pub async fn find_user<R: UserRepository>(
+    _user: AuthUser,
+    Path(id): Path<i32>,
+    Extension(repository): Extension<Arc<R>>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user = repository.find(id).await?;
 
-    (StatusCode::CREATED, Json(user))
+    Ok((StatusCode::OK, Json(user)))
 }
@@ -1,2 +1,4 @@
+pub mod attachment;
 pub mod handler;
+pub mod reminder;
 pub mod repository;
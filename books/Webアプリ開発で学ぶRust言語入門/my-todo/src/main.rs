@@ -2,20 +2,125 @@
 //!
 //! ## API
 //!
+//! - /users
+//!     - GET: ユーザー情報の一覧取得
+//!     - POST: ユーザー情報の作成 (ユーザー名は50文字まで、パスワードは8〜128文字で、argon2でハッシュ化して保存)
+//! - /users/:id
+//!     - GET: idに対応するユーザー情報の取得
+//! - /login
+//!     - POST: ログインしてセッショントークンを発行 (`SESSION_TTL_SECS`で有効期間を指定でき、既定は30日)
+//! - /logout
+//!     - POST: `Authorization: Bearer <token>`ヘッダーのセッションを削除
+//!
+//! `GET /users`、`GET /users/:id`、`/logout`と、`/todos`、`/attachments`、`/labels`以下は`Authorization: Bearer <token>`ヘッダーが必要
+//! Todoとラベルはログインしたユーザーが所有するものだけを扱い、他のユーザーのものは404になる
+//!
 //! - /todos
 //!     - GET: Todo情報の一覧取得
-//!     - POST: Todo情報の作成
+//!         - `status`, `labels` (カンマ区切りのラベルID。子孫のラベルが付いたTodoも含む), `text` で絞り込み
+//!         - `due_before` (期限がこの日時より前) と `overdue` (未完了で期限切れ) で絞り込み
+//!         - `sort` (`id`, `text`, `priority`, `due_at`, `estimate` をカンマ区切りで優先する順に指定) と `order` (`asc`, `desc`) で並び替え
+//!           (期限や見積もりのないTodoは末尾に並べる)
+//!         - `limit` (既定は50件) と `offset` でページ分割し、次のページは`Link`ヘッダーで返す
+//!     - POST: Todo情報の作成 (`due_at`はタイムゾーン付きのRFC 3339形式で指定し、UTCで保存する)
+//!         - `parent_id`を指定するとそのTodoのサブタスクになる (作成後は変更できない)
+//!         - `recurrence` (`freq`は`daily`, `weekly`, `monthly`、`interval`, `by_weekday`, `count`, `until`) を指定すると、
+//!           完了にしたときに次の期限で同じ内容のTodoを作り、規則を引き継ぐ
+//!           (完了にしたTodoも規則を残し、作ったTodoを`next_occurrence_id`で返す。完了にし直しても重ねて作らない)
+//!         - `priority` (`p0`〜`p3`、既定は`p2`) と `estimate` (1〜1000の見積もり) を指定できる
+//!         - `labels`に存在しないラベルや他のユーザーのラベルを含む場合は、それらのIDを`invalid-params`に挙げて422になる
+//! - /todos/search
+//!     - GET: `q`の単語を全て含むTodoを関連度順に検索し、一致箇所を`<mark>`で囲んだスニペット (本文はHTMLとしてエスケープする) を返す
 //! - /todos/:id
 //!     - GET: idに対応するTodo情報の取得
 //!     - PATCH: Todo情報の更新
-//!     - DELETE: Todo情報の削除
+//!         - `status`は`backlog`, `in_progress`, `review`, `done`の順に隣り合う状態にだけ変更でき、それ以外は409になる
+//!         - 未完了のサブタスクが残っている場合は`done`にできず409になる
+//!     - DELETE: Todo情報の削除 (サブタスクとコメントもまとめて削除する)
+//! - /todos/:id/children
+//!     - GET: 直下のサブタスクの一覧取得
+//! - /todos/:id/dependencies
+//!     - POST: `blocked_by`のTodoが完了するまでブロックされるようにする (循環する依存関係は409になる)
+//! - /todos/:id/comments
+//!     - GET: コメントの一覧取得 (投稿した順)
+//!     - POST: コメントの投稿
+//! - /todos/:id/comments/:comment_id
+//!     - PATCH: コメントの編集 (投稿したユーザーだけができ、それ以外は404になる)
+//!     - DELETE: コメントの削除 (投稿したユーザーだけができ、それ以外は404になる)
+//! - /todos/:id/attachments
+//!     - POST: `multipart/form-data`の`file`フィールドのファイルを添付する (上限を超えると413になる)
+//! - /todos/:id/move
+//!     - POST: ボード上で`after`のTodoの直後 (省略した場合は列の先頭) に動かし、`status`を指定した場合は別の列に移す
+//! - /board
+//!     - GET: 状態毎の列にTodoを並び順で返す
+//! - /attachments/:id
+//!     - GET: 添付したときの`Content-Type`で添付ファイルをダウンロード
+//! - /labels
+//!     - GET: ラベル情報の一覧取得
+//!         - `usage=true` で未完了・完了のTodoの件数 (`open_todos`, `closed_todos`) も返す
+//!     - POST: ラベル情報の作成 (`color`は`#rrggbb`形式で、`parent_id`で親のラベルを指定できる)
+//!         - ラベル名は前後の空白を除いて保存し、大文字小文字や全角半角の違いしかない名前は同名として400になる
+//!         - `area/backend`のように`/`で区切った名前は、最後の`/`より前の名前のラベルが親になる (ないか`parent_id`と食い違うと422)
+//! - /labels/:id
+//!     - GET: idに対応するラベル情報の取得
+//!     - PATCH: ラベル名・色・親の変更 (他のラベルと同名には変更できず、自身や子孫を親にすると409になる)。`/`で区切った名前の親はPOSTと同様に求める
+//!     - DELETE: ラベル情報の削除 (Todoに付いている場合は409、`force=true` でTodoから外して削除する)。子のラベルは親に付け替える
+//! - /labels/:id/subtree
+//!     - GET: idのラベルと子孫のラベルの一覧取得
+//! - /labels/:id/merge
+//!     - POST: `source_id`のラベルを付けていたTodoにidのラベルを付け替え、`source_id`のラベルを削除する (子のラベルはidの子になる)
+//!
+//! Todoの`blocked`は完了していないTodoにブロックされているかを表し、`comment_count`はコメントの数を表す
+//!
+//! `SUBTASK_COMPLETION`に`auto_complete`を指定すると、最後のサブタスクを完了にしたときに親のTodoも完了にする (既定は`block`)
+//!
+//! エラーは`application/problem+json` (RFC 7807) 形式で返し、入力値の検証に失敗した場合は422になる
+//!
+//! ## 添付ファイル
+//!
+//! 内容はSHA-256のハッシュをファイル名にしてローカルのディスクに保存し、同じ内容は一度だけ保存する
+//!
+//! - `ATTACHMENT_DIR`: 保存先のディレクトリ (既定は`attachments`)
+//! - `ATTACHMENT_MAX_BYTES`: 1ファイルあたりの大きさの上限 (既定は10MiB)
+//!
+//! ## リマインダー
+//!
+//! 期限を迎えた未完了のTodoをバックグラウンドで走査し、同じ期限につき一度だけ通知する
+//!
+//! - `REMINDER_INTERVAL_SECS`: 走査の間隔 (既定は60秒)
+//! - `REMINDER_NOTIFIER`: 通知先
+//!     - `log` (既定): ログに出力
+//!     - `webhook`: `REMINDER_WEBHOOK_URL`にJSONでPOST (`http://`のみ)
+//!     - `mail`: `REMINDER_MAIL_DIR`に`REMINDER_MAIL_TO`宛てのメールを書き出す (`REMINDER_MAIL_FROM`は省略可)
 
-use std::net::{Ipv4Addr, SocketAddr};
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    str::FromStr,
+    time::Duration,
+};
 
-use sqlx::PgPool;
+use axum::{http::Uri, Router};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    PgPool,
+};
 use web_rust_my_todo::{
+    attachment::{Attachments, LocalAttachmentStore},
     handler::create_app,
-    repository::{label::LabelRepositoryForPostgres, todo::TodoRepositoryForPostgres},
+    reminder::{
+        LogNotifier, MailNotifier, Notifier, ReminderHandle, ReminderScheduler, WebhookNotifier,
+    },
+    repository::{
+        attachment::{AttachmentRepositoryForPostgres, AttachmentRepositoryForSqlite},
+        comment::{CommentRepositoryForPostgres, CommentRepositoryForSqlite},
+        label::{LabelRepositoryForPostgres, LabelRepositoryForSqlite},
+        reminder::{
+            ReminderRepository, ReminderRepositoryForPostgres, ReminderRepositoryForSqlite,
+        },
+        session::{SessionRepositoryForPostgres, SessionRepositoryForSqlite, DEFAULT_SESSION_TTL},
+        todo::{SubtaskCompletion, TodoRepositoryForPostgres, TodoRepositoryForSqlite},
+        user::{UserRepositoryForPostgres, UserRepositoryForSqlite},
+    },
 };
 
 #[tokio::main]
@@ -24,14 +129,11 @@ async fn main() {
 
     tracing::debug!("connect to database");
     let database_url = std::env::var("DATABASE_URL").expect("Undefined [DATABASE_URL]");
-    let pool = PgPool::connect(&database_url)
-        .await
-        .expect("fail connect database");
-
-    let todo_repository = TodoRepositoryForPostgres::new(pool.clone());
-    let label_repository = LabelRepositoryForPostgres::new(pool.clone());
-
-    let app = create_app(todo_repository, label_repository);
+    let (app, reminders) = match database_url.split_once(':').map(|(scheme, _)| scheme) {
+        Some("postgres" | "postgresql") => create_postgres_app(&database_url).await,
+        Some("sqlite") => create_sqlite_app(&database_url).await,
+        _ => panic!("Unsupported database [DATABASE_URL={database_url}]"),
+    };
 
     let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, 3000));
 
@@ -41,6 +143,135 @@ async fn main() {
         .with_graceful_shutdown(shutdown_signal())
         .await
         .expect("fail start server");
+
+    reminders.shutdown().await;
+}
+
+async fn create_postgres_app(database_url: &str) -> (Router, ReminderHandle) {
+    let pool = PgPool::connect(database_url)
+        .await
+        .expect("fail connect database");
+
+    let todo_repository =
+        TodoRepositoryForPostgres::new(pool.clone()).with_subtask_completion(subtask_completion());
+    let comment_repository = CommentRepositoryForPostgres::new(pool.clone());
+    let attachment_repository = AttachmentRepositoryForPostgres::new(pool.clone());
+    let label_repository = LabelRepositoryForPostgres::new(pool.clone());
+    label_repository
+        .normalize_names()
+        .await
+        .expect("fail normalize label names");
+    let user_repository = UserRepositoryForPostgres::new(pool.clone());
+    let session_repository =
+        SessionRepositoryForPostgres::new(pool.clone()).with_ttl(session_ttl());
+    let reminder_repository = ReminderRepositoryForPostgres::new(pool.clone());
+
+    let app = create_app(
+        todo_repository,
+        comment_repository,
+        attachment_repository,
+        attachments(),
+        label_repository,
+        user_repository,
+        session_repository,
+    );
+    (app, spawn_reminder_scheduler(reminder_repository))
+}
+
+async fn create_sqlite_app(database_url: &str) -> (Router, ReminderHandle) {
+    let options = SqliteConnectOptions::from_str(database_url)
+        .expect("invalid database url")
+        .create_if_missing(true);
+    // `:memory:` のデータベースは全ての接続が閉じると消えるため、接続を維持し続ける
+    let pool = SqlitePoolOptions::new()
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(options)
+        .await
+        .expect("fail connect database");
+    sqlx::migrate!("./migrations/sqlite")
+        .run(&pool)
+        .await
+        .expect("fail migrate database");
+
+    let todo_repository =
+        TodoRepositoryForSqlite::new(pool.clone()).with_subtask_completion(subtask_completion());
+    let comment_repository = CommentRepositoryForSqlite::new(pool.clone());
+    let attachment_repository = AttachmentRepositoryForSqlite::new(pool.clone());
+    let label_repository = LabelRepositoryForSqlite::new(pool.clone());
+    label_repository
+        .normalize_names()
+        .await
+        .expect("fail normalize label names");
+    let user_repository = UserRepositoryForSqlite::new(pool.clone());
+    let session_repository = SessionRepositoryForSqlite::new(pool.clone()).with_ttl(session_ttl());
+    let reminder_repository = ReminderRepositoryForSqlite::new(pool.clone());
+
+    let app = create_app(
+        todo_repository,
+        comment_repository,
+        attachment_repository,
+        attachments(),
+        label_repository,
+        user_repository,
+        session_repository,
+    );
+    (app, spawn_reminder_scheduler(reminder_repository))
+}
+
+fn attachments() -> Attachments<LocalAttachmentStore> {
+    let dir = std::env::var("ATTACHMENT_DIR").unwrap_or_else(|_| "attachments".to_string());
+    let attachments = Attachments::new(LocalAttachmentStore::new(dir.into()));
+    match std::env::var("ATTACHMENT_MAX_BYTES") {
+        Ok(max_size) => {
+            attachments.with_max_size(max_size.parse().expect("invalid [ATTACHMENT_MAX_BYTES]"))
+        }
+        Err(_) => attachments,
+    }
+}
+
+fn session_ttl() -> chrono::Duration {
+    std::env::var("SESSION_TTL_SECS")
+        .map(|secs| chrono::Duration::seconds(secs.parse().expect("invalid [SESSION_TTL_SECS]")))
+        .unwrap_or(DEFAULT_SESSION_TTL)
+}
+
+fn subtask_completion() -> SubtaskCompletion {
+    match std::env::var("SUBTASK_COMPLETION").as_deref() {
+        Err(_) | Ok("block") => SubtaskCompletion::Block,
+        Ok("auto_complete") => SubtaskCompletion::AutoComplete,
+        Ok(policy) => panic!("Unsupported policy [SUBTASK_COMPLETION={policy}]"),
+    }
+}
+
+fn spawn_reminder_scheduler<R: ReminderRepository>(repository: R) -> ReminderHandle {
+    let interval = std::env::var("REMINDER_INTERVAL_SECS")
+        .map(|secs| secs.parse().expect("invalid [REMINDER_INTERVAL_SECS]"))
+        .unwrap_or(60);
+    let notifier: Box<dyn Notifier> = match std::env::var("REMINDER_NOTIFIER").as_deref() {
+        Err(_) | Ok("log") => Box::new(LogNotifier),
+        Ok("webhook") => {
+            let url =
+                std::env::var("REMINDER_WEBHOOK_URL").expect("Undefined [REMINDER_WEBHOOK_URL]");
+            let uri: Uri = url.parse().expect("invalid [REMINDER_WEBHOOK_URL]");
+            // HTTPSには対応しないため、送れないURLは起動時に弾く
+            if uri.scheme_str() != Some("http") {
+                panic!("Unsupported scheme [REMINDER_WEBHOOK_URL={url}]");
+            }
+            Box::new(WebhookNotifier::new(uri))
+        }
+        Ok("mail") => Box::new(MailNotifier::new(
+            std::env::var("REMINDER_MAIL_DIR")
+                .expect("Undefined [REMINDER_MAIL_DIR]")
+                .into(),
+            std::env::var("REMINDER_MAIL_FROM").unwrap_or_else(|_| "my-todo@localhost".to_string()),
+            std::env::var("REMINDER_MAIL_TO").expect("Undefined [REMINDER_MAIL_TO]"),
+        )),
+        Ok(notifier) => panic!("Unsupported notifier [REMINDER_NOTIFIER={notifier}]"),
+    };
+
+    tracing::debug!("start reminder scheduler every {interval}s");
+    ReminderScheduler::new(repository, notifier, Duration::from_secs(interval)).spawn()
 }
 
 async fn shutdown_signal() {
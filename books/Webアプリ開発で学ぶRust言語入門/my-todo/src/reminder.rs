@@ -0,0 +1,233 @@
+//! 期限を迎えたTodoを定期的に走査して通知するバックグラウンドタスク
+
+mod notifier;
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::{
+    sync::oneshot,
+    task::JoinHandle,
+    time::{self, MissedTickBehavior},
+};
+
+use crate::repository::{reminder::ReminderRepository, RepositoryError};
+
+pub use notifier::{LogNotifier, MailNotifier, Notifier, WebhookNotifier};
+
+pub struct ReminderScheduler<R: ReminderRepository, N: Notifier> {
+    repository: R,
+    notifier: N,
+    interval: Duration,
+}
+
+impl<R: ReminderRepository, N: Notifier> ReminderScheduler<R, N> {
+    /// 一度に取り出す通知の件数
+    const BATCH_SIZE: u32 = 100;
+
+    pub fn new(repository: R, notifier: N, interval: Duration) -> Self {
+        Self {
+            repository,
+            notifier,
+            interval,
+        }
+    }
+
+    /// `now`までに期限を迎えた未通知のTodoを全て通知し、通知した件数を返す。
+    /// 通知に失敗したものは次の走査で再び通知する
+    pub async fn run_once(&self, now: DateTime<Utc>) -> Result<usize, RepositoryError> {
+        let mut notified = 0;
+        loop {
+            let reminders = self.repository.claim_due(now, Self::BATCH_SIZE).await?;
+            let claimed = reminders.len();
+            let mut failed = 0;
+            for reminder in reminders {
+                match self.notifier.notify(&reminder).await {
+                    Ok(()) => notified += 1,
+                    Err(error) => {
+                        tracing::warn!(todo_id = reminder.todo_id(), "fail notify: {error:#}");
+                        self.repository.release(&reminder).await?;
+                        failed += 1;
+                    }
+                }
+            }
+            // 失敗したものを同じ走査で取り出し続けないよう、失敗があれば次の走査に回す
+            if claimed < Self::BATCH_SIZE as usize || failed > 0 {
+                return Ok(notified);
+            }
+        }
+    }
+
+    /// 最初の走査はすぐに行い、以降は`interval`毎に走査する
+    pub fn spawn(self) -> ReminderHandle {
+        let (shutdown, mut signal) = oneshot::channel();
+        let task = tokio::spawn(async move {
+            let mut interval = time::interval(self.interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = &mut signal => break,
+                    _ = interval.tick() => match self.run_once(Utc::now()).await {
+                        Ok(0) => {}
+                        Ok(notified) => tracing::debug!("notified {notified} reminders"),
+                        Err(error) => tracing::error!("fail scan reminders: {error}"),
+                    },
+                }
+            }
+            tracing::debug!("reminder scheduler stopped");
+        });
+        ReminderHandle { shutdown, task }
+    }
+}
+
+/// 起動したスケジューラーを停止するためのハンドル
+pub struct ReminderHandle {
+    shutdown: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+impl ReminderHandle {
+    /// 走査の途中であれば、その走査の通知を終えてから停止する
+    pub async fn shutdown(self) {
+        let _ = self.shutdown.send(());
+        if let Err(error) = self.task.await {
+            tracing::error!("reminder scheduler panicked: {error}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use anyhow::anyhow;
+    use axum::async_trait;
+    use chrono::Duration as ChronoDuration;
+
+    use crate::repository::{
+        label::LabelRepositoryForMemory,
+        reminder::{Reminder, ReminderRepositoryForMemory},
+        todo::{CreateTodo, TodoRepository, TodoRepositoryForMemory},
+    };
+
+    use super::*;
+
+    /// 通知を記録し、`failures`回だけ失敗する
+    #[derive(Debug, Clone, Default)]
+    struct RecordingNotifier {
+        notified: Arc<Mutex<Vec<i32>>>,
+        failures: Arc<Mutex<u32>>,
+    }
+
+    impl RecordingNotifier {
+        fn failing(failures: u32) -> Self {
+            Self {
+                failures: Arc::new(Mutex::new(failures)),
+                ..Default::default()
+            }
+        }
+
+        fn notified(&self) -> Vec<i32> {
+            self.notified.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl Notifier for RecordingNotifier {
+        async fn notify(&self, reminder: &Reminder) -> anyhow::Result<()> {
+            let mut failures = self.failures.lock().unwrap();
+            if *failures > 0 {
+                *failures -= 1;
+                return Err(anyhow!("unavailable"));
+            }
+            self.notified.lock().unwrap().push(reminder.todo_id());
+            Ok(())
+        }
+    }
+
+    async fn setup<N: Notifier>(
+        notifier: N,
+    ) -> (
+        TodoRepositoryForMemory,
+        ReminderScheduler<ReminderRepositoryForMemory, N>,
+    ) {
+        let todo_repository = TodoRepositoryForMemory::new(LabelRepositoryForMemory::new());
+        let scheduler = ReminderScheduler::new(
+            ReminderRepositoryForMemory::new(todo_repository.clone()),
+            notifier,
+            Duration::from_secs(3600),
+        );
+        (todo_repository, scheduler)
+    }
+
+    async fn create(repository: &TodoRepositoryForMemory, due_at: DateTime<Utc>) -> i32 {
+        repository
+            .create(
+                1,
+                CreateTodo::new("todo".to_string(), vec![]).with_due_at(due_at),
+            )
+            .await
+            .unwrap()
+            .id() as i32
+    }
+
+    #[tokio::test]
+    async fn should_notify_due_todos_once() {
+        let notifier = RecordingNotifier::default();
+        let (todo_repository, scheduler) = setup(notifier.clone()).await;
+        let now = Utc::now();
+        let due = create(&todo_repository, now - ChronoDuration::minutes(1)).await;
+        create(&todo_repository, now + ChronoDuration::minutes(1)).await;
+
+        assert_eq!(scheduler.run_once(now).await.unwrap(), 1);
+        assert_eq!(scheduler.run_once(now).await.unwrap(), 0);
+        assert_eq!(notifier.notified(), vec![due]);
+    }
+
+    #[tokio::test]
+    async fn should_notify_more_than_batch_size() {
+        let notifier = RecordingNotifier::default();
+        let (todo_repository, scheduler) = setup(notifier.clone()).await;
+        let now = Utc::now();
+        // one more than BATCH_SIZE
+        for _ in 0..101 {
+            create(&todo_repository, now).await;
+        }
+
+        assert_eq!(scheduler.run_once(now).await.unwrap(), 101);
+    }
+
+    #[tokio::test]
+    async fn should_retry_failed_notification() {
+        let notifier = RecordingNotifier::failing(1);
+        let (todo_repository, scheduler) = setup(notifier.clone()).await;
+        let now = Utc::now();
+        let due = create(&todo_repository, now).await;
+
+        assert_eq!(scheduler.run_once(now).await.unwrap(), 0);
+        assert!(notifier.notified().is_empty());
+        assert_eq!(scheduler.run_once(now).await.unwrap(), 1);
+        assert_eq!(notifier.notified(), vec![due]);
+    }
+
+    #[tokio::test]
+    async fn should_stop_on_shutdown() {
+        let notifier = RecordingNotifier::default();
+        let (todo_repository, scheduler) = setup(notifier.clone()).await;
+        let due = create(&todo_repository, Utc::now()).await;
+
+        let handle = scheduler.spawn();
+        // the first scan runs immediately
+        time::timeout(Duration::from_secs(5), async {
+            while notifier.notified().is_empty() {
+                time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("reminder is not notified");
+        time::timeout(Duration::from_secs(5), handle.shutdown())
+            .await
+            .expect("scheduler does not stop");
+        assert_eq!(notifier.notified(), vec![due]);
+    }
+}
@@ -0,0 +1,212 @@
+use std::{path::PathBuf, time::Duration};
+
+use anyhow::{bail, Context};
+use axum::async_trait;
+use hyper::{client::HttpConnector, header, Body, Client, Method, Request, Uri};
+
+use crate::repository::reminder::Reminder;
+
+/// 期限を迎えたTodoの通知先
+#[async_trait]
+pub trait Notifier: Send + Sync + 'static {
+    async fn notify(&self, reminder: &Reminder) -> anyhow::Result<()>;
+}
+
+/// 起動時の設定で通知先を切り替えられるよう、トレイトオブジェクトも通知先として扱う
+#[async_trait]
+impl<N: Notifier + ?Sized> Notifier for Box<N> {
+    async fn notify(&self, reminder: &Reminder) -> anyhow::Result<()> {
+        (**self).notify(reminder).await
+    }
+}
+
+/// ログに出力する
+#[derive(Debug, Clone, Default)]
+pub struct LogNotifier;
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    async fn notify(&self, reminder: &Reminder) -> anyhow::Result<()> {
+        tracing::info!(
+            todo_id = reminder.todo_id(),
+            user_id = reminder.user_id(),
+            due_at = %reminder.due_at(),
+            "todo is due: {}",
+            reminder.text()
+        );
+        Ok(())
+    }
+}
+
+/// `Reminder`をJSONでPOSTする。HTTPSには対応しない
+#[derive(Debug, Clone)]
+pub struct WebhookNotifier {
+    client: Client<HttpConnector>,
+    url: Uri,
+}
+
+impl WebhookNotifier {
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    pub fn new(url: Uri) -> Self {
+        Self {
+            client: Client::new(),
+            url,
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    async fn notify(&self, reminder: &Reminder) -> anyhow::Result<()> {
+        let req = Request::builder()
+            .uri(self.url.clone())
+            .method(Method::POST)
+            .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(serde_json::to_vec(reminder)?))?;
+        let res = tokio::time::timeout(Self::TIMEOUT, self.client.request(req))
+            .await
+            .context("webhook timed out")??;
+        if !res.status().is_success() {
+            bail!("webhook responded with {}", res.status());
+        }
+        Ok(())
+    }
+}
+
+/// SMTPサーバーの代わりに、メールを1通ずつ`.eml`ファイルとしてピックアップディレクトリに書き出す
+#[derive(Debug, Clone)]
+pub struct MailNotifier {
+    dir: PathBuf,
+    from: String,
+    to: String,
+}
+
+impl MailNotifier {
+    pub fn new(dir: PathBuf, from: String, to: String) -> Self {
+        Self { dir, from, to }
+    }
+
+    fn message(&self, reminder: &Reminder) -> String {
+        format!(
+            "From: {}\r\nTo: {}\r\nSubject: Todo #{} is due\r\nDate: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n\r\nDue at: {}\r\n",
+            self.from,
+            self.to,
+            reminder.todo_id(),
+            chrono::Utc::now().to_rfc2822(),
+            reminder.text(),
+            reminder.due_at().to_rfc3339(),
+        )
+    }
+}
+
+#[async_trait]
+impl Notifier for MailNotifier {
+    async fn notify(&self, reminder: &Reminder) -> anyhow::Result<()> {
+        // 同じ期限の通知は同じファイル名になり、再送しても重複しない
+        let path = self.dir.join(format!(
+            "todo-{}-{}.eml",
+            reminder.todo_id(),
+            reminder.due_at().timestamp()
+        ));
+        tokio::fs::create_dir_all(&self.dir).await?;
+        tokio::fs::write(&path, self.message(reminder))
+            .await
+            .with_context(|| format!("fail write {}", path.display()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::{Ipv4Addr, SocketAddr},
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use axum::{extract::State, http::StatusCode, routing::post, Router};
+    use chrono::{TimeZone, Utc};
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    fn reminder() -> Reminder {
+        Reminder::new(
+            1,
+            2,
+            "buy milk".to_string(),
+            Utc.with_ymd_and_hms(2030, 1, 1, 0, 0, 0).unwrap(),
+        )
+    }
+
+    /// 受け取ったリクエストボディを送り返すWebhookの受け口を起動する
+    fn serve_webhook(status: StatusCode) -> (Uri, mpsc::UnboundedReceiver<String>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let app = Router::new()
+            .route(
+                "/hook",
+                post(
+                    move |State(tx): State<mpsc::UnboundedSender<String>>, body: String| async move {
+                        tx.send(body).unwrap();
+                        status
+                    },
+                ),
+            )
+            .with_state(tx);
+        let server = axum::Server::bind(&SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+            .serve(app.into_make_service());
+        let url = format!("http://{}/hook", server.local_addr())
+            .parse()
+            .unwrap();
+        tokio::spawn(server);
+        (url, rx)
+    }
+
+    #[tokio::test]
+    async fn should_post_reminder_to_webhook() {
+        let (url, mut rx) = serve_webhook(StatusCode::NO_CONTENT);
+
+        WebhookNotifier::new(url)
+            .notify(&reminder())
+            .await
+            .expect("fail notify");
+
+        let body: Reminder = serde_json::from_str(&rx.recv().await.unwrap()).unwrap();
+        assert_eq!(body, reminder());
+    }
+
+    #[tokio::test]
+    async fn should_fail_when_webhook_fails() {
+        let (url, _rx) = serve_webhook(StatusCode::INTERNAL_SERVER_ERROR);
+
+        let result = WebhookNotifier::new(url).notify(&reminder()).await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn should_write_mail_to_pickup_directory() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!("my-todo-mail-{nanos}"));
+        let notifier = MailNotifier::new(
+            dir.clone(),
+            "todo@example.com".to_string(),
+            "user@example.com".to_string(),
+        );
+
+        notifier.notify(&reminder()).await.expect("fail notify");
+        notifier.notify(&reminder()).await.expect("fail notify");
+
+        let mut entries = std::fs::read_dir(&dir).unwrap();
+        let path = entries.next().unwrap().unwrap().path();
+        assert!(entries.next().is_none(), "same reminder is written twice");
+        let mail = std::fs::read_to_string(path).unwrap();
+        assert!(mail.starts_with("From: todo@example.com\r\nTo: user@example.com\r\n"));
+        assert!(mail.contains("Subject: Todo #1 is due\r\n"));
+        assert!(mail.contains("\r\n\r\nbuy milk\r\n"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
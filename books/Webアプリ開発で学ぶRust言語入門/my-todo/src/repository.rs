@@ -1,7 +1,15 @@
+pub mod attachment;
+pub mod comment;
 pub mod label;
+pub mod reminder;
+pub mod session;
 pub mod todo;
+pub mod user;
 
+use serde::{Deserialize, Deserializer};
 use thiserror::Error;
+use todo::TodoStatus;
+use validator::ValidationError;
 
 type BoxError = Box<dyn std::error::Error + Send + Sync + 'static>;
 
@@ -11,6 +19,47 @@ pub enum RepositoryError {
     NotFound(u32),
     #[error("Duplicate data, id is {0}")]
     Duplicate(i32),
+    #[error("Unauthorized")]
+    Unauthorized,
+    #[error("Invalid status transition of {id} from {from} to {to}")]
+    InvalidTransition {
+        id: u32,
+        from: TodoStatus,
+        to: TodoStatus,
+    },
+    #[error("Todo {0} has open subtasks")]
+    OpenSubtasks(u32),
+    #[error("Todo {id} can not be blocked by {blocked_by}: dependency cycle")]
+    DependencyCycle { id: u32, blocked_by: u32 },
+    #[error("Label {id} is attached to {todos} todos")]
+    LabelInUse { id: i32, todos: i64 },
+    #[error("Label {id} can not be placed under {parent_id}: label cycle")]
+    LabelCycle { id: i32, parent_id: i32 },
+    #[error("Label {0} can not be merged into itself")]
+    MergeIntoSelf(i32),
+    #[error("Labels {0:?} do not exist")]
+    UnknownLabels(Vec<i32>),
+    #[error("Label {0:?} must be placed under the label named by its path")]
+    InvalidLabelPath(String),
     #[error(transparent)]
     Unexpected(BoxError),
 }
+
+/// `null`と省略を区別するため、値があれば`null`も`Some(None)`として受け取る
+fn deserialize_some<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(deserializer).map(Some)
+}
+
+/// 空白文字だけの文字列を拒否する
+fn validate_not_blank(value: &str) -> Result<(), ValidationError> {
+    if value.trim().is_empty() {
+        let mut error = ValidationError::new("blank");
+        error.message = Some("Can not be blank".into());
+        return Err(error);
+    }
+    Ok(())
+}
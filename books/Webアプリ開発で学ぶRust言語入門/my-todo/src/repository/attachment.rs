@@ -0,0 +1,78 @@
+#[cfg(test)]
+mod conformance;
+mod memory;
+mod postgres;
+mod sqlite;
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use super::RepositoryError;
+
+pub use memory::AttachmentRepositoryForMemory;
+pub use postgres::AttachmentRepositoryForPostgres;
+pub use sqlite::AttachmentRepositoryForSqlite;
+
+/// 添付ファイルのメタデータ。`user_id`のユーザーが所有するTodoの添付ファイルだけを扱う
+#[async_trait]
+pub trait AttachmentRepository: Send + Sync + 'static {
+    async fn create(
+        &self,
+        user_id: i32,
+        todo_id: u32,
+        payload: CreateAttachment,
+    ) -> Result<Attachment, RepositoryError>;
+    async fn find(&self, user_id: i32, id: i32) -> Result<Attachment, RepositoryError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreateAttachment {
+    filename: String,
+    content_type: String,
+    size: i64,
+    digest: String,
+}
+
+impl CreateAttachment {
+    pub fn new(filename: String, content_type: String, size: i64, digest: String) -> Self {
+        Self {
+            filename,
+            content_type,
+            size,
+            digest,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct Attachment {
+    id: i32,
+    todo_id: i32,
+    filename: String,
+    content_type: String,
+    /// 内容の大きさ (バイト)
+    size: i64,
+    /// 内容のSHA-256のハッシュ
+    digest: String,
+    created_at: DateTime<Utc>,
+}
+
+impl Attachment {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    pub fn content_type(&self) -> &str {
+        &self.content_type
+    }
+
+    pub fn digest(&self) -> &str {
+        &self.digest
+    }
+}
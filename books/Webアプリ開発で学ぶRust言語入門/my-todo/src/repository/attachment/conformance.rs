@@ -0,0 +1,124 @@
+//! 全ての`AttachmentRepository`の実装が満たすべき振る舞い
+
+use crate::repository::{
+    todo::{conformance::create_todo, TodoRepository},
+    user::{conformance::create_user, UserRepository},
+    RepositoryError,
+};
+
+use super::{AttachmentRepository, CreateAttachment};
+
+pub(crate) async fn run<R: AttachmentRepository, T: TodoRepository, U: UserRepository>(
+    repository: R,
+    todo_repository: T,
+    user_repository: U,
+) {
+    let owner = create_user(&user_repository, "[attachment] owner")
+        .await
+        .id();
+    let other = create_user(&user_repository, "[attachment] other")
+        .await
+        .id();
+
+    crud(&repository, &todo_repository, owner).await;
+    not_found(&repository, &todo_repository, owner).await;
+    ownership(&repository, &todo_repository, owner, other).await;
+}
+
+fn payload(filename: &str) -> CreateAttachment {
+    CreateAttachment::new(
+        filename.to_string(),
+        "text/plain".to_string(),
+        5,
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824".to_string(),
+    )
+}
+
+async fn crud<R: AttachmentRepository, T: TodoRepository>(
+    repository: &R,
+    todo_repository: &T,
+    user_id: i32,
+) {
+    let todo_id = create_todo(todo_repository, user_id, "[attachment::crud] todo").await;
+
+    let created = repository
+        .create(user_id, todo_id, payload("crud.txt"))
+        .await
+        .expect("fail create attachment");
+    assert_eq!(created.todo_id, todo_id as i32);
+    assert_eq!(created.filename, "crud.txt");
+    assert_eq!(created.content_type, "text/plain");
+    assert_eq!(created.size, 5);
+
+    let found = repository
+        .find(user_id, created.id)
+        .await
+        .expect("fail find attachment");
+    assert_eq!(found, created);
+
+    // 同じ内容を別のファイルとして添付できる
+    let same_content = repository
+        .create(user_id, todo_id, payload("copy.txt"))
+        .await
+        .expect("fail create attachment");
+    assert_ne!(same_content.id, created.id);
+    assert_eq!(same_content.digest, created.digest);
+
+    todo_repository.delete(user_id, todo_id).await.unwrap();
+}
+
+/// 存在しないTodoには添付できず、Todoを削除すると添付ファイルも参照できなくなる
+async fn not_found<R: AttachmentRepository, T: TodoRepository>(
+    repository: &R,
+    todo_repository: &T,
+    user_id: i32,
+) {
+    let todo_id = create_todo(todo_repository, user_id, "[attachment::not_found] todo").await;
+    let attachment = repository
+        .create(user_id, todo_id, payload("not_found.txt"))
+        .await
+        .expect("fail create attachment");
+    todo_repository.delete(user_id, todo_id).await.unwrap();
+
+    let result = repository.find(user_id, attachment.id).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == attachment.id as u32),
+        "unexpected result: {result:?}"
+    );
+    let result = repository
+        .create(user_id, todo_id, payload("not_found.txt"))
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == todo_id),
+        "unexpected result: {result:?}"
+    );
+}
+
+/// 他のユーザーのTodoには添付できず、その添付ファイルも存在しないものとして扱う
+async fn ownership<R: AttachmentRepository, T: TodoRepository>(
+    repository: &R,
+    todo_repository: &T,
+    owner: i32,
+    other: i32,
+) {
+    let todo_id = create_todo(todo_repository, owner, "[attachment::ownership] todo").await;
+    let attachment = repository
+        .create(owner, todo_id, payload("ownership.txt"))
+        .await
+        .expect("fail create attachment");
+
+    let result = repository.find(other, attachment.id).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == attachment.id as u32),
+        "unexpected result: {result:?}"
+    );
+    let result = repository
+        .create(other, todo_id, payload("ownership.txt"))
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == todo_id),
+        "unexpected result: {result:?}"
+    );
+
+    todo_repository.delete(owner, todo_id).await.unwrap();
+}
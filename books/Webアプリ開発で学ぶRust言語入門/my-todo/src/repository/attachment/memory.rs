@@ -0,0 +1,100 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use axum::async_trait;
+use chrono::Utc;
+
+use crate::repository::{todo::TodoRepositoryForMemory, RepositoryError};
+
+use super::{Attachment, AttachmentRepository, CreateAttachment};
+
+#[derive(Debug, Default)]
+struct AttachmentData {
+    attachments: HashMap<i32, Attachment>,
+    last_id: i32,
+}
+
+/// 削除されたTodoの添付ファイルは残るが、Todoが存在するか確かめてから返すため参照されない
+#[derive(Debug, Clone, Default)]
+pub struct AttachmentRepositoryForMemory {
+    store: Arc<RwLock<AttachmentData>>,
+    todos: TodoRepositoryForMemory,
+}
+
+impl AttachmentRepositoryForMemory {
+    pub fn new(todos: TodoRepositoryForMemory) -> Self {
+        Self {
+            store: Default::default(),
+            todos,
+        }
+    }
+
+    fn write_store_ref(&self) -> RwLockWriteGuard<'_, AttachmentData> {
+        self.store.write().unwrap()
+    }
+
+    fn read_store_ref(&self) -> RwLockReadGuard<'_, AttachmentData> {
+        self.store.read().unwrap()
+    }
+}
+
+#[async_trait]
+impl AttachmentRepository for AttachmentRepositoryForMemory {
+    async fn create(
+        &self,
+        user_id: i32,
+        todo_id: u32,
+        payload: CreateAttachment,
+    ) -> Result<Attachment, RepositoryError> {
+        let mut store = self.write_store_ref();
+        self.todos.check_owner(user_id, todo_id)?;
+
+        store.last_id += 1;
+        let attachment = Attachment {
+            id: store.last_id,
+            todo_id: todo_id as i32,
+            filename: payload.filename,
+            content_type: payload.content_type,
+            size: payload.size,
+            digest: payload.digest,
+            created_at: Utc::now(),
+        };
+        store.attachments.insert(attachment.id, attachment.clone());
+        Ok(attachment)
+    }
+
+    async fn find(&self, user_id: i32, id: i32) -> Result<Attachment, RepositoryError> {
+        let store = self.read_store_ref();
+        let attachment = store
+            .attachments
+            .get(&id)
+            .ok_or(RepositoryError::NotFound(id as u32))?;
+        self.todos
+            .check_owner(user_id, attachment.todo_id as u32)
+            .map_err(|_| RepositoryError::NotFound(id as u32))?;
+        Ok(attachment.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repository::{
+        attachment::conformance, label::LabelRepositoryForMemory, user::UserRepositoryForMemory,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn conformance() {
+        let todo_repository = TodoRepositoryForMemory::new(LabelRepositoryForMemory::new());
+
+        conformance::run(
+            AttachmentRepositoryForMemory::new(todo_repository.clone()),
+            todo_repository,
+            UserRepositoryForMemory::new(),
+        )
+        .await;
+    }
+}
@@ -0,0 +1,101 @@
+use axum::async_trait;
+use chrono::Utc;
+use sqlx::PgPool;
+
+use crate::repository::RepositoryError;
+
+use super::{Attachment, AttachmentRepository, CreateAttachment};
+
+#[derive(Debug, Clone)]
+pub struct AttachmentRepositoryForPostgres {
+    pool: PgPool,
+}
+
+impl AttachmentRepositoryForPostgres {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl AttachmentRepository for AttachmentRepositoryForPostgres {
+    async fn create(
+        &self,
+        user_id: i32,
+        todo_id: u32,
+        payload: CreateAttachment,
+    ) -> Result<Attachment, RepositoryError> {
+        let attachment = sqlx::query_as::<_, Attachment>(
+            r#"
+                INSERT INTO attachments (todo_id, filename, content_type, size, digest, created_at)
+                SELECT id, $3, $4, $5, $6, $7
+                FROM todo
+                WHERE id = $1
+                  AND user_id = $2
+                RETURNING *;
+            "#,
+        )
+        .bind(todo_id as i32)
+        .bind(user_id)
+        .bind(&payload.filename)
+        .bind(&payload.content_type)
+        .bind(payload.size)
+        .bind(&payload.digest)
+        .bind(Utc::now())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?
+        .ok_or(RepositoryError::NotFound(todo_id))?;
+
+        Ok(attachment)
+    }
+
+    async fn find(&self, user_id: i32, id: i32) -> Result<Attachment, RepositoryError> {
+        let attachment = sqlx::query_as::<_, Attachment>(
+            r#"
+                SELECT attachments.*
+                FROM attachments
+                    INNER JOIN todo ON todo.id = attachments.todo_id
+                WHERE attachments.id = $1
+                  AND todo.user_id = $2;
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?
+        .ok_or(RepositoryError::NotFound(id as u32))?;
+
+        Ok(attachment)
+    }
+}
+
+fn handle_sqlx_error(error: sqlx::Error) -> RepositoryError {
+    RepositoryError::Unexpected(error.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repository::{
+        attachment::conformance, todo::TodoRepositoryForPostgres, user::UserRepositoryForPostgres,
+    };
+
+    use super::*;
+
+    #[ignore = "Dependence of database"]
+    #[tokio::test]
+    async fn conformance() {
+        let database_url = std::env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("fail connect database");
+
+        conformance::run(
+            AttachmentRepositoryForPostgres::new(pool.clone()),
+            TodoRepositoryForPostgres::new(pool.clone()),
+            UserRepositoryForPostgres::new(pool),
+        )
+        .await;
+    }
+}
@@ -0,0 +1,103 @@
+use axum::async_trait;
+use chrono::Utc;
+use sqlx::SqlitePool;
+
+use crate::repository::RepositoryError;
+
+use super::{Attachment, AttachmentRepository, CreateAttachment};
+
+#[derive(Debug, Clone)]
+pub struct AttachmentRepositoryForSqlite {
+    pool: SqlitePool,
+}
+
+impl AttachmentRepositoryForSqlite {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl AttachmentRepository for AttachmentRepositoryForSqlite {
+    async fn create(
+        &self,
+        user_id: i32,
+        todo_id: u32,
+        payload: CreateAttachment,
+    ) -> Result<Attachment, RepositoryError> {
+        let attachment = sqlx::query_as::<_, Attachment>(
+            r#"
+                INSERT INTO attachments (todo_id, filename, content_type, size, digest, created_at)
+                SELECT id, $3, $4, $5, $6, $7
+                FROM todo
+                WHERE id = $1
+                  AND user_id = $2
+                RETURNING *;
+            "#,
+        )
+        .bind(todo_id as i32)
+        .bind(user_id)
+        .bind(&payload.filename)
+        .bind(&payload.content_type)
+        .bind(payload.size)
+        .bind(&payload.digest)
+        .bind(Utc::now())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?
+        .ok_or(RepositoryError::NotFound(todo_id))?;
+
+        Ok(attachment)
+    }
+
+    async fn find(&self, user_id: i32, id: i32) -> Result<Attachment, RepositoryError> {
+        let attachment = sqlx::query_as::<_, Attachment>(
+            r#"
+                SELECT attachments.*
+                FROM attachments
+                    INNER JOIN todo ON todo.id = attachments.todo_id
+                WHERE attachments.id = $1
+                  AND todo.user_id = $2;
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?
+        .ok_or(RepositoryError::NotFound(id as u32))?;
+
+        Ok(attachment)
+    }
+}
+
+fn handle_sqlx_error(error: sqlx::Error) -> RepositoryError {
+    RepositoryError::Unexpected(error.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repository::{
+        attachment::conformance, todo::TodoRepositoryForSqlite, user::UserRepositoryForSqlite,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn conformance() {
+        let pool = SqlitePool::connect("sqlite::memory:")
+            .await
+            .expect("fail connect database");
+        sqlx::migrate!("./migrations/sqlite")
+            .run(&pool)
+            .await
+            .expect("fail migrate database");
+
+        conformance::run(
+            AttachmentRepositoryForSqlite::new(pool.clone()),
+            TodoRepositoryForSqlite::new(pool.clone()),
+            UserRepositoryForSqlite::new(pool),
+        )
+        .await;
+    }
+}
@@ -0,0 +1,92 @@
+#[cfg(test)]
+mod conformance;
+mod memory;
+mod postgres;
+mod sqlite;
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use validator::Validate;
+
+use super::{validate_not_blank, RepositoryError};
+
+pub use memory::CommentRepositoryForMemory;
+pub use postgres::CommentRepositoryForPostgres;
+pub use sqlite::CommentRepositoryForSqlite;
+
+/// コメントの一覧と投稿は`user_id`のユーザーが所有するTodoに限られ、
+/// 編集と削除は投稿したユーザーだけができる
+#[async_trait]
+pub trait CommentRepository: Send + Sync + 'static {
+    /// 投稿した順に返す
+    async fn all(&self, user_id: i32, todo_id: u32) -> Result<Vec<Comment>, RepositoryError>;
+    async fn create(
+        &self,
+        user_id: i32,
+        todo_id: u32,
+        payload: CreateComment,
+    ) -> Result<Comment, RepositoryError>;
+    async fn update(
+        &self,
+        user_id: i32,
+        todo_id: u32,
+        id: i32,
+        payload: UpdateComment,
+    ) -> Result<Comment, RepositoryError>;
+    async fn delete(&self, user_id: i32, todo_id: u32, id: i32) -> Result<(), RepositoryError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Validate)]
+pub struct CreateComment {
+    #[validate(length(min = 1, message = "Can not be empty"))]
+    #[validate(length(max = 1000, message = "Over body length"))]
+    #[validate(custom = "validate_not_blank")]
+    body: String,
+}
+
+impl CreateComment {
+    pub fn new(body: String) -> Self {
+        Self { body }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Validate)]
+pub struct UpdateComment {
+    #[validate(length(min = 1, message = "Can not be empty"))]
+    #[validate(length(max = 1000, message = "Over body length"))]
+    #[validate(custom = "validate_not_blank")]
+    body: String,
+}
+
+impl UpdateComment {
+    pub fn new(body: String) -> Self {
+        Self { body }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct Comment {
+    id: i32,
+    todo_id: i32,
+    /// 投稿したユーザー
+    author_id: i32,
+    body: String,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl Comment {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    pub fn author_id(&self) -> i32 {
+        self.author_id
+    }
+
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+}
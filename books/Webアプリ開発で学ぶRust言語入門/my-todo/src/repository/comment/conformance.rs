@@ -0,0 +1,265 @@
+//! 全ての`CommentRepository`の実装が満たすべき振る舞い
+
+use crate::repository::{
+    todo::{conformance::create_todo, TodoRepository},
+    user::{conformance::create_user, UserRepository},
+    RepositoryError,
+};
+
+use super::{CommentRepository, CreateComment, UpdateComment};
+
+pub(crate) async fn run<R: CommentRepository, T: TodoRepository, U: UserRepository>(
+    repository: R,
+    todo_repository: T,
+    user_repository: U,
+) {
+    let owner = create_user(&user_repository, "[comment] owner").await.id();
+    let other = create_user(&user_repository, "[comment] other").await.id();
+
+    crud(&repository, &todo_repository, owner).await;
+    ordering(&repository, &todo_repository, owner).await;
+    not_found(&repository, &todo_repository, owner).await;
+    ownership(&repository, &todo_repository, owner, other).await;
+}
+
+async fn crud<R: CommentRepository, T: TodoRepository>(
+    repository: &R,
+    todo_repository: &T,
+    user_id: i32,
+) {
+    let todo_id = create_todo(todo_repository, user_id, "[comment::crud] todo").await;
+
+    // create
+    let created = repository
+        .create(
+            user_id,
+            todo_id,
+            CreateComment::new("[comment::crud] body".to_string()),
+        )
+        .await
+        .expect("fail create comment");
+    assert_eq!(created.todo_id, todo_id as i32);
+    assert_eq!(created.author_id, user_id);
+    assert_eq!(created.body, "[comment::crud] body");
+    assert_eq!(created.created_at, created.updated_at);
+    let todo = todo_repository.find(user_id, todo_id).await.unwrap();
+    assert_eq!(todo.comment_count(), 1);
+
+    // all
+    let comments = repository
+        .all(user_id, todo_id)
+        .await
+        .expect("fail fetch all comments");
+    assert_eq!(comments, vec![created.clone()]);
+
+    // update
+    let updated = repository
+        .update(
+            user_id,
+            todo_id,
+            created.id,
+            UpdateComment::new("[comment::crud] updated body".to_string()),
+        )
+        .await
+        .expect("fail update comment");
+    assert_eq!(updated.id, created.id);
+    assert_eq!(updated.body, "[comment::crud] updated body");
+    assert_eq!(updated.created_at, created.created_at);
+    assert!(updated.updated_at >= created.updated_at);
+    let comments = repository.all(user_id, todo_id).await.unwrap();
+    assert_eq!(comments, vec![updated.clone()]);
+
+    // delete
+    repository
+        .delete(user_id, todo_id, created.id)
+        .await
+        .expect("fail delete comment");
+    let comments = repository.all(user_id, todo_id).await.unwrap();
+    assert!(comments.is_empty(), "deleted comment is still listed");
+    let todo = todo_repository.find(user_id, todo_id).await.unwrap();
+    assert_eq!(todo.comment_count(), 0);
+
+    todo_repository.delete(user_id, todo_id).await.unwrap();
+}
+
+/// `all`は投稿した順に、そのTodoのコメントだけを返す
+async fn ordering<R: CommentRepository, T: TodoRepository>(
+    repository: &R,
+    todo_repository: &T,
+    user_id: i32,
+) {
+    let todo_id = create_todo(todo_repository, user_id, "[comment::ordering] todo").await;
+    let another = create_todo(todo_repository, user_id, "[comment::ordering] another").await;
+
+    let mut created = Vec::new();
+    for body in ["first", "second", "third"] {
+        let comment = repository
+            .create(user_id, todo_id, CreateComment::new(body.to_string()))
+            .await
+            .expect("fail create comment");
+        created.push(comment);
+    }
+    repository
+        .create(user_id, another, CreateComment::new("another".to_string()))
+        .await
+        .expect("fail create comment");
+
+    let comments = repository.all(user_id, todo_id).await.unwrap();
+    assert_eq!(comments, created);
+    assert_eq!(
+        todo_repository
+            .find(user_id, todo_id)
+            .await
+            .unwrap()
+            .comment_count(),
+        3
+    );
+    assert_eq!(
+        todo_repository
+            .find(user_id, another)
+            .await
+            .unwrap()
+            .comment_count(),
+        1
+    );
+
+    todo_repository.delete(user_id, todo_id).await.unwrap();
+    todo_repository.delete(user_id, another).await.unwrap();
+}
+
+/// 存在しないTodoのコメントは扱えず、Todoを削除するとコメントもまとめて削除される
+async fn not_found<R: CommentRepository, T: TodoRepository>(
+    repository: &R,
+    todo_repository: &T,
+    user_id: i32,
+) {
+    let todo_id = create_todo(todo_repository, user_id, "[comment::not_found] todo").await;
+    let another = create_todo(todo_repository, user_id, "[comment::not_found] another").await;
+    let comment = repository
+        .create(
+            user_id,
+            todo_id,
+            CreateComment::new("[comment::not_found] body".to_string()),
+        )
+        .await
+        .expect("fail create comment");
+
+    // 別のTodoのコメントとしては扱えない
+    let result = repository
+        .update(
+            user_id,
+            another,
+            comment.id,
+            UpdateComment::new("moved".to_string()),
+        )
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == comment.id as u32),
+        "unexpected result: {result:?}"
+    );
+    let result = repository.delete(user_id, another, comment.id).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == comment.id as u32),
+        "unexpected result: {result:?}"
+    );
+
+    repository
+        .delete(user_id, todo_id, comment.id)
+        .await
+        .unwrap();
+    let result = repository.delete(user_id, todo_id, comment.id).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == comment.id as u32),
+        "unexpected result: {result:?}"
+    );
+
+    let comment = repository
+        .create(
+            user_id,
+            todo_id,
+            CreateComment::new("[comment::not_found] cascade".to_string()),
+        )
+        .await
+        .expect("fail create comment");
+    todo_repository.delete(user_id, todo_id).await.unwrap();
+
+    let result = repository.all(user_id, todo_id).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == todo_id),
+        "unexpected result: {result:?}"
+    );
+    let result = repository
+        .create(user_id, todo_id, CreateComment::new("body".to_string()))
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == todo_id),
+        "unexpected result: {result:?}"
+    );
+    let result = repository
+        .update(
+            user_id,
+            todo_id,
+            comment.id,
+            UpdateComment::new("updated".to_string()),
+        )
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(_))),
+        "unexpected result: {result:?}"
+    );
+
+    todo_repository.delete(user_id, another).await.unwrap();
+}
+
+/// 他のユーザーのTodoやコメントは存在しないものとして扱う
+async fn ownership<R: CommentRepository, T: TodoRepository>(
+    repository: &R,
+    todo_repository: &T,
+    owner: i32,
+    other: i32,
+) {
+    let todo_id = create_todo(todo_repository, owner, "[comment::ownership] todo").await;
+    let comment = repository
+        .create(
+            owner,
+            todo_id,
+            CreateComment::new("[comment::ownership] body".to_string()),
+        )
+        .await
+        .expect("fail create comment");
+
+    let result = repository.all(other, todo_id).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == todo_id),
+        "unexpected result: {result:?}"
+    );
+    let result = repository
+        .create(other, todo_id, CreateComment::new("body".to_string()))
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == todo_id),
+        "unexpected result: {result:?}"
+    );
+    let result = repository
+        .update(
+            other,
+            todo_id,
+            comment.id,
+            UpdateComment::new("updated".to_string()),
+        )
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(_))),
+        "unexpected result: {result:?}"
+    );
+    let result = repository.delete(other, todo_id, comment.id).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(_))),
+        "unexpected result: {result:?}"
+    );
+
+    let comments = repository.all(owner, todo_id).await.unwrap();
+    assert_eq!(comments, vec![comment]);
+
+    todo_repository.delete(owner, todo_id).await.unwrap();
+}
@@ -0,0 +1,151 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use axum::async_trait;
+use chrono::Utc;
+
+use crate::repository::{todo::TodoRepositoryForMemory, RepositoryError};
+
+use super::{Comment, CommentRepository, CreateComment, UpdateComment};
+
+#[derive(Debug, Default)]
+struct CommentData {
+    comments: HashMap<i32, Comment>,
+    last_id: i32,
+}
+
+impl CommentData {
+    fn count(&self, todo_id: i32) -> u32 {
+        self.comments
+            .values()
+            .filter(|comment| comment.todo_id == todo_id)
+            .count() as u32
+    }
+}
+
+/// 削除されたTodoのコメントは残るが、Todoが存在するか確かめてから操作するため参照されない
+#[derive(Debug, Clone, Default)]
+pub struct CommentRepositoryForMemory {
+    store: Arc<RwLock<CommentData>>,
+    todos: TodoRepositoryForMemory,
+}
+
+impl CommentRepositoryForMemory {
+    pub fn new(todos: TodoRepositoryForMemory) -> Self {
+        Self {
+            store: Default::default(),
+            todos,
+        }
+    }
+
+    fn write_store_ref(&self) -> RwLockWriteGuard<'_, CommentData> {
+        self.store.write().unwrap()
+    }
+
+    fn read_store_ref(&self) -> RwLockReadGuard<'_, CommentData> {
+        self.store.read().unwrap()
+    }
+
+    /// `user_id`のユーザーが投稿した`todo_id`のTodoのコメントを取得する
+    fn authored(
+        store: &mut CommentData,
+        user_id: i32,
+        todo_id: u32,
+        id: i32,
+    ) -> Result<&mut Comment, RepositoryError> {
+        store
+            .comments
+            .get_mut(&id)
+            .filter(|comment| comment.todo_id == todo_id as i32 && comment.author_id == user_id)
+            .ok_or(RepositoryError::NotFound(id as u32))
+    }
+}
+
+#[async_trait]
+impl CommentRepository for CommentRepositoryForMemory {
+    async fn all(&self, user_id: i32, todo_id: u32) -> Result<Vec<Comment>, RepositoryError> {
+        let store = self.read_store_ref();
+        self.todos.check_owner(user_id, todo_id)?;
+        let mut comments: Vec<Comment> = store
+            .comments
+            .values()
+            .filter(|comment| comment.todo_id == todo_id as i32)
+            .cloned()
+            .collect();
+        comments.sort_by_key(|comment| comment.id);
+        Ok(comments)
+    }
+
+    async fn create(
+        &self,
+        user_id: i32,
+        todo_id: u32,
+        payload: CreateComment,
+    ) -> Result<Comment, RepositoryError> {
+        let mut store = self.write_store_ref();
+        self.todos.check_owner(user_id, todo_id)?;
+
+        store.last_id += 1;
+        let now = Utc::now();
+        let comment = Comment {
+            id: store.last_id,
+            todo_id: todo_id as i32,
+            author_id: user_id,
+            body: payload.body,
+            created_at: now,
+            updated_at: now,
+        };
+        store.comments.insert(comment.id, comment.clone());
+        self.todos
+            .set_comment_count(todo_id, store.count(todo_id as i32));
+        Ok(comment)
+    }
+
+    async fn update(
+        &self,
+        user_id: i32,
+        todo_id: u32,
+        id: i32,
+        payload: UpdateComment,
+    ) -> Result<Comment, RepositoryError> {
+        let mut store = self.write_store_ref();
+        self.todos.check_owner(user_id, todo_id)?;
+        let comment = Self::authored(&mut store, user_id, todo_id, id)?;
+        comment.body = payload.body;
+        comment.updated_at = Utc::now();
+        Ok(comment.clone())
+    }
+
+    async fn delete(&self, user_id: i32, todo_id: u32, id: i32) -> Result<(), RepositoryError> {
+        let mut store = self.write_store_ref();
+        self.todos.check_owner(user_id, todo_id)?;
+        Self::authored(&mut store, user_id, todo_id, id)?;
+        store.comments.remove(&id);
+        self.todos
+            .set_comment_count(todo_id, store.count(todo_id as i32));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repository::{
+        comment::conformance, label::LabelRepositoryForMemory, user::UserRepositoryForMemory,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn conformance() {
+        let todo_repository = TodoRepositoryForMemory::new(LabelRepositoryForMemory::new());
+
+        conformance::run(
+            CommentRepositoryForMemory::new(todo_repository.clone()),
+            todo_repository,
+            UserRepositoryForMemory::new(),
+        )
+        .await;
+    }
+}
@@ -0,0 +1,166 @@
+use axum::async_trait;
+use chrono::Utc;
+use sqlx::SqlitePool;
+
+use crate::repository::RepositoryError;
+
+use super::{Comment, CommentRepository, CreateComment, UpdateComment};
+
+#[derive(Debug, Clone)]
+pub struct CommentRepositoryForSqlite {
+    pool: SqlitePool,
+}
+
+impl CommentRepositoryForSqlite {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl CommentRepository for CommentRepositoryForSqlite {
+    async fn all(&self, user_id: i32, todo_id: u32) -> Result<Vec<Comment>, RepositoryError> {
+        sqlx::query_scalar::<_, i32>(
+            r#"
+                SELECT id
+                FROM todo
+                WHERE id = $1
+                  AND user_id = $2;
+            "#,
+        )
+        .bind(todo_id as i32)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?
+        .ok_or(RepositoryError::NotFound(todo_id))?;
+
+        let comments = sqlx::query_as::<_, Comment>(
+            r#"
+                SELECT *
+                FROM comments
+                WHERE todo_id = $1
+                ORDER BY id ASC;
+            "#,
+        )
+        .bind(todo_id as i32)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        Ok(comments)
+    }
+
+    async fn create(
+        &self,
+        user_id: i32,
+        todo_id: u32,
+        payload: CreateComment,
+    ) -> Result<Comment, RepositoryError> {
+        let comment = sqlx::query_as::<_, Comment>(
+            r#"
+                INSERT INTO comments (todo_id, author_id, body, created_at, updated_at)
+                SELECT id, $2, $3, $4, $4
+                FROM todo
+                WHERE id = $1
+                  AND user_id = $2
+                RETURNING *;
+            "#,
+        )
+        .bind(todo_id as i32)
+        .bind(user_id)
+        .bind(&payload.body)
+        .bind(Utc::now())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?
+        .ok_or(RepositoryError::NotFound(todo_id))?;
+
+        Ok(comment)
+    }
+
+    async fn update(
+        &self,
+        user_id: i32,
+        todo_id: u32,
+        id: i32,
+        payload: UpdateComment,
+    ) -> Result<Comment, RepositoryError> {
+        let comment = sqlx::query_as::<_, Comment>(
+            r#"
+                UPDATE comments
+                SET body       = $4,
+                    updated_at = $5
+                WHERE id = $1
+                  AND todo_id = $2
+                  AND author_id = $3
+                RETURNING *;
+            "#,
+        )
+        .bind(id)
+        .bind(todo_id as i32)
+        .bind(user_id)
+        .bind(&payload.body)
+        .bind(Utc::now())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?
+        .ok_or(RepositoryError::NotFound(id as u32))?;
+
+        Ok(comment)
+    }
+
+    async fn delete(&self, user_id: i32, todo_id: u32, id: i32) -> Result<(), RepositoryError> {
+        let result = sqlx::query(
+            r#"
+                DELETE
+                FROM comments
+                WHERE id = $1
+                  AND todo_id = $2
+                  AND author_id = $3;
+            "#,
+        )
+        .bind(id)
+        .bind(todo_id as i32)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?;
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::NotFound(id as u32));
+        }
+
+        Ok(())
+    }
+}
+
+fn handle_sqlx_error(error: sqlx::Error) -> RepositoryError {
+    RepositoryError::Unexpected(error.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repository::{
+        comment::conformance, todo::TodoRepositoryForSqlite, user::UserRepositoryForSqlite,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn conformance() {
+        let pool = SqlitePool::connect("sqlite::memory:")
+            .await
+            .expect("fail connect database");
+        sqlx::migrate!("./migrations/sqlite")
+            .run(&pool)
+            .await
+            .expect("fail migrate database");
+
+        conformance::run(
+            CommentRepositoryForSqlite::new(pool.clone()),
+            TodoRepositoryForSqlite::new(pool.clone()),
+            UserRepositoryForSqlite::new(pool),
+        )
+        .await;
+    }
+}
@@ -1,41 +1,274 @@
+#[cfg(test)]
+mod conformance;
 mod memory;
 mod postgres;
+mod sqlite;
+
+use std::collections::HashSet;
 
 use axum::async_trait;
+use caseless::Caseless;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use unicode_normalization::UnicodeNormalization;
+use validator::{Validate, ValidationError};
 
-use super::RepositoryError;
+use super::{deserialize_some, validate_not_blank, RepositoryError};
 
 pub use memory::LabelRepositoryForMemory;
 pub use postgres::LabelRepositoryForPostgres;
+pub use sqlite::LabelRepositoryForSqlite;
 
+/// 操作は`user_id`のユーザーが所有するラベルに限られ、ラベル名の重複もユーザー毎に判定する。
+/// ラベル名は前後の空白を除いて保存し、重複は`normalize_name`で正規化した名前で判定する。
+/// `area/backend`のように`/`で区切った名前のラベルは、最後の`/`より前の名前のラベル (`area`) の子になる
 #[async_trait]
 pub trait LabelRepository: Send + Sync + 'static {
-    async fn all(&self) -> Result<Vec<Label>, RepositoryError>;
-    async fn create(&self, payload: CreateLabel) -> Result<Label, RepositoryError>;
-    async fn delete(&self, id: i32) -> Result<(), RepositoryError>;
+    async fn all(&self, user_id: i32) -> Result<Vec<Label>, RepositoryError>;
+    /// 全てのラベルを、付いているTodoの未完了・完了の件数と共にID順で取得する
+    async fn usage(&self, user_id: i32) -> Result<Vec<LabelUsage>, RepositoryError>;
+    async fn find(&self, user_id: i32, id: i32) -> Result<Label, RepositoryError>;
+    /// `id`のラベルとその子孫のラベルをID順に取得する
+    async fn subtree(&self, user_id: i32, id: i32) -> Result<Vec<Label>, RepositoryError>;
+    /// `/`で区切った名前であれば親を名前から求め、`parent_id`は省略するか同じ親を指定する
+    async fn create(&self, user_id: i32, payload: CreateLabel) -> Result<Label, RepositoryError>;
+    /// 他のラベルと同名に変更しようとした場合は、そのラベルのIDで重複エラーになる。
+    /// 自身や子孫のラベルを親にはできない。名前か親を変更する場合は`create`と同様に名前から親を求める
+    async fn update(
+        &self,
+        user_id: i32,
+        id: i32,
+        payload: UpdateLabel,
+    ) -> Result<Label, RepositoryError>;
+    /// `source_id`のラベルが付いたTodoに`id`のラベルを付け、`source_id`のラベルを削除する。
+    /// `source_id`の子のラベルは`id`の子になるため、`id`が`source_id`の子孫であれば統合できない
+    async fn merge(
+        &self,
+        user_id: i32,
+        id: i32,
+        payload: MergeLabel,
+    ) -> Result<Label, RepositoryError>;
+    /// Todoに付いているラベルは`force`を指定しない限り削除せず、指定した場合はTodoから外して削除する。
+    /// 子のラベルは削除したラベルの親に付け替える
+    async fn delete(&self, user_id: i32, id: i32, force: bool) -> Result<(), RepositoryError>;
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+/// 前後の空白を除き、NFKC正規化して大文字小文字を畳み込む。`Bug`と`ｂｕｇ`は同じ名前になる
+fn normalize_name(name: &str) -> String {
+    name.trim().nfkc().default_case_fold().nfkc().collect()
+}
+
+/// `/`で区切った名前であれば、親のラベルの名前 (最後の`/`より前の部分) を返す
+fn parent_path(name: &str) -> Option<&str> {
+    name.trim().rsplit_once('/').map(|(parent, _)| parent)
+}
+
+/// `/`で区切った名前`name`のラベルの親を決める。`found`は名前から求めた親のラベル、
+/// `requested`は指定された親 (省略した場合は`None`) で、指定された親が名前と食い違えばエラーにする
+fn path_parent(
+    name: &str,
+    found: Option<i32>,
+    requested: Option<Option<i32>>,
+) -> Result<i32, RepositoryError> {
+    match (found, requested) {
+        (Some(parent_id), None) => Ok(parent_id),
+        (Some(parent_id), Some(Some(requested))) if requested == parent_id => Ok(parent_id),
+        _ => Err(RepositoryError::InvalidLabelPath(name.trim().to_string())),
+    }
+}
+
+/// 正規化した名前を持たないラベル (`legacy`) に設定する値を求める。`taken`は使用済みの所有者と正規化した名前の組。
+/// 正規化すると重複するラベルは、最も古いもの以外をNULLのまま残すため対象にしない。
+/// 所有者のいない (所有者の導入前に作られた) ラベルは、それらの間で重複を判定する
+fn backfill_normalized_names(
+    taken: Vec<(Option<i32>, String)>,
+    legacy: Vec<(i32, Option<i32>, String)>,
+) -> Vec<(i32, String)> {
+    let mut taken: HashSet<(Option<i32>, String)> = taken.into_iter().collect();
+    legacy
+        .into_iter()
+        .filter_map(|(id, user_id, name)| {
+            let normalized_name = normalize_name(&name);
+            if taken.insert((user_id, normalized_name.clone())) {
+                Some((id, normalized_name))
+            } else {
+                tracing::warn!("label {id} duplicates the name of another label: {name:?}");
+                None
+            }
+        })
+        .collect()
+}
+
+/// `#rrggbb`形式の色だけを受け付ける
+fn validate_color(color: &str) -> Result<(), ValidationError> {
+    let hex = color.strip_prefix('#').unwrap_or_default();
+    if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        let mut error = ValidationError::new("color");
+        error.message = Some("Must be a hex color such as #1e90ff".into());
+        return Err(error);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Validate)]
 pub struct CreateLabel {
+    #[validate(length(min = 1, message = "Can not be empty"))]
+    #[validate(length(max = 50, message = "Over name length"))]
+    #[validate(custom = "validate_not_blank")]
     name: String,
+    #[serde(default)]
+    #[validate(custom = "validate_color")]
+    color: Option<String>,
+    #[serde(default)]
+    parent_id: Option<i32>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+impl CreateLabel {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            color: None,
+            parent_id: None,
+        }
+    }
+
+    pub fn with_parent(self, parent_id: i32) -> Self {
+        Self {
+            parent_id: Some(parent_id),
+            ..self
+        }
+    }
+
+    pub fn with_color(self, color: String) -> Self {
+        Self {
+            color: Some(color),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, Validate)]
 pub struct UpdateLabel {
-    id: i32,
-    name: String,
+    #[validate(length(min = 1, message = "Can not be empty"))]
+    #[validate(length(max = 50, message = "Over name length"))]
+    #[validate(custom = "validate_not_blank")]
+    name: Option<String>,
+    /// 省略した場合は変更せず、`null`の場合は色を外す
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[validate(custom = "validate_color")]
+    color: Option<Option<String>>,
+    /// 省略した場合は変更せず、`null`の場合は最上位のラベルにする
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    parent_id: Option<Option<i32>>,
+}
+
+impl UpdateLabel {
+    pub fn with_name(self, name: String) -> Self {
+        Self {
+            name: Some(name),
+            ..self
+        }
+    }
+
+    pub fn with_color(self, color: Option<String>) -> Self {
+        Self {
+            color: Some(color),
+            ..self
+        }
+    }
+
+    pub fn with_parent(self, parent_id: Option<i32>) -> Self {
+        Self {
+            parent_id: Some(parent_id),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MergeLabel {
+    source_id: i32,
+}
+
+impl MergeLabel {
+    pub fn new(source_id: i32) -> Self {
+        Self { source_id }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
 pub struct Label {
     id: i32,
     name: String,
+    color: Option<String>,
+    parent_id: Option<i32>,
 }
 
 impl Label {
-    fn new(id: i32, name: String) -> Self {
-        Self { id, name }
+    pub fn new(id: i32, name: String, color: Option<String>, parent_id: Option<i32>) -> Self {
+        Self {
+            id,
+            name,
+            color,
+            parent_id,
+        }
+    }
+
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+}
+
+/// 完了していないTodoを`open_todos`、完了したTodoを`closed_todos`に数える
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct LabelUsage {
+    #[serde(flatten)]
+    #[sqlx(flatten)]
+    label: Label,
+    open_todos: i64,
+    closed_todos: i64,
+}
+
+impl LabelUsage {
+    pub fn new(label: Label, open_todos: i64, closed_todos: i64) -> Self {
+        Self {
+            label,
+            open_todos,
+            closed_todos,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Validate)]
+pub struct LabelQuery {
+    /// ラベル毎のTodoの件数も返すか
+    #[serde(default)]
+    usage: bool,
+}
+
+impl LabelQuery {
+    pub fn usage(&self) -> bool {
+        self.usage
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Validate)]
+pub struct DeleteLabelQuery {
+    /// Todoに付いていてもラベルを外して削除するか
+    #[serde(default)]
+    force: bool,
+}
+
+impl DeleteLabelQuery {
+    pub fn force(&self) -> bool {
+        self.force
     }
 }
@@ -0,0 +1,688 @@
+//! 全ての`LabelRepository`の実装が満たすべき振る舞い
+
+use crate::repository::{
+    user::{conformance::create_user, UserRepository},
+    RepositoryError,
+};
+
+use super::{CreateLabel, LabelRepository, MergeLabel, UpdateLabel};
+
+pub(crate) async fn run<R: LabelRepository, U: UserRepository>(repository: R, user_repository: U) {
+    let owner = create_user(&user_repository, "[label] owner").await.id();
+    let other = create_user(&user_repository, "[label] other").await.id();
+
+    crud(&repository, owner).await;
+    ordering(&repository, owner).await;
+    not_found(&repository, owner).await;
+    duplicate(&repository, owner).await;
+    normalized_duplicate(&repository, owner).await;
+    update(&repository, owner).await;
+    hierarchy(&repository, owner).await;
+    paths(&repository, owner).await;
+    id_allocation(&repository, owner).await;
+    ownership(&repository, owner, other).await;
+}
+
+async fn crud<R: LabelRepository>(repository: &R, user_id: i32) {
+    let name = "[label::crud] name";
+
+    // create
+    let created = repository
+        .create(user_id, CreateLabel::new(name.to_string()))
+        .await
+        .expect("fail create label");
+    assert_eq!(created.name, name);
+
+    // all
+    let labels = repository
+        .all(user_id)
+        .await
+        .expect("fail fetch all labels");
+    assert!(labels.contains(&created), "created label is not listed");
+
+    // find
+    let found = repository
+        .find(user_id, created.id)
+        .await
+        .expect("fail find label");
+    assert_eq!(found, created);
+
+    // delete
+    repository
+        .delete(user_id, created.id, false)
+        .await
+        .expect("fail delete label");
+    let labels = repository
+        .all(user_id)
+        .await
+        .expect("fail fetch all labels");
+    assert!(!labels.contains(&created), "deleted label is still listed");
+}
+
+/// `all`はIDの昇順で返す
+async fn ordering<R: LabelRepository>(repository: &R, user_id: i32) {
+    let first = repository
+        .create(
+            user_id,
+            CreateLabel::new("[label::ordering] first".to_string()),
+        )
+        .await
+        .expect("fail create label");
+    let second = repository
+        .create(
+            user_id,
+            CreateLabel::new("[label::ordering] second".to_string()),
+        )
+        .await
+        .expect("fail create label");
+
+    let labels = repository
+        .all(user_id)
+        .await
+        .expect("fail fetch all labels");
+    assert!(
+        labels.windows(2).all(|pair| pair[0].id < pair[1].id),
+        "labels are not ordered by id ascending: {labels:?}"
+    );
+    let position = |id| labels.iter().position(|label| label.id == id);
+    assert!(position(first.id) < position(second.id));
+
+    repository.delete(user_id, first.id, false).await.unwrap();
+    repository.delete(user_id, second.id, false).await.unwrap();
+}
+
+async fn not_found<R: LabelRepository>(repository: &R, user_id: i32) {
+    let label = repository
+        .create(
+            user_id,
+            CreateLabel::new("[label::not_found] name".to_string()),
+        )
+        .await
+        .expect("fail create label");
+    repository.delete(user_id, label.id, false).await.unwrap();
+
+    let result = repository.delete(user_id, label.id, false).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == label.id as u32),
+        "unexpected result: {result:?}"
+    );
+    let result = repository.find(user_id, label.id).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == label.id as u32),
+        "unexpected result: {result:?}"
+    );
+    let result = repository
+        .update(
+            user_id,
+            label.id,
+            UpdateLabel::default().with_name("[label::not_found] renamed".to_string()),
+        )
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == label.id as u32),
+        "unexpected result: {result:?}"
+    );
+
+    // merge from or into a missing label
+    let existing = repository
+        .create(
+            user_id,
+            CreateLabel::new("[label::not_found] existing".to_string()),
+        )
+        .await
+        .expect("fail create label");
+    let result = repository
+        .merge(user_id, existing.id, MergeLabel::new(label.id))
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == label.id as u32),
+        "unexpected result: {result:?}"
+    );
+    let result = repository
+        .merge(user_id, label.id, MergeLabel::new(existing.id))
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == label.id as u32),
+        "unexpected result: {result:?}"
+    );
+    let result = repository
+        .merge(user_id, existing.id, MergeLabel::new(existing.id))
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::MergeIntoSelf(id)) if id == existing.id),
+        "unexpected result: {result:?}"
+    );
+    repository
+        .delete(user_id, existing.id, false)
+        .await
+        .unwrap();
+}
+
+/// 同名のラベルは既存のラベルのIDで重複エラーになる
+async fn duplicate<R: LabelRepository>(repository: &R, user_id: i32) {
+    let name = "[label::duplicate] name";
+    let label = repository
+        .create(user_id, CreateLabel::new(name.to_string()))
+        .await
+        .expect("fail create label");
+
+    let result = repository
+        .create(user_id, CreateLabel::new(name.to_string()))
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::Duplicate(id)) if id == label.id),
+        "unexpected result: {result:?}"
+    );
+
+    repository.delete(user_id, label.id, false).await.unwrap();
+}
+
+/// 前後の空白・互換文字・大文字小文字の違いは同じ名前とみなし、同時に作成しても重複しない
+async fn normalized_duplicate<R: LabelRepository>(repository: &R, user_id: i32) {
+    let label = repository
+        .create(
+            user_id,
+            CreateLabel::new("  [label::normalized] Straße \t".to_string()),
+        )
+        .await
+        .expect("fail create label");
+    assert_eq!(label.name, "[label::normalized] Straße");
+
+    for name in [
+        "[label::normalized] Straße",
+        "[label::normalized] STRASSE",
+        "[LABEL::NORMALIZED] strasse",
+        "［ｌａｂｅｌ::ｎｏｒｍａｌｉｚｅｄ］　ｓｔｒａｓｓｅ",
+    ] {
+        let result = repository
+            .create(user_id, CreateLabel::new(name.to_string()))
+            .await;
+        assert!(
+            matches!(result, Err(RepositoryError::Duplicate(id)) if id == label.id),
+            "unexpected result for {name}: {result:?}"
+        );
+    }
+
+    let other = repository
+        .create(
+            user_id,
+            CreateLabel::new("[label::normalized] other".to_string()),
+        )
+        .await
+        .expect("fail create label");
+    let result = repository
+        .update(
+            user_id,
+            other.id,
+            UpdateLabel::default().with_name("[label::normalized] STRASSE".to_string()),
+        )
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::Duplicate(id)) if id == label.id),
+        "unexpected result: {result:?}"
+    );
+    assert_eq!(repository.find(user_id, other.id).await.unwrap(), other);
+
+    // 自身の名前の表記だけを変えるのは重複にならない
+    let renamed = repository
+        .update(
+            user_id,
+            label.id,
+            UpdateLabel::default().with_name(" [label::normalized] STRASSE ".to_string()),
+        )
+        .await
+        .expect("fail update label");
+    assert_eq!(renamed.name, "[label::normalized] STRASSE");
+
+    // concurrent creation
+    let name = "[label::normalized] concurrent";
+    let (first, second) = tokio::join!(
+        repository.create(user_id, CreateLabel::new(name.to_string())),
+        repository.create(user_id, CreateLabel::new(name.to_uppercase())),
+    );
+    let created = match (first, second) {
+        (Ok(created), Err(RepositoryError::Duplicate(id)))
+        | (Err(RepositoryError::Duplicate(id)), Ok(created))
+            if id == created.id =>
+        {
+            created
+        }
+        result => panic!("unexpected result: {result:?}"),
+    };
+
+    for label in [label, other, created] {
+        repository.delete(user_id, label.id, false).await.unwrap();
+    }
+}
+
+/// 名前と色は個別に変更でき、他のラベルと同名には変更できない
+async fn update<R: LabelRepository>(repository: &R, user_id: i32) {
+    let label = repository
+        .create(
+            user_id,
+            CreateLabel::new("[label::update] name".to_string()).with_color("#1E90FF".to_string()),
+        )
+        .await
+        .expect("fail create label");
+    assert_eq!(label.color.as_deref(), Some("#1E90FF"));
+    let other = repository
+        .create(
+            user_id,
+            CreateLabel::new("[label::update] other".to_string()),
+        )
+        .await
+        .expect("fail create label");
+    assert_eq!(other.color, None);
+
+    // rename
+    let renamed = repository
+        .update(
+            user_id,
+            label.id,
+            UpdateLabel::default().with_name("[label::update] renamed".to_string()),
+        )
+        .await
+        .expect("fail update label");
+    assert_eq!(renamed.name, "[label::update] renamed");
+    assert_eq!(renamed.color.as_deref(), Some("#1E90FF"));
+
+    // recolor
+    let recolored = repository
+        .update(
+            user_id,
+            label.id,
+            UpdateLabel::default().with_color(Some("#ff0000".to_string())),
+        )
+        .await
+        .expect("fail update label");
+    assert_eq!(recolored.name, "[label::update] renamed");
+    assert_eq!(recolored.color.as_deref(), Some("#ff0000"));
+    assert_eq!(repository.find(user_id, label.id).await.unwrap(), recolored);
+
+    // 同じ名前のままの変更は重複にならない
+    let uncolored = repository
+        .update(
+            user_id,
+            label.id,
+            UpdateLabel::default()
+                .with_name("[label::update] renamed".to_string())
+                .with_color(None),
+        )
+        .await
+        .expect("fail update label");
+    assert_eq!(uncolored.color, None);
+
+    let result = repository
+        .update(
+            user_id,
+            label.id,
+            UpdateLabel::default().with_name(other.name.clone()),
+        )
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::Duplicate(id)) if id == other.id),
+        "unexpected result: {result:?}"
+    );
+    assert_eq!(repository.find(user_id, label.id).await.unwrap(), uncolored);
+
+    repository.delete(user_id, label.id, false).await.unwrap();
+    repository.delete(user_id, other.id, false).await.unwrap();
+}
+
+/// 親は自身や子孫にはできず、削除や統合で子のラベルを付け替える
+async fn hierarchy<R: LabelRepository>(repository: &R, user_id: i32) {
+    let create = |name: &str, parent_id: Option<i32>| {
+        let payload = CreateLabel::new(format!("[label::hierarchy] {name}"));
+        let payload = match parent_id {
+            Some(parent_id) => payload.with_parent(parent_id),
+            None => payload,
+        };
+        async move {
+            repository
+                .create(user_id, payload)
+                .await
+                .expect("fail create label")
+        }
+    };
+    let parent_of = |id: i32| async move { repository.find(user_id, id).await.unwrap().parent_id };
+    let area = create("area", None).await;
+    let backend = create("area/backend", Some(area.id)).await;
+    let db = create("area/backend/db", Some(backend.id)).await;
+    let frontend = create("area/frontend", Some(area.id)).await;
+    let misc = create("misc", None).await;
+    assert_eq!(backend.parent_id, Some(area.id));
+
+    // subtree
+    let subtree = repository.subtree(user_id, area.id).await.unwrap();
+    assert_eq!(
+        subtree,
+        vec![area.clone(), backend.clone(), db.clone(), frontend.clone()]
+    );
+    let subtree = repository.subtree(user_id, backend.id).await.unwrap();
+    assert_eq!(subtree, vec![backend.clone(), db.clone()]);
+    let subtree = repository.subtree(user_id, db.id).await.unwrap();
+    assert_eq!(subtree, vec![db.clone()]);
+
+    // missing parent
+    let deleted = create("deleted", None).await;
+    repository.delete(user_id, deleted.id, false).await.unwrap();
+    let result = repository
+        .create(
+            user_id,
+            CreateLabel::new("[label::hierarchy] orphan".to_string()).with_parent(deleted.id),
+        )
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == deleted.id as u32),
+        "unexpected result: {result:?}"
+    );
+    let result = repository
+        .update(
+            user_id,
+            misc.id,
+            UpdateLabel::default().with_parent(Some(deleted.id)),
+        )
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == deleted.id as u32),
+        "unexpected result: {result:?}"
+    );
+    let result = repository.subtree(user_id, deleted.id).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == deleted.id as u32),
+        "unexpected result: {result:?}"
+    );
+
+    // cycles
+    for parent_id in [area.id, db.id] {
+        let result = repository
+            .update(
+                user_id,
+                area.id,
+                UpdateLabel::default().with_parent(Some(parent_id)),
+            )
+            .await;
+        assert!(
+            matches!(result, Err(RepositoryError::LabelCycle { id, parent_id: parent }) if id == area.id && parent == parent_id),
+            "unexpected result: {result:?}"
+        );
+    }
+    assert_eq!(parent_of(area.id).await, None);
+
+    // move and detach
+    let renamed = repository
+        .update(
+            user_id,
+            frontend.id,
+            UpdateLabel::default().with_name("[label::hierarchy] frontend".to_string()),
+        )
+        .await
+        .expect("fail update label");
+    assert_eq!(renamed.parent_id, Some(area.id));
+    let moved = repository
+        .update(
+            user_id,
+            frontend.id,
+            UpdateLabel::default().with_parent(Some(backend.id)),
+        )
+        .await
+        .expect("fail update label");
+    assert_eq!(moved.parent_id, Some(backend.id));
+    let detached = repository
+        .update(
+            user_id,
+            frontend.id,
+            UpdateLabel::default().with_parent(None),
+        )
+        .await
+        .expect("fail update label");
+    assert_eq!(detached.parent_id, None);
+
+    // children of a deleted label move to its parent
+    repository.delete(user_id, backend.id, false).await.unwrap();
+    assert_eq!(parent_of(db.id).await, Some(area.id));
+
+    // children of a merged label move to the target
+    let result = repository
+        .merge(user_id, db.id, MergeLabel::new(area.id))
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::LabelCycle { id, parent_id }) if id == area.id && parent_id == db.id),
+        "unexpected result: {result:?}"
+    );
+    repository
+        .merge(user_id, misc.id, MergeLabel::new(area.id))
+        .await
+        .expect("fail merge labels");
+    assert_eq!(parent_of(db.id).await, Some(misc.id));
+
+    for label in [db, frontend, misc] {
+        repository.delete(user_id, label.id, false).await.unwrap();
+    }
+}
+
+/// `/`で区切った名前のラベルは、名前から求めた親の子になる
+async fn paths<R: LabelRepository>(repository: &R, user_id: i32) {
+    let create = |name: &str, parent_id: Option<i32>| {
+        let payload = CreateLabel::new(format!("[label::paths] {name}"));
+        let payload = match parent_id {
+            Some(parent_id) => payload.with_parent(parent_id),
+            None => payload,
+        };
+        async move { repository.create(user_id, payload).await }
+    };
+    let update = |id: i32, payload: UpdateLabel| async move {
+        repository.update(user_id, id, payload).await
+    };
+    let area = create("area", None).await.expect("fail create label");
+    let backend = create("area/backend", None)
+        .await
+        .expect("fail create label");
+    let db = create("area/backend/db", Some(backend.id))
+        .await
+        .expect("fail create label");
+    // 親の名前も正規化して探す
+    let frontend = create("ＡＲＥＡ/frontend", None)
+        .await
+        .expect("fail create label");
+    assert_eq!(area.parent_id, None);
+    assert_eq!(backend.parent_id, Some(area.id));
+    assert_eq!(db.parent_id, Some(backend.id));
+    assert_eq!(frontend.parent_id, Some(area.id));
+
+    // the parent must exist and match the path
+    for (name, parent_id) in [
+        ("area/missing/db", None),
+        ("area/backend/api", Some(area.id)),
+        ("area//api", None),
+    ] {
+        let result = create(name, parent_id).await;
+        assert!(
+            matches!(&result, Err(RepositoryError::InvalidLabelPath(path)) if *path == format!("[label::paths] {name}")),
+            "unexpected result: {result:?}"
+        );
+    }
+    for payload in [
+        UpdateLabel::default().with_parent(Some(area.id)),
+        UpdateLabel::default().with_parent(None),
+        UpdateLabel::default().with_name("[label::paths] area/missing/db".to_string()),
+        UpdateLabel::default()
+            .with_name("[label::paths] area/db".to_string())
+            .with_parent(Some(backend.id)),
+    ] {
+        let result = update(db.id, payload).await;
+        assert!(
+            matches!(result, Err(RepositoryError::InvalidLabelPath(_))),
+            "unexpected result: {result:?}"
+        );
+    }
+    let label = repository.find(user_id, db.id).await.unwrap();
+    assert_eq!(label, db);
+
+    // renaming derives the parent from the new name
+    let renamed = update(
+        db.id,
+        UpdateLabel::default().with_name("[label::paths] area/db".to_string()),
+    )
+    .await
+    .expect("fail update label");
+    assert_eq!(renamed.parent_id, Some(area.id));
+    let renamed = update(
+        backend.id,
+        UpdateLabel::default().with_name("[label::paths] area/db/backend".to_string()),
+    )
+    .await
+    .expect("fail update label");
+    assert_eq!(renamed.parent_id, Some(db.id));
+
+    // a path can not create a cycle
+    let result = update(
+        area.id,
+        UpdateLabel::default().with_name("[label::paths] area/db/area".to_string()),
+    )
+    .await;
+    assert!(
+        matches!(result, Err(RepositoryError::LabelCycle { id, parent_id }) if id == area.id && parent_id == db.id),
+        "unexpected result: {result:?}"
+    );
+
+    // a name without a path keeps the parent until it is changed
+    let renamed = update(
+        backend.id,
+        UpdateLabel::default().with_name("[label::paths] backend".to_string()),
+    )
+    .await
+    .expect("fail update label");
+    assert_eq!(renamed.parent_id, Some(db.id));
+    let detached = update(backend.id, UpdateLabel::default().with_parent(None))
+        .await
+        .expect("fail update label");
+    assert_eq!(detached.parent_id, None);
+
+    for label in [backend, db, frontend, area] {
+        repository.delete(user_id, label.id, false).await.unwrap();
+    }
+}
+
+/// 削除されたIDは再利用されない
+async fn id_allocation<R: LabelRepository>(repository: &R, user_id: i32) {
+    let first = repository
+        .create(
+            user_id,
+            CreateLabel::new("[label::id_allocation] first".to_string()),
+        )
+        .await
+        .expect("fail create label");
+    let second = repository
+        .create(
+            user_id,
+            CreateLabel::new("[label::id_allocation] second".to_string()),
+        )
+        .await
+        .expect("fail create label");
+    assert!(first.id < second.id);
+
+    repository.delete(user_id, second.id, false).await.unwrap();
+    let third = repository
+        .create(
+            user_id,
+            CreateLabel::new("[label::id_allocation] third".to_string()),
+        )
+        .await
+        .expect("fail create label");
+    assert!(second.id < third.id);
+
+    repository.delete(user_id, first.id, false).await.unwrap();
+    repository.delete(user_id, third.id, false).await.unwrap();
+}
+
+/// 他のユーザーのラベルは存在しないものとして扱い、同名のラベルも作成できる
+async fn ownership<R: LabelRepository>(repository: &R, owner: i32, other: i32) {
+    let name = "[label::ownership] name";
+    let label = repository
+        .create(owner, CreateLabel::new(name.to_string()))
+        .await
+        .expect("fail create label");
+
+    let labels = repository.all(other).await.expect("fail fetch all labels");
+    assert!(!labels.contains(&label), "other user's label is listed");
+    let result = repository.find(other, label.id).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == label.id as u32),
+        "unexpected result: {result:?}"
+    );
+    let result = repository
+        .update(
+            other,
+            label.id,
+            UpdateLabel::default().with_color(Some("#000000".to_string())),
+        )
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == label.id as u32),
+        "unexpected result: {result:?}"
+    );
+
+    let result = repository.delete(other, label.id, false).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == label.id as u32),
+        "unexpected result: {result:?}"
+    );
+    let labels = repository.all(owner).await.expect("fail fetch all labels");
+    assert!(labels.contains(&label), "label is deleted by other user");
+
+    let same_name = repository
+        .create(other, CreateLabel::new(name.to_string()))
+        .await
+        .expect("fail create label with the same name as other user's");
+    assert_ne!(label.id, same_name.id);
+
+    // other user's label can not be a parent
+    let result = repository
+        .create(
+            other,
+            CreateLabel::new("[label::ownership] child".to_string()).with_parent(label.id),
+        )
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == label.id as u32),
+        "unexpected result: {result:?}"
+    );
+    let result = repository
+        .update(
+            other,
+            same_name.id,
+            UpdateLabel::default().with_parent(Some(label.id)),
+        )
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == label.id as u32),
+        "unexpected result: {result:?}"
+    );
+    let result = repository.subtree(other, label.id).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == label.id as u32),
+        "unexpected result: {result:?}"
+    );
+
+    // other user's label can not be merged in either direction
+    let result = repository
+        .merge(other, same_name.id, MergeLabel::new(label.id))
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == label.id as u32),
+        "unexpected result: {result:?}"
+    );
+    let result = repository
+        .merge(other, label.id, MergeLabel::new(same_name.id))
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == label.id as u32),
+        "unexpected result: {result:?}"
+    );
+    let labels = repository.all(owner).await.expect("fail fetch all labels");
+    assert!(labels.contains(&label), "label is merged by other user");
+
+    repository.delete(owner, label.id, false).await.unwrap();
+    repository.delete(other, same_name.id, false).await.unwrap();
+}
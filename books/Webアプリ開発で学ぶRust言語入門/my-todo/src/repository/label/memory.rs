@@ -7,9 +7,32 @@ use axum::async_trait;
 
 use crate::repository::RepositoryError;
 
-use super::{CreateLabel, Label, LabelRepository};
+use super::{
+    normalize_name, parent_path, path_parent, CreateLabel, Label, LabelRepository, LabelUsage,
+    MergeLabel, UpdateLabel,
+};
 
-type LabelData = HashMap<i32, Label>;
+#[derive(Debug, Clone)]
+struct LabelRecord {
+    user_id: i32,
+    normalized_name: String,
+    label: Label,
+}
+
+/// Todoに付いているラベルと、そのTodoが完了しているか
+#[derive(Debug, Clone, Default)]
+struct TodoLabels {
+    labels: Vec<i32>,
+    done: bool,
+}
+
+#[derive(Debug, Default)]
+struct LabelData {
+    labels: HashMap<i32, LabelRecord>,
+    /// Todo毎のラベル。Todoのラベルはこちらで管理する
+    todos: HashMap<u32, TodoLabels>,
+    last_id: i32,
+}
 
 #[derive(Debug, Clone, Default)]
 pub struct LabelRepositoryForMemory {
@@ -23,35 +46,349 @@ impl LabelRepositoryForMemory {
         }
     }
 
-    fn write_store_ref(&self) -> RwLockWriteGuard<LabelData> {
+    fn write_store_ref(&self) -> RwLockWriteGuard<'_, LabelData> {
         self.store.write().unwrap()
     }
 
-    fn read_store_ref(&self) -> RwLockReadGuard<LabelData> {
+    fn read_store_ref(&self) -> RwLockReadGuard<'_, LabelData> {
         self.store.read().unwrap()
     }
+
+    /// 指定されたIDのうち`user_id`のユーザーが所有するラベルをID順に取得する
+    pub(crate) fn select(&self, user_id: i32, ids: &[i32]) -> Vec<Label> {
+        Self::select_from(&self.read_store_ref(), user_id, ids)
+    }
+
+    fn select_from(store: &LabelData, user_id: i32, ids: &[i32]) -> Vec<Label> {
+        let mut labels: Vec<Label> = ids
+            .iter()
+            .filter_map(|id| store.labels.get(id))
+            .filter(|record| record.user_id == user_id)
+            .map(|record| record.label.clone())
+            .collect();
+        labels.sort_by_key(|label| label.id);
+        labels
+    }
+
+    /// `id`のラベルとその子孫のラベルのIDを取得する。ラベルがなければ空になる
+    pub(crate) fn subtree_ids(&self, id: i32) -> Vec<i32> {
+        Self::subtree_from(&self.read_store_ref(), id)
+    }
+
+    fn subtree_from(store: &LabelData, id: i32) -> Vec<i32> {
+        if !store.labels.contains_key(&id) {
+            return Vec::new();
+        }
+        let mut ids = vec![id];
+        let mut index = 0;
+        while let Some(parent_id) = ids.get(index).copied() {
+            ids.extend(
+                store
+                    .labels
+                    .values()
+                    .filter(|record| record.label.parent_id == Some(parent_id))
+                    .map(|record| record.label.id),
+            );
+            index += 1;
+        }
+        ids
+    }
+
+    /// 親に指定されたラベルを`user_id`のユーザーが所有しているか確かめる
+    fn check_parent(
+        store: &LabelData,
+        user_id: i32,
+        parent_id: i32,
+    ) -> Result<(), RepositoryError> {
+        store
+            .labels
+            .get(&parent_id)
+            .filter(|record| record.user_id == user_id)
+            .map(|_| ())
+            .ok_or(RepositoryError::NotFound(parent_id as u32))
+    }
+
+    /// `user_id`のユーザーが所有する、正規化すると`name`と同じ名前のラベルを探す
+    fn find_by_name(store: &LabelData, user_id: i32, name: &str) -> Option<i32> {
+        let normalized_name = normalize_name(name);
+        store
+            .labels
+            .values()
+            .find(|record| record.user_id == user_id && record.normalized_name == normalized_name)
+            .map(|record| record.label.id)
+    }
+
+    /// `parent_id`の子のラベルを`new_parent_id`の子に付け替える
+    fn reparent(store: &mut LabelData, parent_id: i32, new_parent_id: Option<i32>) {
+        for record in store.labels.values_mut() {
+            if record.label.parent_id == Some(parent_id) {
+                record.label.parent_id = new_parent_id;
+            }
+        }
+    }
+
+    /// Todoに付いているラベルを取得する
+    pub(crate) fn todo_labels(&self, user_id: i32, todo_id: u32) -> Vec<Label> {
+        let store = self.read_store_ref();
+        match store.todos.get(&todo_id) {
+            Some(todo) => Self::select_from(&store, user_id, &todo.labels),
+            None => Vec::new(),
+        }
+    }
+
+    /// Todoに付いているラベルを置き換え、元のラベルを返す
+    pub(crate) fn set_labels(&self, todo_id: u32, labels: Vec<i32>) -> Vec<i32> {
+        let mut store = self.write_store_ref();
+        std::mem::replace(&mut store.todos.entry(todo_id).or_default().labels, labels)
+    }
+
+    /// ラベル毎の件数に使うため、Todoが完了したかを記録する
+    pub(crate) fn set_done(&self, todo_id: u32, done: bool) {
+        let mut store = self.write_store_ref();
+        store.todos.entry(todo_id).or_default().done = done;
+    }
+
+    /// 削除されたTodoの記録を消す
+    pub(crate) fn untrack(&self, todo_ids: &[u32]) {
+        let mut store = self.write_store_ref();
+        for id in todo_ids {
+            store.todos.remove(id);
+        }
+    }
 }
 
 #[async_trait]
 impl LabelRepository for LabelRepositoryForMemory {
-    async fn all(&self) -> Result<Vec<Label>, RepositoryError> {
+    async fn all(&self, user_id: i32) -> Result<Vec<Label>, RepositoryError> {
+        let store = self.read_store_ref();
+        let mut labels: Vec<Label> = store
+            .labels
+            .values()
+            .filter(|record| record.user_id == user_id)
+            .map(|record| record.label.clone())
+            .collect();
+        labels.sort_by_key(|label| label.id);
+        Ok(labels)
+    }
+
+    async fn usage(&self, user_id: i32) -> Result<Vec<LabelUsage>, RepositoryError> {
+        let store = self.read_store_ref();
+        let mut labels: Vec<LabelUsage> = store
+            .labels
+            .values()
+            .filter(|record| record.user_id == user_id)
+            .map(|record| {
+                let todos = store
+                    .todos
+                    .values()
+                    .filter(|todo| todo.labels.contains(&record.label.id));
+                let (closed, open): (Vec<_>, Vec<_>) = todos.partition(|todo| todo.done);
+                LabelUsage::new(record.label.clone(), open.len() as i64, closed.len() as i64)
+            })
+            .collect();
+        labels.sort_by_key(|usage| usage.label.id);
+        Ok(labels)
+    }
+
+    async fn find(&self, user_id: i32, id: i32) -> Result<Label, RepositoryError> {
         let store = self.read_store_ref();
-        Ok(store.values().cloned().collect())
+        store
+            .labels
+            .get(&id)
+            .filter(|record| record.user_id == user_id)
+            .map(|record| record.label.clone())
+            .ok_or(RepositoryError::NotFound(id as u32))
+    }
+
+    async fn subtree(&self, user_id: i32, id: i32) -> Result<Vec<Label>, RepositoryError> {
+        let store = self.read_store_ref();
+        store
+            .labels
+            .get(&id)
+            .filter(|record| record.user_id == user_id)
+            .ok_or(RepositoryError::NotFound(id as u32))?;
+        Ok(Self::select_from(
+            &store,
+            user_id,
+            &Self::subtree_from(&store, id),
+        ))
     }
 
-    async fn create(&self, payload: CreateLabel) -> Result<Label, RepositoryError> {
+    async fn create(&self, user_id: i32, payload: CreateLabel) -> Result<Label, RepositoryError> {
         let mut store = self.write_store_ref();
-        let id = (store.len() + 1) as i32;
-        let label = Label::new(id, payload.name);
-        store.insert(id, label.clone());
+        let parent_id = match parent_path(&payload.name) {
+            Some(path) => {
+                let found = Self::find_by_name(&store, user_id, path);
+                Some(path_parent(
+                    &payload.name,
+                    found,
+                    payload.parent_id.map(Some),
+                )?)
+            }
+            None => payload.parent_id,
+        };
+        if let Some(parent_id) = parent_id {
+            Self::check_parent(&store, user_id, parent_id)?;
+        }
+        let normalized_name = normalize_name(&payload.name);
+        if let Some(record) = store
+            .labels
+            .values()
+            .find(|record| record.user_id == user_id && record.normalized_name == normalized_name)
+        {
+            return Err(RepositoryError::Duplicate(record.label.id));
+        }
+
+        store.last_id += 1;
+        let id = store.last_id;
+        let label = Label::new(
+            id,
+            payload.name.trim().to_string(),
+            payload.color,
+            parent_id,
+        );
+        store.labels.insert(
+            id,
+            LabelRecord {
+                user_id,
+                normalized_name,
+                label: label.clone(),
+            },
+        );
         Ok(label)
     }
 
-    async fn delete(&self, id: i32) -> Result<(), RepositoryError> {
+    async fn update(
+        &self,
+        user_id: i32,
+        id: i32,
+        payload: UpdateLabel,
+    ) -> Result<Label, RepositoryError> {
+        let mut store = self.write_store_ref();
+        let current = store
+            .labels
+            .get(&id)
+            .filter(|record| record.user_id == user_id)
+            .ok_or(RepositoryError::NotFound(id as u32))?;
+        let name = payload.name.as_deref().unwrap_or(&current.label.name);
+        let parent_id = match parent_path(name) {
+            Some(path) if payload.name.is_some() || payload.parent_id.is_some() => {
+                let found = Self::find_by_name(&store, user_id, path);
+                Some(Some(path_parent(name, found, payload.parent_id)?))
+            }
+            _ => payload.parent_id,
+        };
+        if let Some(Some(parent_id)) = parent_id {
+            Self::check_parent(&store, user_id, parent_id)?;
+            if Self::subtree_from(&store, id).contains(&parent_id) {
+                return Err(RepositoryError::LabelCycle { id, parent_id });
+            }
+        }
+        let normalized_name = payload.name.as_deref().map(normalize_name);
+        if let Some(normalized_name) = &normalized_name {
+            if let Some(record) = store.labels.values().find(|record| {
+                record.user_id == user_id
+                    && record.label.id != id
+                    && record.normalized_name == *normalized_name
+            }) {
+                return Err(RepositoryError::Duplicate(record.label.id));
+            }
+        }
+
+        let record = store.labels.get_mut(&id).unwrap();
+        if let Some(normalized_name) = normalized_name {
+            record.normalized_name = normalized_name;
+        }
+        let label = &mut record.label;
+        if let Some(name) = payload.name {
+            label.name = name.trim().to_string();
+        }
+        if let Some(color) = payload.color {
+            label.color = color;
+        }
+        if let Some(parent_id) = parent_id {
+            label.parent_id = parent_id;
+        }
+        Ok(label.clone())
+    }
+
+    async fn merge(
+        &self,
+        user_id: i32,
+        id: i32,
+        payload: MergeLabel,
+    ) -> Result<Label, RepositoryError> {
+        let source_id = payload.source_id;
+        let mut store = self.write_store_ref();
+        for label_id in [id, source_id] {
+            store
+                .labels
+                .get(&label_id)
+                .filter(|record| record.user_id == user_id)
+                .ok_or(RepositoryError::NotFound(label_id as u32))?;
+        }
+        if id == source_id {
+            return Err(RepositoryError::MergeIntoSelf(id));
+        }
+        if Self::subtree_from(&store, source_id).contains(&id) {
+            return Err(RepositoryError::LabelCycle {
+                id: source_id,
+                parent_id: id,
+            });
+        }
+
+        store.labels.remove(&source_id);
+        Self::reparent(&mut store, source_id, Some(id));
+        for todo in store.todos.values_mut() {
+            if !todo.labels.contains(&source_id) {
+                continue;
+            }
+            todo.labels.retain(|label| *label != source_id);
+            if !todo.labels.contains(&id) {
+                todo.labels.push(id);
+                todo.labels.sort();
+            }
+        }
+        Ok(store.labels[&id].label.clone())
+    }
+
+    async fn delete(&self, user_id: i32, id: i32, force: bool) -> Result<(), RepositoryError> {
         let mut store = self.write_store_ref();
         store
-            .remove(&id)
+            .labels
+            .get(&id)
+            .filter(|record| record.user_id == user_id)
             .ok_or(RepositoryError::NotFound(id as u32))?;
+        let todos = store
+            .todos
+            .values()
+            .filter(|todo| todo.labels.contains(&id))
+            .count() as i64;
+        if todos > 0 && !force {
+            return Err(RepositoryError::LabelInUse { id, todos });
+        }
+
+        let parent_id = store.labels.remove(&id).unwrap().label.parent_id;
+        Self::reparent(&mut store, id, parent_id);
+        for todo in store.todos.values_mut() {
+            todo.labels.retain(|label| *label != id);
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::repository::{label::conformance, user::UserRepositoryForMemory};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn conformance() {
+        conformance::run(
+            LabelRepositoryForMemory::new(),
+            UserRepositoryForMemory::new(),
+        )
+        .await;
+    }
+}
@@ -1,9 +1,12 @@
 use axum::async_trait;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 
 use crate::repository::RepositoryError;
 
-use super::{CreateLabel, Label, LabelRepository};
+use super::{
+    backfill_normalized_names, normalize_name, parent_path, path_parent, CreateLabel, Label,
+    LabelRepository, LabelUsage, MergeLabel, UpdateLabel,
+};
 
 #[derive(Debug, Clone)]
 pub struct LabelRepositoryForPostgres {
@@ -14,18 +17,97 @@ impl LabelRepositoryForPostgres {
     pub fn new(pool: PgPool) -> Self {
         Self { pool }
     }
+
+    /// 正規化した名前を持たないラベルに`normalize_name`で値を設定する。
+    /// 移行前から正規化すると重複していたラベルは、最も古いもの以外をNULLのまま残す
+    pub async fn normalize_names(&self) -> Result<(), RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(handle_sqlx_error)?;
+        // 同時に作成されたラベルと重複しないよう、値を求めて設定するまで他の書き込みを止める
+        sqlx::query("LOCK TABLE label IN SHARE ROW EXCLUSIVE MODE;")
+            .execute(&mut *tx)
+            .await
+            .map_err(handle_sqlx_error)?;
+
+        let taken = sqlx::query_as::<_, (Option<i32>, String)>(
+            r#"
+                SELECT user_id, normalized_name
+                FROM label
+                WHERE normalized_name IS NOT NULL;
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?;
+        let legacy = sqlx::query_as::<_, (i32, Option<i32>, String)>(
+            r#"
+                SELECT id, user_id, name
+                FROM label
+                WHERE normalized_name IS NULL
+                ORDER BY id;
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        for (id, normalized_name) in backfill_normalized_names(taken, legacy) {
+            sqlx::query(
+                r#"
+                    UPDATE label
+                    SET normalized_name = $1
+                    WHERE id = $2;
+                "#,
+            )
+            .bind(normalized_name)
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(handle_sqlx_error)?;
+        }
+
+        tx.commit().await.map_err(handle_sqlx_error)
+    }
+
+    /// 一意制約に違反した場合に、同じ名前のラベルのIDで重複エラーにする
+    async fn duplicate(
+        &self,
+        user_id: i32,
+        normalized_name: &str,
+        error: sqlx::Error,
+    ) -> RepositoryError {
+        let found = sqlx::query_scalar::<_, i32>(
+            r#"
+                SELECT id
+                FROM label
+                WHERE user_id = $1
+                  AND normalized_name = $2;
+            "#,
+        )
+        .bind(user_id)
+        .bind(normalized_name)
+        .fetch_optional(&self.pool)
+        .await;
+
+        match found {
+            Ok(Some(id)) => RepositoryError::Duplicate(id),
+            Ok(None) => handle_sqlx_error(error),
+            Err(error) => handle_sqlx_error(error),
+        }
+    }
 }
 
 #[async_trait]
 impl LabelRepository for LabelRepositoryForPostgres {
-    async fn all(&self) -> Result<Vec<Label>, RepositoryError> {
+    async fn all(&self, user_id: i32) -> Result<Vec<Label>, RepositoryError> {
         let labels = sqlx::query_as::<_, Label>(
             r#"
                 SELECT *
                 FROM label
+                WHERE user_id = $1
                 ORDER BY id ASC;
             "#,
         )
+        .bind(user_id)
         .fetch_all(&self.pool)
         .await
         .map_err(handle_sqlx_error)?;
@@ -33,95 +115,591 @@ impl LabelRepository for LabelRepositoryForPostgres {
         Ok(labels)
     }
 
-    async fn create(&self, payload: CreateLabel) -> Result<Label, RepositoryError> {
+    async fn usage(&self, user_id: i32) -> Result<Vec<LabelUsage>, RepositoryError> {
+        let labels = sqlx::query_as::<_, LabelUsage>(
+            r#"
+                SELECT label.*,
+                       COUNT(todo.id) FILTER (WHERE todo.status <> 'done') AS open_todos,
+                       COUNT(todo.id) FILTER (WHERE todo.status = 'done')  AS closed_todos
+                FROM label
+                         LEFT OUTER JOIN todo_labels ON todo_labels.label_id = label.id
+                         LEFT OUTER JOIN todo ON todo.id = todo_labels.todo_id
+                WHERE label.user_id = $1
+                GROUP BY label.id
+                ORDER BY label.id ASC;
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        Ok(labels)
+    }
+
+    async fn find(&self, user_id: i32, id: i32) -> Result<Label, RepositoryError> {
         let label = sqlx::query_as::<_, Label>(
             r#"
                 SELECT *
                 FROM label
-                WHERE name = $1;
+                WHERE id = $1
+                  AND user_id = $2;
             "#,
         )
-        .bind(&payload.name)
+        .bind(id)
+        .bind(user_id)
         .fetch_optional(&self.pool)
         .await
+        .map_err(handle_sqlx_error)?
+        .ok_or(RepositoryError::NotFound(id as u32))?;
+
+        Ok(label)
+    }
+
+    async fn subtree(&self, user_id: i32, id: i32) -> Result<Vec<Label>, RepositoryError> {
+        let labels = sqlx::query_as::<_, Label>(
+            r#"
+                WITH RECURSIVE subtree (id) AS (
+                    SELECT id
+                    FROM label
+                    WHERE id = $1
+                      AND user_id = $2
+                    UNION
+                    SELECT label.id
+                    FROM label
+                        INNER JOIN subtree ON label.parent_id = subtree.id
+                )
+                SELECT label.*
+                FROM label
+                    INNER JOIN subtree ON label.id = subtree.id
+                ORDER BY label.id ASC;
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
         .map_err(handle_sqlx_error)?;
+        if labels.is_empty() {
+            return Err(RepositoryError::NotFound(id as u32));
+        }
+
+        Ok(labels)
+    }
+
+    async fn create(&self, user_id: i32, payload: CreateLabel) -> Result<Label, RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(handle_sqlx_error)?;
 
-        if let Some(label) = label {
-            return Err(RepositoryError::Duplicate(label.id));
+        let parent_id = match parent_path(&payload.name) {
+            Some(path) => {
+                let found = find_by_name(&mut tx, user_id, path).await?;
+                Some(path_parent(
+                    &payload.name,
+                    found,
+                    payload.parent_id.map(Some),
+                )?)
+            }
+            None => payload.parent_id,
+        };
+        if let Some(parent_id) = parent_id {
+            lock_parent(&mut tx, user_id, parent_id).await?;
         }
 
-        let label = sqlx::query_as::<_, Label>(
+        let normalized_name = normalize_name(&payload.name);
+        let result = sqlx::query_as::<_, Label>(
             r#"
-                INSERT INTO label (name)
-                VALUES ($1)
+                INSERT INTO label (name, normalized_name, user_id, color, parent_id)
+                VALUES ($1, $2, $3, $4, $5)
                 RETURNING *;
             "#,
         )
-        .bind(&payload.name)
-        .fetch_one(&self.pool)
+        .bind(payload.name.trim())
+        .bind(&normalized_name)
+        .bind(user_id)
+        .bind(&payload.color)
+        .bind(parent_id)
+        .fetch_one(&mut *tx)
+        .await;
+        let label = match result {
+            Err(error) if is_unique_violation(&error) => {
+                tx.rollback().await.map_err(handle_sqlx_error)?;
+                return Err(self.duplicate(user_id, &normalized_name, error).await);
+            }
+            result => result.map_err(handle_sqlx_error)?,
+        };
+
+        tx.commit().await.map_err(handle_sqlx_error)?;
+
+        Ok(label)
+    }
+
+    async fn update(
+        &self,
+        user_id: i32,
+        id: i32,
+        payload: UpdateLabel,
+    ) -> Result<Label, RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(handle_sqlx_error)?;
+
+        // 名前から親を求める場合も親が変わりうる
+        if payload.parent_id.flatten().is_some()
+            || payload.name.as_deref().and_then(parent_path).is_some()
+        {
+            lock_tree(&mut tx, user_id).await?;
+        }
+        let current_name = sqlx::query_scalar::<_, String>(
+            r#"
+                SELECT name
+                FROM label
+                WHERE id = $1
+                  AND user_id = $2
+                FOR UPDATE;
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
         .await
-        .map_err(handle_sqlx_error)?;
+        .map_err(handle_sqlx_error)?
+        .ok_or(RepositoryError::NotFound(id as u32))?;
+        let name = payload.name.as_deref().unwrap_or(&current_name);
+        let parent_id = match parent_path(name) {
+            Some(path) if payload.name.is_some() || payload.parent_id.is_some() => {
+                let found = find_by_name(&mut tx, user_id, path).await?;
+                Some(Some(path_parent(name, found, payload.parent_id)?))
+            }
+            _ => payload.parent_id,
+        };
+        if let Some(Some(parent_id)) = parent_id {
+            lock_parent(&mut tx, user_id, parent_id).await?;
+            if in_subtree(&mut tx, id, parent_id).await? {
+                return Err(RepositoryError::LabelCycle { id, parent_id });
+            }
+        }
+
+        let normalized_name = payload.name.as_deref().map(normalize_name);
+        let result = sqlx::query_as::<_, Label>(
+            r#"
+                UPDATE label
+                SET name            = COALESCE($3, name),
+                    normalized_name = COALESCE($4, normalized_name),
+                    color           = CASE WHEN $5 THEN $6 ELSE color END,
+                    parent_id       = CASE WHEN $7 THEN $8 ELSE parent_id END
+                WHERE id = $1
+                  AND user_id = $2
+                RETURNING *;
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(payload.name.as_deref().map(str::trim))
+        .bind(&normalized_name)
+        .bind(payload.color.is_some())
+        .bind(payload.color.flatten())
+        .bind(parent_id.is_some())
+        .bind(parent_id.flatten())
+        .fetch_one(&mut *tx)
+        .await;
+        let label = match (result, normalized_name) {
+            (Err(error), Some(normalized_name)) if is_unique_violation(&error) => {
+                tx.rollback().await.map_err(handle_sqlx_error)?;
+                return Err(self.duplicate(user_id, &normalized_name, error).await);
+            }
+            (result, _) => result.map_err(handle_sqlx_error)?,
+        };
+
+        tx.commit().await.map_err(handle_sqlx_error)?;
 
         Ok(label)
     }
 
-    async fn delete(&self, id: i32) -> Result<(), RepositoryError> {
-        let result = sqlx::query(
+    async fn merge(
+        &self,
+        user_id: i32,
+        id: i32,
+        payload: MergeLabel,
+    ) -> Result<Label, RepositoryError> {
+        let source_id = payload.source_id;
+        let mut tx = self.pool.begin().await.map_err(handle_sqlx_error)?;
+
+        lock_tree(&mut tx, user_id).await?;
+
+        // 両方のラベルをID順にロックし、同時に行った統合や削除と競合しないようにする
+        let labels = sqlx::query_as::<_, Label>(
+            r#"
+                SELECT *
+                FROM label
+                WHERE id = ANY($1)
+                  AND user_id = $2
+                ORDER BY id
+                FOR UPDATE;
+            "#,
+        )
+        .bind([id, source_id])
+        .bind(user_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?;
+        let label = labels
+            .iter()
+            .find(|label| label.id == id)
+            .cloned()
+            .ok_or(RepositoryError::NotFound(id as u32))?;
+        if !labels.iter().any(|label| label.id == source_id) {
+            return Err(RepositoryError::NotFound(source_id as u32));
+        }
+        if id == source_id {
+            return Err(RepositoryError::MergeIntoSelf(id));
+        }
+        // `source_id`の子のラベルを`id`の子にするため、`id`が`source_id`の子孫であれば循環になる
+        if in_subtree(&mut tx, source_id, id).await? {
+            return Err(RepositoryError::LabelCycle {
+                id: source_id,
+                parent_id: id,
+            });
+        }
+
+        // 既に`id`のラベルが付いているTodoには重複して付けない
+        sqlx::query(
+            r#"
+                INSERT INTO todo_labels (todo_id, label_id)
+                SELECT DISTINCT todo_id, $1
+                FROM todo_labels
+                WHERE label_id = $2
+                  AND todo_id NOT IN (SELECT todo_id FROM todo_labels WHERE label_id = $1);
+            "#,
+        )
+        .bind(id)
+        .bind(source_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        sqlx::query(
+            r#"
+                DELETE
+                FROM todo_labels
+                WHERE label_id = $1;
+            "#,
+        )
+        .bind(source_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        sqlx::query(
+            r#"
+                UPDATE label
+                SET parent_id = $1
+                WHERE parent_id = $2;
+            "#,
+        )
+        .bind(id)
+        .bind(source_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        sqlx::query(
             r#"
                 DELETE
                 FROM label
                 WHERE id = $1;
             "#,
         )
+        .bind(source_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        tx.commit().await.map_err(handle_sqlx_error)?;
+
+        Ok(label)
+    }
+
+    async fn delete(&self, user_id: i32, id: i32, force: bool) -> Result<(), RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(handle_sqlx_error)?;
+
+        lock_tree(&mut tx, user_id).await?;
+
+        let parent_id = sqlx::query_scalar::<_, Option<i32>>(
+            r#"
+                DELETE
+                FROM label
+                WHERE id = $1
+                  AND user_id = $2
+                RETURNING parent_id;
+            "#,
+        )
         .bind(id)
-        .execute(&self.pool)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?
+        .ok_or(RepositoryError::NotFound(id as u32))?;
+
+        // 削除でラベルの行をロックしてから数え、付いていればロールバックする
+        let todos = sqlx::query_scalar::<_, i64>(
+            r#"
+                SELECT COUNT(*)
+                FROM todo_labels
+                WHERE label_id = $1;
+            "#,
+        )
+        .bind(id)
+        .fetch_one(&mut *tx)
         .await
         .map_err(handle_sqlx_error)?;
-        if result.rows_affected() == 0 {
-            return Err(RepositoryError::NotFound(id as u32));
+        if todos > 0 && !force {
+            return Err(RepositoryError::LabelInUse { id, todos });
         }
 
+        sqlx::query(
+            r#"
+                DELETE
+                FROM todo_labels
+                WHERE label_id = $1;
+            "#,
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        sqlx::query(
+            r#"
+                UPDATE label
+                SET parent_id = $2
+                WHERE parent_id = $1;
+            "#,
+        )
+        .bind(id)
+        .bind(parent_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        tx.commit().await.map_err(handle_sqlx_error)?;
+
         Ok(())
     }
 }
 
+/// `user_id`のユーザーが所有する、正規化すると`name`と同じ名前のラベルを探す。親にするため共有ロックを取る
+async fn find_by_name(
+    conn: &mut PgConnection,
+    user_id: i32,
+    name: &str,
+) -> Result<Option<i32>, RepositoryError> {
+    sqlx::query_scalar::<_, i32>(
+        r#"
+            SELECT id
+            FROM label
+            WHERE user_id = $1
+              AND normalized_name = $2
+            FOR SHARE;
+        "#,
+    )
+    .bind(user_id)
+    .bind(normalize_name(name))
+    .fetch_optional(conn)
+    .await
+    .map_err(handle_sqlx_error)
+}
+
+/// 親に指定されたラベルを`user_id`のユーザーが所有しているか確かめる
+async fn lock_parent(
+    conn: &mut PgConnection,
+    user_id: i32,
+    parent_id: i32,
+) -> Result<(), RepositoryError> {
+    sqlx::query_scalar::<_, i32>(
+        r#"
+            SELECT id
+            FROM label
+            WHERE id = $1
+              AND user_id = $2
+            FOR SHARE;
+        "#,
+    )
+    .bind(parent_id)
+    .bind(user_id)
+    .fetch_optional(conn)
+    .await
+    .map_err(handle_sqlx_error)?
+    .ok_or(RepositoryError::NotFound(parent_id as u32))?;
+
+    Ok(())
+}
+
+// 同じユーザーのラベルの親の変更を直列化し、同時に変更した親で循環が生じないようにする
+async fn lock_tree(conn: &mut PgConnection, user_id: i32) -> Result<(), RepositoryError> {
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtext('label_tree'), $1);")
+        .bind(user_id)
+        .execute(conn)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+    Ok(())
+}
+
+/// `id`のラベルが`root`のラベル自身かその子孫か
+async fn in_subtree(conn: &mut PgConnection, root: i32, id: i32) -> Result<bool, RepositoryError> {
+    sqlx::query_scalar::<_, bool>(
+        r#"
+            WITH RECURSIVE subtree (id) AS (
+                SELECT $1::INTEGER
+                UNION
+                SELECT label.id
+                FROM label
+                    INNER JOIN subtree ON label.parent_id = subtree.id
+            )
+            SELECT EXISTS (SELECT * FROM subtree WHERE id = $2);
+        "#,
+    )
+    .bind(root)
+    .bind(id)
+    .fetch_one(conn)
+    .await
+    .map_err(handle_sqlx_error)
+}
+
+fn is_unique_violation(error: &sqlx::Error) -> bool {
+    error
+        .as_database_error()
+        .is_some_and(|error| error.is_unique_violation())
+}
+
 fn handle_sqlx_error(error: sqlx::Error) -> RepositoryError {
     RepositoryError::Unexpected(error.into())
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::repository::{
+        label::conformance,
+        user::{conformance::create_user, UserRepositoryForPostgres},
+    };
+
     use super::*;
 
     #[ignore = "Dependence of database"]
     #[tokio::test]
-    async fn crud_scenario() {
+    async fn conformance() {
         let database_url = std::env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
         let pool = PgPool::connect(&database_url)
             .await
             .expect("fail connect database");
-        let repository = LabelRepositoryForPostgres::new(pool);
+        let repository = LabelRepositoryForPostgres::new(pool.clone());
+        repository
+            .normalize_names()
+            .await
+            .expect("fail normalize label names");
 
-        let label_text = "test_label";
+        conformance::run(repository, UserRepositoryForPostgres::new(pool)).await;
+    }
 
-        // create
-        let created = repository
-            .create(CreateLabel {
-                name: label_text.to_string(),
-            })
+    /// 移行前のラベルは`normalize_name`で正規化した名前を設定し、重複したラベルはNULLのまま残す
+    #[ignore = "Dependence of database"]
+    #[tokio::test]
+    async fn normalize_legacy_names() {
+        let database_url = std::env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(&database_url)
             .await
-            .expect("fail create label");
-        assert_eq!(created.name, label_text);
+            .expect("fail connect database");
+        let user_id = create_user(
+            &UserRepositoryForPostgres::new(pool.clone()),
+            "[label] legacy",
+        )
+        .await
+        .id();
 
-        // all
-        let labels = repository.all().await.expect("fail fetch all labels");
-        let label = labels.into_iter().next().unwrap();
-        assert_eq!(label.name, label_text);
+        // 所有者の導入前に作られたラベルには所有者がいない
+        let ownerless = format!("ownerless {user_id}");
+        let mut ids = Vec::new();
+        for (name, owner) in [
+            ("ＢＵＧ ", Some(user_id)),
+            ("bug", Some(user_id)),
+            ("bug#7", Some(user_id)),
+            ("Straße", Some(user_id)),
+            (ownerless.as_str(), None),
+            (&ownerless.to_uppercase(), None),
+            (ownerless.as_str(), Some(user_id)),
+        ] {
+            let id = sqlx::query_scalar::<_, i32>(
+                r#"
+                    INSERT INTO label (name, user_id)
+                    VALUES ($1, $2)
+                    RETURNING id;
+                "#,
+            )
+            .bind(name)
+            .bind(owner)
+            .fetch_one(&pool)
+            .await
+            .expect("fail insert legacy label");
+            ids.push(id);
+        }
 
-        // delete
+        let repository = LabelRepositoryForPostgres::new(pool.clone());
+        repository
+            .normalize_names()
+            .await
+            .expect("fail normalize label names");
+        // 2回目は何もしない
         repository
-            .delete(created.id)
+            .normalize_names()
+            .await
+            .expect("fail normalize label names");
+
+        let mut normalized = Vec::new();
+        for id in &ids {
+            let name = sqlx::query_scalar::<_, Option<String>>(
+                r#"
+                    SELECT normalized_name
+                    FROM label
+                    WHERE id = $1;
+                "#,
+            )
+            .bind(id)
+            .fetch_one(&pool)
+            .await
+            .expect("fail fetch normalized name");
+            normalized.push(name);
+        }
+        assert_eq!(
+            normalized,
+            [
+                Some("bug".to_string()),
+                None,
+                Some("bug#7".to_string()),
+                Some("strasse".to_string()),
+                Some(ownerless.clone()),
+                None,
+                Some(ownerless.clone()),
+            ]
+        );
+
+        // 名前を変えれば重複していたラベルも一意インデックスの対象になる
+        let renamed = repository
+            .update(
+                user_id,
+                ids[1],
+                UpdateLabel::default().with_name("bug (old)".to_string()),
+            )
             .await
-            .expect("fail delete label");
+            .expect("fail rename legacy label");
+        assert_eq!(renamed.name, "bug (old)");
+        let res = repository
+            .update(
+                user_id,
+                ids[1],
+                UpdateLabel::default().with_name("bug#7".to_string()),
+            )
+            .await;
+        assert!(
+            matches!(res, Err(RepositoryError::Duplicate(id)) if id == ids[2]),
+            "unexpected result: {res:?}"
+        );
     }
 }
@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod conformance;
+mod memory;
+mod postgres;
+mod sqlite;
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+use super::RepositoryError;
+
+pub use memory::ReminderRepositoryForMemory;
+pub use postgres::ReminderRepositoryForPostgres;
+pub use sqlite::ReminderRepositoryForSqlite;
+
+/// 全てのユーザーのTodoを対象に、期限を迎えたTodoの通知を同じ期限につき一度だけ行う
+#[async_trait]
+pub trait ReminderRepository: Send + Sync + 'static {
+    /// `now`までに期限を迎えた未完了のTodoのうち、その期限で未通知のものを通知済みにして期限の古い順に返す
+    async fn claim_due(
+        &self,
+        now: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<Reminder>, RepositoryError>;
+    /// 通知に失敗した場合に通知済みの記録を取り消し、次の走査で再び返るようにする
+    async fn release(&self, reminder: &Reminder) -> Result<(), RepositoryError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct Reminder {
+    todo_id: i32,
+    user_id: i32,
+    text: String,
+    due_at: DateTime<Utc>,
+}
+
+impl Reminder {
+    pub fn new(todo_id: i32, user_id: i32, text: String, due_at: DateTime<Utc>) -> Self {
+        Self {
+            todo_id,
+            user_id,
+            text,
+            due_at,
+        }
+    }
+
+    pub fn todo_id(&self) -> i32 {
+        self.todo_id
+    }
+
+    pub fn user_id(&self) -> i32 {
+        self.user_id
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn due_at(&self) -> DateTime<Utc> {
+        self.due_at
+    }
+}
@@ -0,0 +1,146 @@
+//! 全ての`ReminderRepository`の実装が満たすべき振る舞い
+
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use serde_json::json;
+
+use crate::repository::{
+    todo::{conformance::complete, CreateTodo, TodoRepository, UpdateTodo},
+    user::{conformance::create_user, UserRepository},
+};
+
+use super::{Reminder, ReminderRepository};
+
+pub(crate) async fn run<R: ReminderRepository, T: TodoRepository, U: UserRepository>(
+    repository: R,
+    todo_repository: T,
+    user_repository: U,
+) {
+    let user_id = create_user(&user_repository, "[reminder] owner").await.id();
+    // Postgresはマイクロ秒までしか保存しないため秒単位に丸める
+    let now = Utc::now().duration_trunc(Duration::seconds(1)).unwrap();
+
+    let create = |text: &str, due_at: Option<DateTime<Utc>>| {
+        let payload = CreateTodo::new(format!("[reminder] {text}"), vec![]);
+        let payload = match due_at {
+            Some(due_at) => payload.with_due_at(due_at),
+            None => payload,
+        };
+        let todo_repository = &todo_repository;
+        async move {
+            todo_repository
+                .create(user_id, payload)
+                .await
+                .expect("fail create todo")
+        }
+    };
+    let update = |id: u32, payload: serde_json::Value| {
+        let payload: UpdateTodo = serde_json::from_value(payload).unwrap();
+        let todo_repository = &todo_repository;
+        async move {
+            todo_repository
+                .update(user_id, id, payload)
+                .await
+                .expect("fail update todo")
+        }
+    };
+    // 他のテストのTodoも対象になるため、このユーザーのものだけを見る
+    let claim = |limit: u32| {
+        let repository = &repository;
+        async move {
+            let reminders = repository
+                .claim_due(now, limit)
+                .await
+                .expect("fail claim due reminders");
+            assert!(
+                reminders.len() <= limit as usize,
+                "over limit: {reminders:?}"
+            );
+            reminders
+                .into_iter()
+                .filter(|reminder| reminder.user_id() == user_id)
+                .collect::<Vec<Reminder>>()
+        }
+    };
+
+    let overdue = create("overdue", Some(now - Duration::hours(1))).await;
+    let just_due = create("just due", Some(now)).await;
+    let upcoming = create("upcoming", Some(now + Duration::hours(1))).await;
+    let done = create("done", Some(now - Duration::hours(1))).await;
+    complete(&todo_repository, user_id, done.id()).await;
+    let someday = create("someday", None).await;
+
+    // due todos are claimed once, oldest first
+    let reminders = claim(100).await;
+    assert_eq!(
+        reminders,
+        vec![
+            Reminder::new(
+                overdue.id() as i32,
+                user_id,
+                "[reminder] overdue".to_string(),
+                now - Duration::hours(1),
+            ),
+            Reminder::new(
+                just_due.id() as i32,
+                user_id,
+                "[reminder] just due".to_string(),
+                now,
+            ),
+        ]
+    );
+    assert!(claim(100).await.is_empty(), "reminders are claimed twice");
+
+    // released reminders are claimed again
+    repository
+        .release(&reminders[0])
+        .await
+        .expect("fail release reminder");
+    let found: Vec<i32> = claim(100).await.iter().map(Reminder::todo_id).collect();
+    assert_eq!(found, vec![overdue.id() as i32]);
+
+    // changing the due date reminds again
+    update(
+        just_due.id(),
+        json!({ "due_at": now - Duration::minutes(1) }),
+    )
+    .await;
+    let reminders = claim(100).await;
+    let found: Vec<(i32, DateTime<Utc>)> = reminders
+        .iter()
+        .map(|reminder| (reminder.todo_id(), reminder.due_at()))
+        .collect();
+    assert_eq!(
+        found,
+        vec![(just_due.id() as i32, now - Duration::minutes(1))]
+    );
+
+    // releasing a stale reminder does not forget the newer one
+    repository
+        .release(&Reminder::new(
+            just_due.id() as i32,
+            user_id,
+            "[reminder] just due".to_string(),
+            now,
+        ))
+        .await
+        .expect("fail release reminder");
+    assert!(claim(100).await.is_empty(), "reminders are claimed twice");
+
+    // completed todos are not reminded even when released
+    repository.release(&reminders[0]).await.unwrap();
+    complete(&todo_repository, user_id, just_due.id()).await;
+    assert!(claim(100).await.is_empty(), "completed todo is reminded");
+
+    // limit
+    let first = create("first", Some(now - Duration::hours(3))).await;
+    let second = create("second", Some(now - Duration::hours(2))).await;
+    let mut found = Vec::new();
+    for _ in 0..2 {
+        found.extend(claim(1).await.iter().map(Reminder::todo_id));
+    }
+    assert_eq!(found, vec![first.id() as i32, second.id() as i32]);
+
+    for todo in [overdue, just_due, upcoming, done, someday, first, second] {
+        todo_repository.delete(user_id, todo.id()).await.unwrap();
+    }
+}
@@ -0,0 +1,84 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock, RwLockWriteGuard},
+};
+
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+
+use crate::repository::{todo::TodoRepositoryForMemory, RepositoryError};
+
+use super::{Reminder, ReminderRepository};
+
+/// TodoのIDと通知したときの期限
+type ReminderData = HashMap<i32, DateTime<Utc>>;
+
+#[derive(Debug, Clone, Default)]
+pub struct ReminderRepositoryForMemory {
+    store: Arc<RwLock<ReminderData>>,
+    todos: TodoRepositoryForMemory,
+}
+
+impl ReminderRepositoryForMemory {
+    pub fn new(todos: TodoRepositoryForMemory) -> Self {
+        Self {
+            store: Default::default(),
+            todos,
+        }
+    }
+
+    fn write_store_ref(&self) -> RwLockWriteGuard<'_, ReminderData> {
+        self.store.write().unwrap()
+    }
+}
+
+#[async_trait]
+impl ReminderRepository for ReminderRepositoryForMemory {
+    async fn claim_due(
+        &self,
+        now: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<Reminder>, RepositoryError> {
+        let mut store = self.write_store_ref();
+        let reminders: Vec<Reminder> = self
+            .todos
+            .due(now)
+            .into_iter()
+            .filter(|reminder| store.get(&reminder.todo_id) != Some(&reminder.due_at))
+            .take(limit as usize)
+            .collect();
+        for reminder in &reminders {
+            store.insert(reminder.todo_id, reminder.due_at);
+        }
+        Ok(reminders)
+    }
+
+    async fn release(&self, reminder: &Reminder) -> Result<(), RepositoryError> {
+        let mut store = self.write_store_ref();
+        if store.get(&reminder.todo_id) == Some(&reminder.due_at) {
+            store.remove(&reminder.todo_id);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repository::{
+        label::LabelRepositoryForMemory, reminder::conformance, user::UserRepositoryForMemory,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn conformance() {
+        let todo_repository = TodoRepositoryForMemory::new(LabelRepositoryForMemory::new());
+
+        conformance::run(
+            ReminderRepositoryForMemory::new(todo_repository.clone()),
+            todo_repository,
+            UserRepositoryForMemory::new(),
+        )
+        .await;
+    }
+}
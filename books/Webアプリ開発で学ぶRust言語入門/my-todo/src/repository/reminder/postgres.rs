@@ -0,0 +1,112 @@
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::repository::RepositoryError;
+
+use super::{Reminder, ReminderRepository};
+
+#[derive(Debug, Clone)]
+pub struct ReminderRepositoryForPostgres {
+    pool: PgPool,
+}
+
+impl ReminderRepositoryForPostgres {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl ReminderRepository for ReminderRepositoryForPostgres {
+    /// 複数のプロセスが同時に走査しても、期限が一致する記録は更新しないため一つのプロセスだけが通知する
+    async fn claim_due(
+        &self,
+        now: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<Reminder>, RepositoryError> {
+        let reminders = sqlx::query_as::<_, Reminder>(
+            r#"
+                WITH claimed AS (
+                    INSERT INTO reminders (todo_id, due_at, sent_at)
+                    SELECT id, due_at, $1
+                    FROM todo
+                    WHERE user_id IS NOT NULL
+                      AND status <> 'done'
+                      AND due_at <= $1
+                      AND NOT EXISTS (
+                          SELECT 1
+                          FROM reminders
+                          WHERE reminders.todo_id = todo.id
+                            AND reminders.due_at = todo.due_at
+                      )
+                    ORDER BY due_at, id
+                    LIMIT $2
+                    ON CONFLICT (todo_id) DO UPDATE
+                        SET due_at  = EXCLUDED.due_at,
+                            sent_at = EXCLUDED.sent_at
+                        WHERE reminders.due_at <> EXCLUDED.due_at
+                    RETURNING todo_id, due_at
+                )
+                SELECT claimed.todo_id, todo.user_id, todo.text, claimed.due_at
+                FROM claimed
+                         JOIN todo ON todo.id = claimed.todo_id
+                ORDER BY claimed.due_at, claimed.todo_id;
+            "#,
+        )
+        .bind(now)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        Ok(reminders)
+    }
+
+    async fn release(&self, reminder: &Reminder) -> Result<(), RepositoryError> {
+        sqlx::query(
+            r#"
+                DELETE
+                FROM reminders
+                WHERE todo_id = $1
+                  AND due_at = $2;
+            "#,
+        )
+        .bind(reminder.todo_id)
+        .bind(reminder.due_at)
+        .execute(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        Ok(())
+    }
+}
+
+fn handle_sqlx_error(error: sqlx::Error) -> RepositoryError {
+    RepositoryError::Unexpected(error.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repository::{
+        reminder::conformance, todo::TodoRepositoryForPostgres, user::UserRepositoryForPostgres,
+    };
+
+    use super::*;
+
+    #[ignore = "Dependence of database"]
+    #[tokio::test]
+    async fn conformance() {
+        let database_url = std::env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("fail connect database");
+
+        conformance::run(
+            ReminderRepositoryForPostgres::new(pool.clone()),
+            TodoRepositoryForPostgres::new(pool.clone()),
+            UserRepositoryForPostgres::new(pool),
+        )
+        .await;
+    }
+}
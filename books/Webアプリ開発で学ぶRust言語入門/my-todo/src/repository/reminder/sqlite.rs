@@ -0,0 +1,123 @@
+use axum::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::SqlitePool;
+
+use crate::repository::RepositoryError;
+
+use super::{Reminder, ReminderRepository};
+
+#[derive(Debug, Clone)]
+pub struct ReminderRepositoryForSqlite {
+    pool: SqlitePool,
+}
+
+impl ReminderRepositoryForSqlite {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl ReminderRepository for ReminderRepositoryForSqlite {
+    async fn claim_due(
+        &self,
+        now: DateTime<Utc>,
+        limit: u32,
+    ) -> Result<Vec<Reminder>, RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(handle_sqlx_error)?;
+
+        let reminders = sqlx::query_as::<_, Reminder>(
+            r#"
+                SELECT id AS todo_id, user_id, text, due_at
+                FROM todo
+                WHERE user_id IS NOT NULL
+                  AND status <> 'done'
+                  AND due_at <= $1
+                  AND NOT EXISTS (
+                      SELECT 1
+                      FROM reminders
+                      WHERE reminders.todo_id = todo.id
+                        AND reminders.due_at = todo.due_at
+                  )
+                ORDER BY due_at, id
+                LIMIT $2;
+            "#,
+        )
+        .bind(now)
+        .bind(limit as i64)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        for reminder in &reminders {
+            sqlx::query(
+                r#"
+                    INSERT INTO reminders (todo_id, due_at, sent_at)
+                    VALUES ($1, $2, $3)
+                    ON CONFLICT (todo_id) DO UPDATE
+                        SET due_at  = excluded.due_at,
+                            sent_at = excluded.sent_at;
+                "#,
+            )
+            .bind(reminder.todo_id)
+            .bind(reminder.due_at)
+            .bind(now)
+            .execute(&mut *tx)
+            .await
+            .map_err(handle_sqlx_error)?;
+        }
+
+        tx.commit().await.map_err(handle_sqlx_error)?;
+
+        Ok(reminders)
+    }
+
+    async fn release(&self, reminder: &Reminder) -> Result<(), RepositoryError> {
+        sqlx::query(
+            r#"
+                DELETE
+                FROM reminders
+                WHERE todo_id = $1
+                  AND due_at = $2;
+            "#,
+        )
+        .bind(reminder.todo_id)
+        .bind(reminder.due_at)
+        .execute(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        Ok(())
+    }
+}
+
+fn handle_sqlx_error(error: sqlx::Error) -> RepositoryError {
+    RepositoryError::Unexpected(error.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repository::{
+        reminder::conformance, todo::TodoRepositoryForSqlite, user::UserRepositoryForSqlite,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn conformance() {
+        let pool = SqlitePool::connect("sqlite::memory:")
+            .await
+            .expect("fail connect database");
+        sqlx::migrate!("./migrations/sqlite")
+            .run(&pool)
+            .await
+            .expect("fail migrate database");
+
+        conformance::run(
+            ReminderRepositoryForSqlite::new(pool.clone()),
+            TodoRepositoryForSqlite::new(pool.clone()),
+            UserRepositoryForSqlite::new(pool),
+        )
+        .await;
+    }
+}
@@ -0,0 +1,79 @@
+#[cfg(test)]
+mod conformance;
+mod memory;
+mod postgres;
+mod sqlite;
+
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use axum::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::FromRow;
+
+use super::RepositoryError;
+
+pub use memory::SessionRepositoryForMemory;
+pub use postgres::SessionRepositoryForPostgres;
+pub use sqlite::SessionRepositoryForSqlite;
+
+/// セッションの既定の有効期間
+pub const DEFAULT_SESSION_TTL: Duration = Duration::days(30);
+
+#[async_trait]
+pub trait SessionRepository: Send + Sync + 'static {
+    async fn create(&self, user_id: i32) -> Result<Session, RepositoryError>;
+    /// 有効期限が切れたセッションは見つからないものとして扱う
+    async fn find(&self, token: &str) -> Result<Session, RepositoryError>;
+    async fn delete(&self, token: &str) -> Result<(), RepositoryError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    token: String,
+    user_id: i32,
+    expires_at: DateTime<Utc>,
+}
+
+impl Session {
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    pub fn user_id(&self) -> i32 {
+        self.user_id
+    }
+
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        self.expires_at
+    }
+}
+
+/// 保存するセッション。トークンそのものは保存しない
+#[derive(Debug, Clone, FromRow)]
+struct SessionDto {
+    user_id: i32,
+    expires_at: DateTime<Utc>,
+}
+
+impl SessionDto {
+    fn into_session(self, token: &str) -> Session {
+        Session {
+            token: token.to_string(),
+            user_id: self.user_id,
+            expires_at: self.expires_at,
+        }
+    }
+}
+
+/// 推測できないよう、OSの乱数から256bitのトークンを生成する
+fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// 保存先が漏れてもセッションを乗っ取られないよう、トークンはハッシュにして保存する
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
@@ -0,0 +1,94 @@
+//! 全ての`SessionRepository`の実装が満たすべき振る舞い
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::Utc;
+
+use crate::repository::{
+    user::{CreateUser, UserRepository},
+    RepositoryError,
+};
+
+use super::SessionRepository;
+
+/// `expired`には有効期間を0にしたリポジトリを渡す
+pub(crate) async fn run<R: SessionRepository, U: UserRepository>(
+    repository: R,
+    expired: R,
+    user_repository: U,
+) {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    let user = user_repository
+        .create(CreateUser::new(
+            format!("[session] user {nanos}"),
+            "password".to_string(),
+        ))
+        .await
+        .expect("fail create user");
+
+    // create
+    let session = repository
+        .create(user.id())
+        .await
+        .expect("fail create session");
+    assert_eq!(session.user_id(), user.id());
+    assert!(
+        session.expires_at() > Utc::now(),
+        "session is already expired"
+    );
+
+    // find
+    let found = repository
+        .find(session.token())
+        .await
+        .expect("fail find session");
+    assert_eq!(session, found);
+
+    // tokens are unique per session
+    let other = repository
+        .create(user.id())
+        .await
+        .expect("fail create session");
+    assert_ne!(session.token(), other.token());
+
+    // unknown token
+    let result = repository.find("unknown token").await;
+    assert!(
+        matches!(result, Err(RepositoryError::Unauthorized)),
+        "unexpected result: {result:?}"
+    );
+
+    // expired
+    let stale = expired
+        .create(user.id())
+        .await
+        .expect("fail create session");
+    let result = expired.find(stale.token()).await;
+    assert!(
+        matches!(result, Err(RepositoryError::Unauthorized)),
+        "unexpected result: {result:?}"
+    );
+
+    // delete
+    repository
+        .delete(other.token())
+        .await
+        .expect("fail delete session");
+    let result = repository.find(other.token()).await;
+    assert!(
+        matches!(result, Err(RepositoryError::Unauthorized)),
+        "unexpected result: {result:?}"
+    );
+    let result = repository.delete(other.token()).await;
+    assert!(
+        matches!(result, Err(RepositoryError::Unauthorized)),
+        "unexpected result: {result:?}"
+    );
+    repository
+        .find(session.token())
+        .await
+        .expect("other sessions are kept");
+}
@@ -0,0 +1,98 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use axum::async_trait;
+use chrono::{Duration, Utc};
+
+use crate::repository::RepositoryError;
+
+use super::{
+    generate_token, hash_token, Session, SessionDto, SessionRepository, DEFAULT_SESSION_TTL,
+};
+
+/// トークンのハッシュをキーにする
+type SessionData = HashMap<String, SessionDto>;
+
+#[derive(Debug, Clone)]
+pub struct SessionRepositoryForMemory {
+    store: Arc<RwLock<SessionData>>,
+    ttl: Duration,
+}
+
+impl Default for SessionRepositoryForMemory {
+    fn default() -> Self {
+        Self {
+            store: Arc::default(),
+            ttl: DEFAULT_SESSION_TTL,
+        }
+    }
+}
+
+impl SessionRepositoryForMemory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_ttl(self, ttl: Duration) -> Self {
+        Self { ttl, ..self }
+    }
+
+    fn write_store_ref(&self) -> RwLockWriteGuard<'_, SessionData> {
+        self.store.write().unwrap()
+    }
+
+    fn read_store_ref(&self) -> RwLockReadGuard<'_, SessionData> {
+        self.store.read().unwrap()
+    }
+}
+
+#[async_trait]
+impl SessionRepository for SessionRepositoryForMemory {
+    async fn create(&self, user_id: i32) -> Result<Session, RepositoryError> {
+        let token = generate_token();
+        let session = SessionDto {
+            user_id,
+            expires_at: Utc::now() + self.ttl,
+        };
+        let mut store = self.write_store_ref();
+        store.insert(hash_token(&token), session.clone());
+        Ok(session.into_session(&token))
+    }
+
+    async fn find(&self, token: &str) -> Result<Session, RepositoryError> {
+        let store = self.read_store_ref();
+        let session = store
+            .get(&hash_token(token))
+            .filter(|session| session.expires_at > Utc::now())
+            .cloned()
+            .ok_or(RepositoryError::Unauthorized)?;
+        Ok(session.into_session(token))
+    }
+
+    async fn delete(&self, token: &str) -> Result<(), RepositoryError> {
+        let mut store = self.write_store_ref();
+        store
+            .remove(&hash_token(token))
+            .ok_or(RepositoryError::Unauthorized)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repository::{session::conformance, user::UserRepositoryForMemory};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn conformance() {
+        conformance::run(
+            SessionRepositoryForMemory::new(),
+            SessionRepositoryForMemory::new().with_ttl(Duration::zero()),
+            UserRepositoryForMemory::new(),
+        )
+        .await;
+    }
+}
@@ -0,0 +1,140 @@
+use axum::async_trait;
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+
+use crate::repository::RepositoryError;
+
+use super::{
+    generate_token, hash_token, Session, SessionDto, SessionRepository, DEFAULT_SESSION_TTL,
+};
+
+#[derive(Debug, Clone)]
+pub struct SessionRepositoryForPostgres {
+    pool: PgPool,
+    ttl: Duration,
+}
+
+impl SessionRepositoryForPostgres {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            ttl: DEFAULT_SESSION_TTL,
+        }
+    }
+
+    pub fn with_ttl(self, ttl: Duration) -> Self {
+        Self { ttl, ..self }
+    }
+}
+
+#[async_trait]
+impl SessionRepository for SessionRepositoryForPostgres {
+    async fn create(&self, user_id: i32) -> Result<Session, RepositoryError> {
+        let token = generate_token();
+        let session = sqlx::query_as::<_, SessionDto>(
+            r#"
+                INSERT INTO sessions (token_hash, user_id, expires_at)
+                VALUES ($1, $2, $3)
+                RETURNING user_id, expires_at;
+            "#,
+        )
+        .bind(hash_token(&token))
+        .bind(user_id)
+        .bind(Utc::now() + self.ttl)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        Ok(session.into_session(&token))
+    }
+
+    async fn find(&self, token: &str) -> Result<Session, RepositoryError> {
+        let session = sqlx::query_as::<_, SessionDto>(
+            r#"
+                SELECT user_id, expires_at
+                FROM sessions
+                WHERE token_hash = $1
+                  AND expires_at > $2;
+            "#,
+        )
+        .bind(hash_token(token))
+        .bind(Utc::now())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?
+        .ok_or(RepositoryError::Unauthorized)?;
+
+        Ok(session.into_session(token))
+    }
+
+    async fn delete(&self, token: &str) -> Result<(), RepositoryError> {
+        let result = sqlx::query(
+            r#"
+                DELETE FROM sessions
+                WHERE token_hash = $1;
+            "#,
+        )
+        .bind(hash_token(token))
+        .execute(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        if result.rows_affected() == 0 {
+            return Err(RepositoryError::Unauthorized);
+        }
+        Ok(())
+    }
+}
+
+fn handle_sqlx_error(error: sqlx::Error) -> RepositoryError {
+    RepositoryError::Unexpected(error.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repository::{
+        session::conformance,
+        user::{conformance::create_user, UserRepositoryForPostgres},
+    };
+
+    use super::*;
+
+    #[ignore = "Dependence of database"]
+    #[tokio::test]
+    async fn conformance() {
+        let database_url = std::env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
+        let pool = PgPool::connect(&database_url)
+            .await
+            .expect("fail connect database");
+
+        conformance::run(
+            SessionRepositoryForPostgres::new(pool.clone()),
+            SessionRepositoryForPostgres::new(pool.clone()).with_ttl(Duration::zero()),
+            UserRepositoryForPostgres::new(pool.clone()),
+        )
+        .await;
+
+        // トークンそのものは保存しない
+        let user = create_user(
+            &UserRepositoryForPostgres::new(pool.clone()),
+            "[session] stored",
+        )
+        .await;
+        let session = SessionRepositoryForPostgres::new(pool.clone())
+            .create(user.id())
+            .await
+            .expect("fail create session");
+        let stored = sqlx::query_scalar::<_, i64>(
+            r#"
+                SELECT COUNT(*)
+                FROM sessions
+                WHERE token_hash = $1;
+            "#,
+        )
+        .bind(session.token())
+        .fetch_one(&pool)
+        .await
+        .expect("fail count sessions");
+        assert_eq!(stored, 0);
+    }
+}
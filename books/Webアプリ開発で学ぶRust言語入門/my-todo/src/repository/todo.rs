@@ -1,52 +1,767 @@
+#[cfg(test)]
+pub(crate) mod conformance;
 mod memory;
+mod position;
 mod postgres;
+mod recurrence;
+mod sqlite;
 
 pub use memory::TodoRepositoryForMemory;
 pub use postgres::TodoRepositoryForPostgres;
+pub use recurrence::{Frequency, Recurrence};
+pub use sqlite::TodoRepositoryForSqlite;
 
-use super::RepositoryError;
+use super::label::Label;
+use super::{deserialize_some, validate_not_blank, RepositoryError};
+use chrono::{DateTime, Utc};
+use recurrence::validate_recurrence;
+use serde::de::Error as _;
+use serde::de::IntoDeserializer;
 use serde::Deserialize;
+use serde::Deserializer;
 use serde::Serialize;
+use sqlx::FromRow;
+use validator::Validate;
 
+/// 操作は`user_id`のユーザーが所有するTodoに限られ、他のユーザーのTodoは存在しないものとして扱う
 #[axum::async_trait]
 pub trait TodoRepository: Send + Sync + 'static {
-    async fn all(&self) -> Result<Vec<Todo>, RepositoryError>;
-    async fn find(&self, id: u32) -> Result<Todo, RepositoryError>;
-    async fn create(&self, payload: CreateTodo) -> Result<Todo, RepositoryError>;
-    async fn update(&self, id: u32, payload: UpdateTodo) -> Result<Todo, RepositoryError>;
-    async fn delete(&self, id: u32) -> Result<(), RepositoryError>;
+    async fn all(&self, user_id: i32, query: &TodoQuery) -> Result<TodoPage, RepositoryError>;
+    /// 関連度の高い順に返す
+    async fn search(
+        &self,
+        user_id: i32,
+        search: &TodoSearch,
+    ) -> Result<Vec<TodoSearchHit>, RepositoryError>;
+    async fn find(&self, user_id: i32, id: u32) -> Result<Todo, RepositoryError>;
+    async fn create(&self, user_id: i32, payload: CreateTodo) -> Result<Todo, RepositoryError>;
+    async fn update(
+        &self,
+        user_id: i32,
+        id: u32,
+        payload: UpdateTodo,
+    ) -> Result<Todo, RepositoryError>;
+    /// サブタスクもまとめて削除する
+    async fn delete(&self, user_id: i32, id: u32) -> Result<(), RepositoryError>;
+    /// 直下のサブタスクをIDの昇順で返す
+    async fn children(&self, user_id: i32, id: u32) -> Result<Vec<Todo>, RepositoryError>;
+    async fn board(&self, user_id: i32) -> Result<Board, RepositoryError>;
+    /// 列の中での位置を変え、`status`を指定した場合は別の列に移す
+    async fn move_todo(
+        &self,
+        user_id: i32,
+        id: u32,
+        payload: MoveTodo,
+    ) -> Result<Todo, RepositoryError>;
+    /// `id`のTodoが`blocked_by`のTodoにブロックされるようにする。既にある依存関係は何もしない
+    async fn add_dependency(
+        &self,
+        user_id: i32,
+        id: u32,
+        payload: CreateDependency,
+    ) -> Result<Todo, RepositoryError>;
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Validate)]
 pub struct CreateTodo {
+    #[validate(length(min = 1, message = "Can not be empty"))]
+    #[validate(length(max = 100, message = "Over text length"))]
+    #[validate(custom = "validate_not_blank")]
     text: String,
+    #[serde(default)]
+    labels: Vec<i32>,
+    /// タイムゾーン付きで受け取り、UTCで保存する
+    #[serde(default)]
+    due_at: Option<DateTime<Utc>>,
+    /// 親のTodo。作成後は変更できない
+    #[serde(default)]
+    parent_id: Option<u32>,
+    /// 完了にしたとき、次の期限で同じ内容のTodoを作る
+    #[serde(default)]
+    #[validate(custom = "validate_recurrence")]
+    recurrence: Option<Recurrence>,
+    #[serde(default)]
+    priority: Priority,
+    #[validate(range(min = 1, max = 1000, message = "Must be between 1 and 1000"))]
+    #[serde(default)]
+    estimate: Option<u32>,
 }
 
 impl CreateTodo {
-    pub fn new(text: String) -> Self {
-        Self { text }
+    pub fn new(text: String, labels: Vec<i32>) -> Self {
+        Self {
+            text,
+            labels,
+            due_at: None,
+            parent_id: None,
+            recurrence: None,
+            priority: Priority::default(),
+            estimate: None,
+        }
+    }
+
+    pub fn with_parent(self, parent_id: u32) -> Self {
+        Self {
+            parent_id: Some(parent_id),
+            ..self
+        }
+    }
+
+    pub fn with_due_at(self, due_at: DateTime<Utc>) -> Self {
+        Self {
+            due_at: Some(due_at),
+            ..self
+        }
+    }
+
+    pub fn with_recurrence(self, recurrence: Recurrence) -> Self {
+        Self {
+            recurrence: Some(recurrence),
+            ..self
+        }
+    }
+
+    pub fn with_priority(self, priority: Priority) -> Self {
+        Self { priority, ..self }
+    }
+
+    pub fn with_estimate(self, estimate: u32) -> Self {
+        Self {
+            estimate: Some(estimate),
+            ..self
+        }
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Validate)]
 pub struct UpdateTodo {
+    #[validate(length(min = 1, message = "Can not be empty"))]
+    #[validate(length(max = 100, message = "Over text length"))]
+    #[validate(custom = "validate_not_blank")]
     text: Option<String>,
-    completed: Option<bool>,
+    /// 現在の状態から許可されていない状態への変更はエラーになる
+    status: Option<TodoStatus>,
+    labels: Option<Vec<i32>>,
+    /// 省略した場合は変更せず、`null`の場合は期限を外す
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    due_at: Option<Option<DateTime<Utc>>>,
+    /// 省略した場合は変更せず、`null`の場合は繰り返しをやめる
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[validate(custom = "validate_recurrence")]
+    recurrence: Option<Option<Recurrence>>,
+    priority: Option<Priority>,
+    /// 省略した場合は変更せず、`null`の場合は見積もりを外す
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    #[validate(range(min = 1, max = 1000, message = "Must be between 1 and 1000"))]
+    estimate: Option<Option<u32>>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Todo {
     id: u32,
     text: String,
-    completed: bool,
+    status: TodoStatus,
+    labels: Vec<Label>,
+    due_at: Option<DateTime<Utc>>,
+    parent_id: Option<u32>,
+    /// 完了していないTodoにブロックされている
+    blocked: bool,
+    recurrence: Option<Recurrence>,
+    /// 繰り返しの規則に従って作った次のTodo
+    next_occurrence_id: Option<u32>,
+    priority: Priority,
+    /// 作業量の見積もり (単位は利用者が決める)
+    estimate: Option<u32>,
+    comment_count: u32,
 }
 
 impl Todo {
-    pub fn new(id: u32, text: String) -> Self {
+    pub fn new(id: u32, text: String, labels: Vec<Label>) -> Self {
         Self {
             id,
             text,
-            completed: false,
+            status: TodoStatus::default(),
+            labels,
+            due_at: None,
+            parent_id: None,
+            blocked: false,
+            recurrence: None,
+            next_occurrence_id: None,
+            priority: Priority::default(),
+            estimate: None,
+            comment_count: 0,
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn parent_id(&self) -> Option<u32> {
+        self.parent_id
+    }
+
+    pub fn status(&self) -> TodoStatus {
+        self.status
+    }
+
+    pub fn is_blocked(&self) -> bool {
+        self.blocked
+    }
+
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
+    pub fn comment_count(&self) -> u32 {
+        self.comment_count
+    }
+
+    /// 完了しておらず、期限を過ぎている
+    fn is_overdue(&self, now: DateTime<Utc>) -> bool {
+        self.status != TodoStatus::Done && self.due_at.is_some_and(|due_at| due_at < now)
+    }
+}
+
+/// 依存関係の追加。自身や、自身にブロックされているTodoにはブロックされるようにできない
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CreateDependency {
+    blocked_by: u32,
+}
+
+impl CreateDependency {
+    pub fn new(blocked_by: u32) -> Self {
+        Self { blocked_by }
+    }
+}
+
+/// Todoの進捗。`backlog → in_progress → review → done`の順に一段階ずつ進み、一段階ずつ戻せる
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "todo_status", rename_all = "snake_case")]
+pub enum TodoStatus {
+    #[default]
+    Backlog,
+    InProgress,
+    Review,
+    Done,
+}
+
+impl TodoStatus {
+    /// ボードの列の順
+    pub const ALL: [TodoStatus; 4] = [
+        TodoStatus::Backlog,
+        TodoStatus::InProgress,
+        TodoStatus::Review,
+        TodoStatus::Done,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TodoStatus::Backlog => "backlog",
+            TodoStatus::InProgress => "in_progress",
+            TodoStatus::Review => "review",
+            TodoStatus::Done => "done",
+        }
+    }
+
+    /// 同じ状態への変更は何もしない変更として許可する
+    pub fn can_transition_to(self, next: TodoStatus) -> bool {
+        use TodoStatus::*;
+
+        self == next
+            || matches!(
+                (self, next),
+                (Backlog, InProgress)
+                    | (InProgress, Backlog | Review)
+                    | (Review, InProgress | Done)
+                    | (Done, Review)
+            )
+    }
+
+    /// `id`のTodoを`self`から`next`に変更できるか検証する
+    fn transition(self, id: u32, next: TodoStatus) -> Result<TodoStatus, RepositoryError> {
+        if !self.can_transition_to(next) {
+            return Err(RepositoryError::InvalidTransition {
+                id,
+                from: self,
+                to: next,
+            });
+        }
+        Ok(next)
+    }
+}
+
+impl std::fmt::Display for TodoStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// 優先度。`p0`が最も高く、昇順に並び替えると優先度の高い順になる
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, sqlx::Type,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "todo_priority", rename_all = "snake_case")]
+pub enum Priority {
+    P0,
+    P1,
+    #[default]
+    P2,
+    P3,
+}
+
+/// 並び替えに使う項目
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TodoSort {
+    #[default]
+    Id,
+    Text,
+    Priority,
+    /// 期限のないTodoは並び順に関わらず末尾に並べる
+    DueAt,
+    /// 見積もりのないTodoは並び順に関わらず末尾に並べる
+    Estimate,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl SortOrder {
+    fn as_sql(self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+}
+
+const DEFAULT_LIMIT: u32 = 50;
+
+/// Todo一覧の絞り込み・並び替え・ページ分割の条件
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Validate)]
+pub struct TodoQuery {
+    status: Option<TodoStatus>,
+    /// 指定したラベルが全て付いているTodoに絞り込む (カンマ区切り)。子孫のラベルが付いていてもよい
+    #[serde(default, deserialize_with = "deserialize_ids")]
+    labels: Vec<i32>,
+    /// 本文に含まれる文字列 (大文字小文字は区別しない)
+    text: Option<String>,
+    /// 並び替えに使う項目 (カンマ区切りで優先する順に指定する)
+    #[serde(default, deserialize_with = "deserialize_sorts")]
+    sort: Vec<TodoSort>,
+    /// 全ての項目に適用する。省略時は最初の項目がIDなら降順 (新しい順)、それ以外は昇順
+    order: Option<SortOrder>,
+    #[validate(range(min = 1, max = 100, message = "Must be between 1 and 100"))]
+    limit: Option<u32>,
+    #[serde(default)]
+    offset: u32,
+    /// 期限がこの日時より前のTodoに絞り込む
+    due_before: Option<DateTime<Utc>>,
+    /// 完了しておらず期限を過ぎているか
+    overdue: Option<bool>,
+}
+
+impl TodoQuery {
+    fn order(&self) -> SortOrder {
+        self.order.unwrap_or(match self.sorts()[0] {
+            TodoSort::Id => SortOrder::Desc,
+            _ => SortOrder::Asc,
+        })
+    }
+
+    /// 省略時はIDで並び替える
+    fn sorts(&self) -> &[TodoSort] {
+        if self.sort.is_empty() {
+            &[TodoSort::Id]
+        } else {
+            &self.sort
+        }
+    }
+
+    fn limit(&self) -> u32 {
+        self.limit.unwrap_or(DEFAULT_LIMIT)
+    }
+
+    /// 次のページがあるか判定するため、1件多く取得する
+    fn fetch_limit(&self) -> u32 {
+        self.limit() + 1
+    }
+
+    /// `text`を部分一致させるLIKEのパターン (エスケープ文字は`\`)
+    fn text_pattern(&self) -> Option<String> {
+        self.text.as_ref().map(|text| {
+            let escaped = text
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            format!("%{escaped}%")
+        })
+    }
+
+    /// `table`のカラムで並び替えるORDER BY句の中身。同じ値の場合はIDで順序を決める
+    fn order_by(&self, table: &str, collate: &str) -> String {
+        let order = self.order().as_sql();
+        let mut keys: Vec<String> = self
+            .sorts()
+            .iter()
+            .map(|sort| match sort {
+                TodoSort::Id => format!("{table}.id {order}"),
+                TodoSort::Text => format!("{table}.text COLLATE {collate} {order}"),
+                TodoSort::Priority => format!("{table}.priority {order}"),
+                TodoSort::DueAt => format!("{table}.due_at {order} NULLS LAST"),
+                TodoSort::Estimate => format!("{table}.estimate {order} NULLS LAST"),
+            })
+            .collect();
+        if !self.sorts().contains(&TodoSort::Id) {
+            keys.push(format!("{table}.id {order}"));
         }
+        keys.join(", ")
+    }
+}
+
+fn deserialize_ids<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<i32>, D::Error> {
+    String::deserialize(deserializer)?
+        .split(',')
+        .filter(|id| !id.trim().is_empty())
+        .map(|id| id.trim().parse().map_err(D::Error::custom))
+        .collect()
+}
+
+fn deserialize_sorts<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<TodoSort>, D::Error> {
+    String::deserialize(deserializer)?
+        .split(',')
+        .filter(|sort| !sort.trim().is_empty())
+        .map(|sort| TodoSort::deserialize(sort.trim().into_deserializer()))
+        .collect()
+}
+
+/// 1ページ分のTodoと、次のページがある場合はその開始位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TodoPage {
+    todos: Vec<Todo>,
+    next_offset: Option<u32>,
+}
+
+impl TodoPage {
+    /// `TodoQuery::fetch_limit`件まで取得したTodoから1ページ分を切り出す
+    fn new(mut todos: Vec<Todo>, query: &TodoQuery) -> Self {
+        let limit = query.limit();
+        let next_offset = (todos.len() > limit as usize).then(|| {
+            todos.truncate(limit as usize);
+            query.offset + limit
+        });
+        Self { todos, next_offset }
+    }
+
+    pub fn todos(&self) -> &[Todo] {
+        &self.todos
+    }
+
+    pub fn next_offset(&self) -> Option<u32> {
+        self.next_offset
+    }
+
+    pub fn into_todos(self) -> Vec<Todo> {
+        self.todos
+    }
+}
+
+/// サブタスクが残っている親のTodoを完了にしようとしたときの振る舞い。
+/// どちらの場合も未完了のサブタスクがあるTodoは完了にできない
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SubtaskCompletion {
+    /// 親のTodoは利用者が完了にする
+    #[default]
+    Block,
+    /// 最後のサブタスクを完了にしたとき、ワークフローを経ずに親のTodoも完了にする
+    AutoComplete,
+}
+
+/// 状態毎の列にボード上の並び順でTodoを並べたもの
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Board {
+    columns: Vec<BoardColumn>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BoardColumn {
+    status: TodoStatus,
+    todos: Vec<Todo>,
+}
+
+impl Board {
+    /// 並び順に並んだTodoを状態毎の列に振り分ける。Todoがない状態も空の列にする
+    fn new(todos: Vec<Todo>) -> Self {
+        let mut columns: Vec<BoardColumn> = TodoStatus::ALL
+            .into_iter()
+            .map(|status| BoardColumn {
+                status,
+                todos: Vec::new(),
+            })
+            .collect();
+        for todo in todos {
+            if let Some(column) = columns
+                .iter_mut()
+                .find(|column| column.status == todo.status)
+            {
+                column.todos.push(todo);
+            }
+        }
+        Self { columns }
+    }
+
+    pub fn columns(&self) -> &[BoardColumn] {
+        &self.columns
+    }
+}
+
+impl BoardColumn {
+    pub fn status(&self) -> TodoStatus {
+        self.status
+    }
+
+    pub fn todos(&self) -> &[Todo] {
+        &self.todos
+    }
+}
+
+/// ボード上でTodoを動かす先
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MoveTodo {
+    /// 移す先の列。省略した場合は同じ列の中で並べ替える
+    status: Option<TodoStatus>,
+    /// 直後に並べる同じ列のTodo。省略した場合は列の先頭に並べる
+    after: Option<u32>,
+}
+
+impl MoveTodo {
+    pub fn new(status: Option<TodoStatus>, after: Option<u32>) -> Self {
+        Self { status, after }
+    }
+}
+
+/// 並び順のキーが壊れている場合のエラー
+fn invalid_position() -> RepositoryError {
+    RepositoryError::Unexpected("invalid todo position".into())
+}
+
+const DEFAULT_SEARCH_LIMIT: u32 = 20;
+
+/// スニペット中で検索語に一致した箇所を囲む文字列
+const HIGHLIGHT_START: &str = "<mark>";
+const HIGHLIGHT_END: &str = "</mark>";
+/// データベースに一致した箇所を囲ませる目印。本文をエスケープしてから強調のタグに置き換える
+const MARKER_START: char = '\u{2}';
+const MARKER_END: char = '\u{3}';
+
+/// 全文検索の条件。検索語は全て含むTodoだけが一致する
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Validate)]
+pub struct TodoSearch {
+    #[validate(length(min = 1, message = "Can not be empty"))]
+    #[validate(length(max = 100, message = "Over text length"))]
+    #[validate(custom = "validate_not_blank")]
+    q: String,
+    #[validate(range(min = 1, max = 100, message = "Must be between 1 and 100"))]
+    limit: Option<u32>,
+}
+
+impl TodoSearch {
+    pub fn new(q: String) -> Self {
+        Self { q, limit: None }
+    }
+
+    fn limit(&self) -> u32 {
+        self.limit.unwrap_or(DEFAULT_SEARCH_LIMIT)
+    }
+
+    /// 重複を除いた小文字の検索語
+    fn terms(&self) -> Vec<String> {
+        let mut terms: Vec<String> = words(&self.q).map(str::to_lowercase).collect();
+        terms.sort_unstable();
+        terms.dedup();
+        terms
+    }
+}
+
+/// 英数字の連続を1つの単語として分割する
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+}
+
+/// 検索に一致したTodoと関連度、検索語を強調したスニペット
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TodoSearchHit {
+    todo: Todo,
+    rank: f32,
+    snippet: String,
+}
+
+impl TodoSearchHit {
+    pub fn todo(&self) -> &Todo {
+        &self.todo
+    }
+
+    pub fn snippet(&self) -> &str {
+        &self.snippet
+    }
+}
+
+/// ラベルIDの重複を取り除き、昇順に並べる
+fn normalize_label_ids(mut ids: Vec<i32>) -> Vec<i32> {
+    ids.sort_unstable();
+    ids.dedup();
+    ids
+}
+
+/// 見つからなかった (存在しないか他のユーザーの) ラベルがあれば、それらのIDをまとめてエラーにする
+fn check_unknown_labels(labels: &[i32], found: &[i32]) -> Result<(), RepositoryError> {
+    let unknown: Vec<i32> = labels
+        .iter()
+        .filter(|id| !found.contains(id))
+        .copied()
+        .collect();
+    if unknown.is_empty() {
+        Ok(())
+    } else {
+        Err(RepositoryError::UnknownLabels(unknown))
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+struct TodoWithLabelDto {
+    id: i32,
+    text: String,
+    status: TodoStatus,
+    due_at: Option<DateTime<Utc>>,
+    parent_id: Option<i32>,
+    blocked: bool,
+    recurrence: Option<sqlx::types::Json<Recurrence>>,
+    next_occurrence_id: Option<i32>,
+    priority: Priority,
+    estimate: Option<i32>,
+    comment_count: i64,
+    label_id: Option<i32>,
+    label_name: Option<String>,
+    label_color: Option<String>,
+    label_parent_id: Option<i32>,
+}
+
+/// Todo毎にまとまって並んだ行をラベル付きのTodoに集約する
+fn fold_todos(rows: Vec<TodoWithLabelDto>) -> Vec<Todo> {
+    let mut todos: Vec<Todo> = Vec::new();
+    for row in rows {
+        let label = row
+            .label_id
+            .zip(row.label_name)
+            .map(|(id, name)| Label::new(id, name, row.label_color, row.label_parent_id));
+        match todos.last_mut() {
+            Some(todo) if todo.id == row.id as u32 => todo.labels.extend(label),
+            _ => todos.push(Todo {
+                id: row.id as u32,
+                text: row.text,
+                status: row.status,
+                labels: label.into_iter().collect(),
+                due_at: row.due_at,
+                parent_id: row.parent_id.map(|id| id as u32),
+                blocked: row.blocked,
+                recurrence: row.recurrence.map(|recurrence| recurrence.0),
+                next_occurrence_id: row.next_occurrence_id.map(|id| id as u32),
+                priority: row.priority,
+                estimate: row.estimate.map(|estimate| estimate as u32),
+                comment_count: row.comment_count as u32,
+            }),
+        }
+    }
+    todos
+}
+
+#[derive(Debug, Clone, FromRow)]
+struct TodoSearchHitDto {
+    #[sqlx(flatten)]
+    todo: TodoWithLabelDto,
+    rank: f32,
+    snippet: String,
+}
+
+/// `fold_todos`と同様に、Todo毎にまとまって並んだ行を検索結果に集約する
+fn fold_search_hits(rows: Vec<TodoSearchHitDto>) -> Vec<TodoSearchHit> {
+    let mut ranks: Vec<(f32, String)> = Vec::new();
+    let mut last_id = None;
+    let mut todo_rows = Vec::with_capacity(rows.len());
+    for row in rows {
+        if last_id != Some(row.todo.id) {
+            last_id = Some(row.todo.id);
+            ranks.push((row.rank, row.snippet));
+        }
+        todo_rows.push(row.todo);
+    }
+
+    fold_todos(todo_rows)
+        .into_iter()
+        .zip(ranks)
+        .map(|(todo, (rank, snippet))| TodoSearchHit {
+            todo,
+            rank,
+            snippet: mark_snippet(&snippet),
+        })
+        .collect()
+}
+
+/// 目印で囲まれたスニペットの本文をエスケープし、目印を強調のタグに置き換える。
+/// 本文に目印と同じ文字が含まれていてもタグの対応は崩さない
+fn mark_snippet(snippet: &str) -> String {
+    let mut marked = String::with_capacity(snippet.len());
+    let mut open = false;
+    for c in snippet.chars() {
+        match c {
+            MARKER_START if !open => {
+                marked.push_str(HIGHLIGHT_START);
+                open = true;
+            }
+            MARKER_END if open => {
+                marked.push_str(HIGHLIGHT_END);
+                open = false;
+            }
+            MARKER_START | MARKER_END => {}
+            c => push_escaped(&mut marked, c),
+        }
+    }
+    if open {
+        marked.push_str(HIGHLIGHT_END);
+    }
+    marked
+}
+
+/// スニペットはHTMLとして表示されるため、本文の文字はエスケープして加える
+fn push_escaped(snippet: &mut String, c: char) {
+    match c {
+        '&' => snippet.push_str("&amp;"),
+        '<' => snippet.push_str("&lt;"),
+        '>' => snippet.push_str("&gt;"),
+        '"' => snippet.push_str("&quot;"),
+        '\'' => snippet.push_str("&#39;"),
+        c => snippet.push(c),
     }
 }
@@ -0,0 +1,1812 @@
+//! 全ての`TodoRepository`の実装が満たすべき振る舞い
+
+use chrono::{DateTime, Duration, DurationRound, Utc};
+
+use crate::repository::{
+    label::{CreateLabel, LabelRepository, LabelUsage, MergeLabel},
+    user::{conformance::create_user, UserRepository},
+    RepositoryError,
+};
+
+use super::{
+    Board, CreateDependency, CreateTodo, Frequency, MoveTodo, Priority, Recurrence, SortOrder,
+    Todo, TodoQuery, TodoRepository, TodoSearch, TodoSort, TodoStatus, UpdateTodo,
+};
+
+pub(crate) async fn run<R: TodoRepository, L: LabelRepository, U: UserRepository>(
+    repository: R,
+    label_repository: L,
+    user_repository: U,
+) {
+    let owner = create_user(&user_repository, "[todo] owner").await.id();
+    let other = create_user(&user_repository, "[todo] other").await.id();
+    let searcher = create_user(&user_repository, "[todo] searcher").await.id();
+    let scheduler = create_user(&user_repository, "[todo] scheduler").await.id();
+    let planner = create_user(&user_repository, "[todo] planner").await.id();
+    let parent = create_user(&user_repository, "[todo] parent").await.id();
+    let dependent = create_user(&user_repository, "[todo] dependent").await.id();
+    let recurring = create_user(&user_repository, "[todo] recurring").await.id();
+    let prioritizer = create_user(&user_repository, "[todo] prioritizer")
+        .await
+        .id();
+    let labeler = create_user(&user_repository, "[todo] labeler").await.id();
+    let merger = create_user(&user_repository, "[todo] merger").await.id();
+    let gardener = create_user(&user_repository, "[todo] gardener").await.id();
+
+    crud(&repository, owner).await;
+    ordering(&repository, owner).await;
+    not_found(&repository, owner).await;
+    id_allocation(&repository, owner).await;
+    labels(&repository, &label_repository, owner).await;
+    ownership(&repository, &label_repository, owner, other).await;
+    query(&repository, &label_repository, searcher).await;
+    search(&repository, searcher, other).await;
+    due_dates(&repository, scheduler).await;
+    status_workflow(&repository, owner, other).await;
+    board(&repository, planner, other).await;
+    subtasks(&repository, parent, other).await;
+    dependencies(&repository, dependent, other).await;
+    recurrence(&repository, &label_repository, recurring).await;
+    priorities(&repository, prioritizer).await;
+    label_usage(&repository, &label_repository, labeler).await;
+    label_merge(&repository, &label_repository, merger).await;
+    label_hierarchy(&repository, &label_repository, gardener).await;
+}
+
+/// `SubtaskCompletion::AutoComplete`を設定したリポジトリが満たすべき振る舞い
+pub(crate) async fn run_auto_complete<R: TodoRepository, U: UserRepository>(
+    repository: R,
+    user_repository: U,
+) {
+    use TodoStatus::*;
+
+    let user_id = create_user(&user_repository, "[todo] auto_complete")
+        .await
+        .id();
+    let create = |text: &str, parent: Option<u32>| {
+        let payload = CreateTodo::new(format!("[todo::auto_complete] {text}"), vec![]);
+        let payload = match parent {
+            Some(parent) => payload.with_parent(parent),
+            None => payload,
+        };
+        let repository = &repository;
+        async move {
+            repository
+                .create(user_id, payload)
+                .await
+                .expect("fail create todo")
+                .id
+        }
+    };
+    let status = |id: u32| {
+        let repository = &repository;
+        async move {
+            repository
+                .find(user_id, id)
+                .await
+                .expect("fail find todo")
+                .status
+        }
+    };
+
+    let parent = create("parent", None).await;
+    let a = create("a", Some(parent)).await;
+    let b = create("b", Some(parent)).await;
+    let grandchild = create("grandchild", Some(a)).await;
+    update_status(&repository, user_id, parent, InProgress)
+        .await
+        .unwrap();
+
+    // the last open subtask completes its parent, but not a parent with other open subtasks
+    complete(&repository, user_id, grandchild).await;
+    assert_eq!(status(a).await, Done);
+    assert_eq!(status(parent).await, InProgress);
+
+    complete(&repository, user_id, b).await;
+    assert_eq!(status(parent).await, Done);
+
+    // a todo with open subtasks still can not be completed by hand
+    let blocked = create("blocked", None).await;
+    create("open", Some(blocked)).await;
+    update_status(&repository, user_id, blocked, InProgress)
+        .await
+        .unwrap();
+    update_status(&repository, user_id, blocked, Review)
+        .await
+        .unwrap();
+    let result = update_status(&repository, user_id, blocked, Done).await;
+    assert!(
+        matches!(result, Err(RepositoryError::OpenSubtasks(id)) if id == blocked),
+        "unexpected result: {result:?}"
+    );
+
+    for id in [parent, blocked] {
+        repository.delete(user_id, id).await.unwrap();
+    }
+}
+
+/// 状態を一段階ずつ進めて完了にする
+pub(crate) async fn complete<R: TodoRepository>(repository: &R, user_id: i32, id: u32) -> Todo {
+    let mut todo = None;
+    for status in [TodoStatus::InProgress, TodoStatus::Review, TodoStatus::Done] {
+        todo = Some(
+            update_status(repository, user_id, id, status)
+                .await
+                .expect("fail update todo"),
+        );
+    }
+    todo.unwrap()
+}
+
+async fn update_status<R: TodoRepository>(
+    repository: &R,
+    user_id: i32,
+    id: u32,
+    status: TodoStatus,
+) -> Result<Todo, RepositoryError> {
+    repository
+        .update(
+            user_id,
+            id,
+            UpdateTodo {
+                text: None,
+                status: Some(status),
+                labels: None,
+                due_at: None,
+                recurrence: None,
+                priority: None,
+                estimate: None,
+            },
+        )
+        .await
+}
+
+/// 他のリポジトリのテストでデータを紐付けるTodoを作成する
+pub(crate) async fn create_todo<R: TodoRepository>(
+    repository: &R,
+    user_id: i32,
+    text: &str,
+) -> u32 {
+    repository
+        .create(user_id, CreateTodo::new(text.to_string(), vec![]))
+        .await
+        .expect("fail create todo")
+        .id()
+}
+
+async fn crud<R: TodoRepository>(repository: &R, user_id: i32) {
+    let text = "[todo::crud] text";
+
+    // create
+    let created = repository
+        .create(user_id, CreateTodo::new(text.to_string(), vec![]))
+        .await
+        .expect("fail create todo");
+    assert_eq!(created.text, text);
+    assert_eq!(created.status, TodoStatus::Backlog);
+    assert!(created.labels.is_empty());
+
+    // find
+    let todo = repository
+        .find(user_id, created.id)
+        .await
+        .expect("fail find todo");
+    assert_eq!(created, todo);
+
+    // all
+    let todos = repository
+        .all(user_id, &TodoQuery::default())
+        .await
+        .expect("fail fetch all todos")
+        .into_todos();
+    assert!(todos.contains(&created), "created todo is not listed");
+
+    // update
+    let updated_text = "[todo::crud] updated text";
+    let todo = repository
+        .update(
+            user_id,
+            created.id,
+            UpdateTodo {
+                text: Some(updated_text.to_string()),
+                status: Some(TodoStatus::InProgress),
+                labels: None,
+                due_at: None,
+                recurrence: None,
+                priority: None,
+                estimate: None,
+            },
+        )
+        .await
+        .expect("fail update todo");
+    assert_eq!(
+        Todo {
+            id: created.id,
+            text: updated_text.to_string(),
+            status: TodoStatus::InProgress,
+            labels: vec![],
+            due_at: None,
+            parent_id: None,
+            blocked: false,
+            recurrence: None,
+            next_occurrence_id: None,
+            priority: Priority::P2,
+            estimate: None,
+            comment_count: 0,
+        },
+        todo
+    );
+
+    // partial update keeps the other fields
+    let todo = repository
+        .update(
+            user_id,
+            created.id,
+            UpdateTodo {
+                text: None,
+                status: Some(TodoStatus::Backlog),
+                labels: None,
+                due_at: None,
+                recurrence: None,
+                priority: None,
+                estimate: None,
+            },
+        )
+        .await
+        .expect("fail update todo");
+    assert_eq!(todo.text, updated_text);
+    assert_eq!(todo.status, TodoStatus::Backlog);
+
+    // delete
+    repository
+        .delete(user_id, created.id)
+        .await
+        .expect("fail delete todo");
+    let todos = repository
+        .all(user_id, &TodoQuery::default())
+        .await
+        .expect("fail fetch all todos")
+        .into_todos();
+    assert!(todos.iter().all(|todo| todo.id != created.id));
+}
+
+/// `all`はIDの降順 (新しい順) で返す
+async fn ordering<R: TodoRepository>(repository: &R, user_id: i32) {
+    let older = repository
+        .create(
+            user_id,
+            CreateTodo::new("[todo::ordering] older".to_string(), vec![]),
+        )
+        .await
+        .expect("fail create todo");
+    let newer = repository
+        .create(
+            user_id,
+            CreateTodo::new("[todo::ordering] newer".to_string(), vec![]),
+        )
+        .await
+        .expect("fail create todo");
+
+    let todos = repository
+        .all(user_id, &TodoQuery::default())
+        .await
+        .expect("fail fetch all todos")
+        .into_todos();
+    assert!(
+        todos.windows(2).all(|pair| pair[0].id > pair[1].id),
+        "todos are not ordered by id descending: {todos:?}"
+    );
+    let position = |id| todos.iter().position(|todo| todo.id == id);
+    assert!(position(newer.id) < position(older.id));
+
+    repository.delete(user_id, older.id).await.unwrap();
+    repository.delete(user_id, newer.id).await.unwrap();
+}
+
+async fn not_found<R: TodoRepository>(repository: &R, user_id: i32) {
+    let todo = repository
+        .create(
+            user_id,
+            CreateTodo::new("[todo::not_found] text".to_string(), vec![]),
+        )
+        .await
+        .expect("fail create todo");
+    repository.delete(user_id, todo.id).await.unwrap();
+    let id = todo.id;
+
+    let result = repository.find(user_id, id).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(not_found)) if not_found == id),
+        "unexpected result: {result:?}"
+    );
+
+    let result = repository
+        .update(
+            user_id,
+            id,
+            UpdateTodo {
+                text: Some("[todo::not_found] updated text".to_string()),
+                status: None,
+                labels: None,
+                due_at: None,
+                recurrence: None,
+                priority: None,
+                estimate: None,
+            },
+        )
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(not_found)) if not_found == id),
+        "unexpected result: {result:?}"
+    );
+
+    let result = repository.delete(user_id, id).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(not_found)) if not_found == id),
+        "unexpected result: {result:?}"
+    );
+}
+
+/// 削除されたIDは再利用されない
+async fn id_allocation<R: TodoRepository>(repository: &R, user_id: i32) {
+    let first = repository
+        .create(
+            user_id,
+            CreateTodo::new("[todo::id_allocation] first".to_string(), vec![]),
+        )
+        .await
+        .expect("fail create todo");
+    let second = repository
+        .create(
+            user_id,
+            CreateTodo::new("[todo::id_allocation] second".to_string(), vec![]),
+        )
+        .await
+        .expect("fail create todo");
+    assert!(first.id < second.id);
+
+    repository.delete(user_id, first.id).await.unwrap();
+    let third = repository
+        .create(
+            user_id,
+            CreateTodo::new("[todo::id_allocation] third".to_string(), vec![]),
+        )
+        .await
+        .expect("fail create todo");
+    assert!(second.id < third.id);
+    let todo = repository
+        .find(user_id, second.id)
+        .await
+        .expect("fail find todo");
+    assert_eq!(second, todo);
+
+    repository.delete(user_id, second.id).await.unwrap();
+    repository.delete(user_id, third.id).await.unwrap();
+}
+
+/// ラベルはIDの昇順に重複なく紐付き、削除されたラベルはTodoから外れる
+async fn labels<R: TodoRepository, L: LabelRepository>(
+    repository: &R,
+    label_repository: &L,
+    user_id: i32,
+) {
+    let first = label_repository
+        .create(
+            user_id,
+            CreateLabel::new("[todo::labels] first".to_string()),
+        )
+        .await
+        .expect("fail create label");
+    let second = label_repository
+        .create(
+            user_id,
+            CreateLabel::new("[todo::labels] second".to_string()),
+        )
+        .await
+        .expect("fail create label");
+    let deleted = label_repository
+        .create(
+            user_id,
+            CreateLabel::new("[todo::labels] deleted".to_string()),
+        )
+        .await
+        .expect("fail create label");
+    label_repository
+        .delete(user_id, deleted.id(), false)
+        .await
+        .unwrap();
+
+    // create with labels
+    let todo = repository
+        .create(
+            user_id,
+            CreateTodo::new(
+                "[todo::labels] text".to_string(),
+                vec![second.id(), first.id(), second.id()],
+            ),
+        )
+        .await
+        .expect("fail create todo");
+    assert_eq!(vec![first.clone(), second.clone()], todo.labels);
+    let found = repository
+        .find(user_id, todo.id)
+        .await
+        .expect("fail find todo");
+    assert_eq!(todo, found);
+
+    // unknown labels are reported together
+    let result = repository
+        .create(
+            user_id,
+            CreateTodo::new(
+                "[todo::labels] unknown".to_string(),
+                vec![i32::MAX, first.id(), deleted.id()],
+            ),
+        )
+        .await;
+    assert!(
+        matches!(&result, Err(RepositoryError::UnknownLabels(ids)) if *ids == [deleted.id(), i32::MAX]),
+        "unexpected result: {result:?}"
+    );
+    let result = repository
+        .update(
+            user_id,
+            todo.id,
+            UpdateTodo {
+                text: Some("[todo::labels] unknown".to_string()),
+                status: None,
+                labels: Some(vec![first.id(), deleted.id()]),
+                due_at: None,
+                recurrence: None,
+                priority: None,
+                estimate: None,
+            },
+        )
+        .await;
+    assert!(
+        matches!(&result, Err(RepositoryError::UnknownLabels(ids)) if *ids == [deleted.id()]),
+        "unexpected result: {result:?}"
+    );
+    let found = repository
+        .find(user_id, todo.id)
+        .await
+        .expect("fail find todo");
+    assert_eq!(todo, found, "failed update must not change the todo");
+
+    // replace labels
+    let todo = repository
+        .update(
+            user_id,
+            todo.id,
+            UpdateTodo {
+                text: None,
+                status: None,
+                labels: Some(vec![first.id()]),
+                due_at: None,
+                recurrence: None,
+                priority: None,
+                estimate: None,
+            },
+        )
+        .await
+        .expect("fail update todo");
+    assert_eq!(vec![first.clone()], todo.labels);
+
+    // label in use is not deleted unless forced, then detached
+    let result = label_repository.delete(user_id, first.id(), false).await;
+    assert!(
+        matches!(result, Err(RepositoryError::LabelInUse { id, todos: 1 }) if id == first.id()),
+        "unexpected result: {result:?}"
+    );
+    label_repository
+        .delete(user_id, first.id(), true)
+        .await
+        .unwrap();
+    let todo = repository
+        .find(user_id, todo.id)
+        .await
+        .expect("fail find todo");
+    assert!(todo.labels.is_empty());
+
+    repository.delete(user_id, todo.id).await.unwrap();
+    label_repository
+        .delete(user_id, second.id(), false)
+        .await
+        .unwrap();
+}
+
+/// 他のユーザーのTodoやラベルは存在しないものとして扱う
+async fn ownership<R: TodoRepository, L: LabelRepository>(
+    repository: &R,
+    label_repository: &L,
+    owner: i32,
+    other: i32,
+) {
+    let label = label_repository
+        .create(
+            owner,
+            CreateLabel::new("[todo::ownership] label".to_string()),
+        )
+        .await
+        .expect("fail create label");
+    let todo = repository
+        .create(
+            owner,
+            CreateTodo::new("[todo::ownership] text".to_string(), vec![label.id()]),
+        )
+        .await
+        .expect("fail create todo");
+    let id = todo.id;
+
+    let todos = repository
+        .all(other, &TodoQuery::default())
+        .await
+        .expect("fail fetch all todos")
+        .into_todos();
+    assert!(
+        todos.iter().all(|todo| todo.id != id),
+        "other user's todo is listed"
+    );
+
+    let result = repository.find(other, id).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(not_found)) if not_found == id),
+        "unexpected result: {result:?}"
+    );
+
+    let result = repository
+        .update(
+            other,
+            id,
+            UpdateTodo {
+                text: Some("[todo::ownership] updated text".to_string()),
+                status: Some(TodoStatus::InProgress),
+                labels: Some(vec![]),
+                due_at: None,
+                recurrence: None,
+                priority: None,
+                estimate: None,
+            },
+        )
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(not_found)) if not_found == id),
+        "unexpected result: {result:?}"
+    );
+
+    let result = repository.delete(other, id).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(not_found)) if not_found == id),
+        "unexpected result: {result:?}"
+    );
+
+    let found = repository.find(owner, id).await.expect("fail find todo");
+    assert_eq!(todo, found, "other user must not change the todo");
+
+    // other user's label cannot be attached
+    let result = repository
+        .create(
+            other,
+            CreateTodo::new("[todo::ownership] other".to_string(), vec![label.id()]),
+        )
+        .await;
+    assert!(
+        matches!(&result, Err(RepositoryError::UnknownLabels(ids)) if *ids == [label.id()]),
+        "unexpected result: {result:?}"
+    );
+
+    repository.delete(owner, id).await.unwrap();
+    label_repository
+        .delete(owner, label.id(), false)
+        .await
+        .unwrap();
+}
+
+/// 絞り込み・並び替え・ページ分割はどの実装でも同じ結果になる
+async fn query<R: TodoRepository, L: LabelRepository>(
+    repository: &R,
+    label_repository: &L,
+    user_id: i32,
+) {
+    let a = label_repository
+        .create(user_id, CreateLabel::new("[todo::query] a".to_string()))
+        .await
+        .expect("fail create label");
+    let b = label_repository
+        .create(user_id, CreateLabel::new("[todo::query] b".to_string()))
+        .await
+        .expect("fail create label");
+
+    let mut ids = Vec::new();
+    for (text, labels) in [
+        ("Buy milk", vec![a.id()]),
+        ("buy BREAD", vec![a.id(), b.id()]),
+        ("Walk 100% dog_", vec![]),
+        ("clean", vec![b.id()]),
+    ] {
+        let todo = repository
+            .create(user_id, CreateTodo::new(text.to_string(), labels))
+            .await
+            .expect("fail create todo");
+        ids.push(todo.id);
+    }
+    complete(repository, user_id, ids[1]).await;
+
+    let search = |query: TodoQuery| async move {
+        let page = repository
+            .all(user_id, &query)
+            .await
+            .expect("fail fetch all todos");
+        let ids: Vec<u32> = page.todos().iter().map(|todo| todo.id).collect();
+        (ids, page.next_offset())
+    };
+    let [milk, bread, walk, clean] = [ids[0], ids[1], ids[2], ids[3]];
+
+    // status
+    let (found, _) = search(TodoQuery {
+        status: Some(TodoStatus::Done),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(found, vec![bread]);
+    let (found, _) = search(TodoQuery {
+        status: Some(TodoStatus::Backlog),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(found, vec![clean, walk, milk]);
+
+    // text matches case-insensitively and treats wildcards literally
+    let (found, _) = search(TodoQuery {
+        text: Some("BUY".to_string()),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(found, vec![bread, milk]);
+    for text in ["100%", "%", "_"] {
+        let (found, _) = search(TodoQuery {
+            text: Some(text.to_string()),
+            ..Default::default()
+        })
+        .await;
+        assert_eq!(found, vec![walk], "text: {text}");
+    }
+
+    // labels must all be attached
+    let (found, _) = search(TodoQuery {
+        labels: vec![a.id()],
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(found, vec![bread, milk]);
+    let (found, _) = search(TodoQuery {
+        labels: vec![b.id(), a.id(), b.id()],
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(found, vec![bread]);
+
+    // sort
+    let (found, _) = search(TodoQuery {
+        sort: vec![TodoSort::Text],
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(found, vec![milk, walk, bread, clean]);
+    let (found, _) = search(TodoQuery {
+        sort: vec![TodoSort::Text],
+        order: Some(SortOrder::Desc),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(found, vec![clean, bread, walk, milk]);
+    let (found, _) = search(TodoQuery {
+        order: Some(SortOrder::Asc),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(found, vec![milk, bread, walk, clean]);
+
+    // pagination
+    let page = |offset| TodoQuery {
+        order: Some(SortOrder::Asc),
+        limit: Some(2),
+        offset,
+        ..Default::default()
+    };
+    assert_eq!(search(page(0)).await, (vec![milk, bread], Some(2)));
+    assert_eq!(search(page(2)).await, (vec![walk, clean], None));
+    assert_eq!(search(page(4)).await, (vec![], None));
+
+    for id in ids {
+        repository.delete(user_id, id).await.unwrap();
+    }
+    label_repository
+        .delete(user_id, a.id(), false)
+        .await
+        .unwrap();
+    label_repository
+        .delete(user_id, b.id(), false)
+        .await
+        .unwrap();
+}
+
+/// 検索語を全て含むTodoだけを、短く一致度の高いものから順に返す
+async fn search<R: TodoRepository>(repository: &R, user_id: i32, other: i32) {
+    let mut ids = Vec::new();
+    for text in ["Buy fresh milk", "milk", "bread and butter"] {
+        let todo = repository
+            .create(user_id, CreateTodo::new(text.to_string(), vec![]))
+            .await
+            .expect("fail create todo");
+        ids.push(todo.id);
+    }
+    let [fresh_milk, milk, bread] = [ids[0], ids[1], ids[2]];
+    let others_milk = repository
+        .create(other, CreateTodo::new("milk".to_string(), vec![]))
+        .await
+        .expect("fail create todo");
+
+    let search = |q: &str, limit: Option<u32>| {
+        let search = TodoSearch {
+            q: q.to_string(),
+            limit,
+        };
+        async move {
+            repository
+                .search(user_id, &search)
+                .await
+                .expect("fail search todos")
+        }
+    };
+
+    let hits = search("milk", None).await;
+    let found: Vec<u32> = hits.iter().map(|hit| hit.todo.id).collect();
+    assert_eq!(found, vec![milk, fresh_milk]);
+    assert!(hits[0].rank > hits[1].rank, "unexpected ranks: {hits:?}");
+    assert_eq!(hits[1].snippet, "Buy fresh <mark>milk</mark>");
+
+    // every term must match regardless of case
+    let hits = search("MILK buy", None).await;
+    let found: Vec<u32> = hits.iter().map(|hit| hit.todo.id).collect();
+    assert_eq!(found, vec![fresh_milk]);
+    assert_eq!(hits[0].snippet, "<mark>Buy</mark> fresh <mark>milk</mark>");
+
+    assert!(search("cheese", None).await.is_empty());
+    assert!(search("!?", None).await.is_empty());
+
+    // the text around the highlights is escaped as HTML
+    let markup = repository
+        .create(
+            user_id,
+            CreateTodo::new(r#"a < b & "cheddar" > 'c'"#.to_string(), vec![]),
+        )
+        .await
+        .expect("fail create todo");
+    let hits = search("cheddar", None).await;
+    assert_eq!(
+        hits[0].snippet,
+        "a &lt; b &amp; &quot;<mark>cheddar</mark>&quot; &gt; &#39;c&#39;"
+    );
+    repository.delete(user_id, markup.id).await.unwrap();
+
+    let hits = search("milk", Some(1)).await;
+    let found: Vec<u32> = hits.iter().map(|hit| hit.todo.id).collect();
+    assert_eq!(found, vec![milk]);
+
+    // updated text is searchable and deleted todos are not
+    repository
+        .update(
+            user_id,
+            bread,
+            UpdateTodo {
+                text: Some("bread and milk".to_string()),
+                status: None,
+                labels: None,
+                due_at: None,
+                recurrence: None,
+                priority: None,
+                estimate: None,
+            },
+        )
+        .await
+        .expect("fail update todo");
+    repository.delete(user_id, fresh_milk).await.unwrap();
+    let found: Vec<u32> = search("milk", None)
+        .await
+        .iter()
+        .map(|hit| hit.todo.id)
+        .collect();
+    assert_eq!(found, vec![milk, bread]);
+
+    repository.delete(user_id, milk).await.unwrap();
+    repository.delete(user_id, bread).await.unwrap();
+    repository.delete(other, others_milk.id).await.unwrap();
+}
+
+/// 期限はタイムゾーンを問わず同じ時刻として保存され、期限切れは未完了のものだけ
+async fn due_dates<R: TodoRepository>(repository: &R, user_id: i32) {
+    // Postgresはマイクロ秒までしか保存しないため秒単位に丸める
+    let now = Utc::now().duration_trunc(Duration::seconds(1)).unwrap();
+    let past = now - Duration::days(1);
+    let future = now + Duration::days(1);
+
+    let create = |text: &str, due_at: Option<DateTime<Utc>>| {
+        let payload = CreateTodo {
+            due_at,
+            ..CreateTodo::new(format!("[todo::due_dates] {text}"), vec![])
+        };
+        async move {
+            repository
+                .create(user_id, payload)
+                .await
+                .expect("fail create todo")
+        }
+    };
+    let overdue = create("overdue", Some(past)).await;
+    let upcoming = create("upcoming", Some(future)).await;
+    let done = create("done", Some(past)).await;
+    let someday = create("someday", None).await;
+    assert_eq!(overdue.due_at, Some(past));
+    assert_eq!(someday.due_at, None);
+
+    let found = repository
+        .find(user_id, overdue.id)
+        .await
+        .expect("fail find todo");
+    assert_eq!(found.due_at, Some(past));
+
+    // omitted due_at is kept
+    let done = complete(repository, user_id, done.id).await;
+    assert_eq!(done.due_at, Some(past));
+
+    let search = |query: TodoQuery| async move {
+        repository
+            .all(user_id, &query)
+            .await
+            .expect("fail fetch all todos")
+            .todos()
+            .iter()
+            .map(|todo| todo.id)
+            .collect::<Vec<u32>>()
+    };
+
+    let found = search(TodoQuery {
+        overdue: Some(true),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(found, vec![overdue.id]);
+    let found = search(TodoQuery {
+        overdue: Some(false),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(found, vec![someday.id, done.id, upcoming.id]);
+
+    let found = search(TodoQuery {
+        due_before: Some(now),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(found, vec![done.id, overdue.id]);
+    let found = search(TodoQuery {
+        due_before: Some(future + Duration::seconds(1)),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(found, vec![done.id, upcoming.id, overdue.id]);
+
+    // null clears due_at
+    let cleared = repository
+        .update(
+            user_id,
+            overdue.id,
+            UpdateTodo {
+                text: None,
+                status: None,
+                labels: None,
+                due_at: Some(None),
+                recurrence: None,
+                priority: None,
+                estimate: None,
+            },
+        )
+        .await
+        .expect("fail update todo");
+    assert_eq!(cleared.due_at, None);
+    let found = search(TodoQuery {
+        overdue: Some(true),
+        ..Default::default()
+    })
+    .await;
+    assert!(found.is_empty(), "unexpected overdue todos: {found:?}");
+
+    for id in [overdue.id, upcoming.id, done.id, someday.id] {
+        repository.delete(user_id, id).await.unwrap();
+    }
+}
+
+/// 状態は隣り合う段階にだけ変更でき、許可されない変更は何も変えずにエラーになる
+async fn status_workflow<R: TodoRepository>(repository: &R, owner: i32, other: i32) {
+    use TodoStatus::*;
+
+    let todo = repository
+        .create(
+            owner,
+            CreateTodo::new("[todo::status_workflow] text".to_string(), vec![]),
+        )
+        .await
+        .expect("fail create todo");
+    let id = todo.id;
+
+    for (from, to) in [(Backlog, Review), (Backlog, Done)] {
+        let result = update_status(repository, owner, id, to).await;
+        assert!(
+            matches!(
+                result,
+                Err(RepositoryError::InvalidTransition { id: found, from: f, to: t })
+                    if found == id && f == from && t == to
+            ),
+            "unexpected result: {result:?}"
+        );
+    }
+    let found = repository.find(owner, id).await.expect("fail find todo");
+    assert_eq!(
+        found.status, Backlog,
+        "rejected transition changed the todo"
+    );
+
+    // every step forward and back, and the same status
+    for status in [
+        InProgress, InProgress, Backlog, InProgress, Review, InProgress, Review, Done, Review, Done,
+    ] {
+        let todo = update_status(repository, owner, id, status)
+            .await
+            .unwrap_or_else(|error| panic!("fail change status to {status}: {error}"));
+        assert_eq!(todo.status, status);
+    }
+
+    let result = update_status(repository, owner, id, InProgress).await;
+    assert!(
+        matches!(
+            result,
+            Err(RepositoryError::InvalidTransition {
+                from: Done,
+                to: InProgress,
+                ..
+            })
+        ),
+        "unexpected result: {result:?}"
+    );
+
+    // other user's todo is not found rather than rejected
+    let result = update_status(repository, other, id, Backlog).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(not_found)) if not_found == id),
+        "unexpected result: {result:?}"
+    );
+
+    repository.delete(owner, id).await.unwrap();
+}
+
+/// ボードは全ての状態の列を持ち、動かしたTodoは指定した位置に並ぶ
+async fn board<R: TodoRepository>(repository: &R, user_id: i32, other: i32) {
+    use TodoStatus::*;
+
+    let columns = |board: Board| -> Vec<(TodoStatus, Vec<u32>)> {
+        board
+            .columns
+            .into_iter()
+            .map(|column| {
+                let ids = column.todos.iter().map(|todo| todo.id).collect();
+                (column.status, ids)
+            })
+            .collect()
+    };
+    let board = || async { columns(repository.board(user_id).await.expect("fail fetch board")) };
+    let move_todo = |id: u32, status: Option<TodoStatus>, after: Option<u32>| async move {
+        repository
+            .move_todo(user_id, id, MoveTodo::new(status, after))
+            .await
+    };
+
+    let mut ids = Vec::new();
+    for text in ["a", "b", "c"] {
+        let todo = repository
+            .create(
+                user_id,
+                CreateTodo::new(format!("[todo::board] {text}"), vec![]),
+            )
+            .await
+            .expect("fail create todo");
+        ids.push(todo.id);
+    }
+    let [a, b, c] = [ids[0], ids[1], ids[2]];
+    assert_eq!(
+        board().await,
+        vec![
+            (Backlog, vec![a, b, c]),
+            (InProgress, vec![]),
+            (Review, vec![]),
+            (Done, vec![]),
+        ]
+    );
+
+    // reorder within a column
+    move_todo(c, None, None).await.expect("fail move todo");
+    assert_eq!(board().await[0], (Backlog, vec![c, a, b]));
+    move_todo(a, None, Some(b)).await.expect("fail move todo");
+    assert_eq!(board().await[0], (Backlog, vec![c, b, a]));
+    move_todo(a, None, Some(c)).await.expect("fail move todo");
+    assert_eq!(board().await[0], (Backlog, vec![c, a, b]));
+
+    // move across columns
+    let moved = move_todo(b, Some(InProgress), None)
+        .await
+        .expect("fail move todo");
+    assert_eq!(moved.status, InProgress);
+    move_todo(c, Some(InProgress), Some(b))
+        .await
+        .expect("fail move todo");
+    assert_eq!(
+        board().await,
+        vec![
+            (Backlog, vec![a]),
+            (InProgress, vec![b, c]),
+            (Review, vec![]),
+            (Done, vec![]),
+        ]
+    );
+
+    // new todos are appended to the backlog
+    let d = repository
+        .create(
+            user_id,
+            CreateTodo::new("[todo::board] d".to_string(), vec![]),
+        )
+        .await
+        .expect("fail create todo")
+        .id;
+    assert_eq!(board().await[0], (Backlog, vec![a, d]));
+
+    // moving into the same gap many times keeps the order
+    for _ in 0..20 {
+        move_todo(a, None, Some(d)).await.expect("fail move todo");
+        move_todo(d, None, Some(a)).await.expect("fail move todo");
+    }
+    move_todo(d, None, None).await.expect("fail move todo");
+    assert_eq!(board().await[0], (Backlog, vec![d, a]));
+
+    // the workflow still applies
+    let result = move_todo(a, Some(Done), None).await;
+    assert!(
+        matches!(
+            result,
+            Err(RepositoryError::InvalidTransition {
+                from: Backlog,
+                to: Done,
+                ..
+            })
+        ),
+        "unexpected result: {result:?}"
+    );
+
+    // `after` must be another todo in the destination column
+    for after in [b, a] {
+        let result = move_todo(a, None, Some(after)).await;
+        assert!(
+            matches!(result, Err(RepositoryError::NotFound(id)) if id == after),
+            "unexpected result: {result:?}"
+        );
+    }
+    assert_eq!(board().await[0], (Backlog, vec![d, a]));
+
+    // other user's todos are neither listed nor movable
+    let others = repository.board(other).await.expect("fail fetch board");
+    assert!(others
+        .columns
+        .iter()
+        .flat_map(|column| &column.todos)
+        .all(|todo| !ids.contains(&todo.id) && todo.id != d));
+    let result = repository
+        .move_todo(other, a, MoveTodo::new(None, None))
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == a),
+        "unexpected result: {result:?}"
+    );
+
+    for id in [a, b, c, d] {
+        repository.delete(user_id, id).await.unwrap();
+    }
+}
+
+/// サブタスクは親と同じユーザーのもので、未完了の間は親を完了にできず、親と一緒に削除される
+async fn subtasks<R: TodoRepository>(repository: &R, user_id: i32, other: i32) {
+    use TodoStatus::*;
+
+    let create = |text: &str, parent: Option<u32>| {
+        let payload = CreateTodo::new(format!("[todo::subtasks] {text}"), vec![]);
+        let payload = match parent {
+            Some(parent) => payload.with_parent(parent),
+            None => payload,
+        };
+        async move {
+            repository
+                .create(user_id, payload)
+                .await
+                .expect("fail create todo")
+        }
+    };
+    let children = |id: u32| async move {
+        repository
+            .children(user_id, id)
+            .await
+            .expect("fail fetch children")
+            .iter()
+            .map(|todo| todo.id)
+            .collect::<Vec<_>>()
+    };
+
+    let parent = create("parent", None).await;
+    assert_eq!(parent.parent_id(), None);
+    let a = create("a", Some(parent.id)).await;
+    assert_eq!(a.parent_id(), Some(parent.id));
+    let grandchild = create("grandchild", Some(a.id)).await.id;
+    let b = create("b", Some(parent.id)).await.id;
+    let (parent, a) = (parent.id, a.id);
+
+    assert_eq!(children(parent).await, vec![a, b]);
+    assert_eq!(children(a).await, vec![grandchild]);
+    assert_eq!(children(b).await, Vec::<u32>::new());
+
+    // other user's todo can be neither a parent nor listed
+    let result = repository
+        .create(
+            other,
+            CreateTodo::new("[todo::subtasks] foreign".to_string(), vec![]).with_parent(parent),
+        )
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == parent),
+        "unexpected result: {result:?}"
+    );
+    let result = repository.children(other, parent).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == parent),
+        "unexpected result: {result:?}"
+    );
+
+    // open subtasks at any depth block completion, by update and by move
+    update_status(repository, user_id, parent, InProgress)
+        .await
+        .unwrap();
+    update_status(repository, user_id, parent, Review)
+        .await
+        .unwrap();
+    complete(repository, user_id, b).await;
+    let result = update_status(repository, user_id, parent, Done).await;
+    assert!(
+        matches!(result, Err(RepositoryError::OpenSubtasks(id)) if id == parent),
+        "unexpected result: {result:?}"
+    );
+    complete(repository, user_id, grandchild).await;
+    let result = repository
+        .move_todo(user_id, parent, MoveTodo::new(Some(Done), None))
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::OpenSubtasks(id)) if id == parent),
+        "unexpected result: {result:?}"
+    );
+    let found = repository.find(user_id, parent).await.unwrap();
+    assert_eq!(found.status, Review, "blocked completion changed the todo");
+
+    // completing the last subtask leaves the parent to the user
+    complete(repository, user_id, a).await;
+    assert_eq!(
+        repository.find(user_id, parent).await.unwrap().status,
+        Review
+    );
+    let todo = update_status(repository, user_id, parent, Done)
+        .await
+        .expect("fail complete todo");
+    assert_eq!(todo.status, Done);
+
+    // deleting a todo deletes its whole subtree, but only for the owner
+    let result = repository.delete(other, parent).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == parent),
+        "unexpected result: {result:?}"
+    );
+    repository.delete(user_id, parent).await.unwrap();
+    for id in [parent, a, b, grandchild] {
+        let result = repository.find(user_id, id).await;
+        assert!(
+            matches!(result, Err(RepositoryError::NotFound(not_found)) if not_found == id),
+            "unexpected result: {result:?}"
+        );
+    }
+    let result = repository.children(user_id, parent).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == parent),
+        "unexpected result: {result:?}"
+    );
+}
+
+/// 依存関係は同じユーザーのTodoの間に限られ、循環せず、完了していないTodoにブロックされている間だけ`blocked`になる
+async fn dependencies<R: TodoRepository>(repository: &R, user_id: i32, other: i32) {
+    let create = |text: &str| {
+        let payload = CreateTodo::new(format!("[todo::dependencies] {text}"), vec![]);
+        async move {
+            repository
+                .create(user_id, payload)
+                .await
+                .expect("fail create todo")
+                .id
+        }
+    };
+    let link = |id: u32, blocked_by: u32| {
+        repository.add_dependency(user_id, id, CreateDependency::new(blocked_by))
+    };
+    let blocked = |id: u32| async move {
+        repository
+            .find(user_id, id)
+            .await
+            .expect("fail find todo")
+            .blocked
+    };
+
+    let a = create("a").await;
+    let b = create("b").await;
+    let c = create("c").await;
+    assert!(!blocked(a).await);
+
+    // a is blocked by b, which is blocked by c
+    let todo = link(a, b).await.expect("fail add dependency");
+    assert!(todo.blocked);
+    assert!(!blocked(b).await);
+    link(a, b)
+        .await
+        .expect("fail add the same dependency twice");
+    link(b, c).await.expect("fail add dependency");
+    assert!(blocked(b).await);
+
+    for (id, blocked_by) in [(a, a), (b, a), (c, a)] {
+        let result = link(id, blocked_by).await;
+        assert!(
+            matches!(
+                result,
+                Err(RepositoryError::DependencyCycle { id: found, blocked_by: by })
+                    if found == id && by == blocked_by
+            ),
+            "unexpected result: {result:?}"
+        );
+    }
+
+    // other user's todos can be neither linked nor blocked
+    let foreign = repository
+        .create(
+            other,
+            CreateTodo::new("[todo::dependencies] foreign".to_string(), vec![]),
+        )
+        .await
+        .expect("fail create todo")
+        .id;
+    for (user, id, blocked_by, missing) in [
+        (user_id, a, foreign, foreign),
+        (user_id, foreign, a, foreign),
+        (other, a, foreign, a),
+    ] {
+        let result = repository
+            .add_dependency(user, id, CreateDependency::new(blocked_by))
+            .await;
+        assert!(
+            matches!(result, Err(RepositoryError::NotFound(id)) if id == missing),
+            "unexpected result: {result:?}"
+        );
+    }
+
+    // completing the blocker unblocks the todo in every listing
+    complete(repository, user_id, b).await;
+    assert!(!blocked(a).await);
+    assert!(blocked(b).await);
+    let page = repository
+        .all(user_id, &TodoQuery::default())
+        .await
+        .expect("fail fetch all todos");
+    let listed: Vec<(u32, bool)> = page
+        .todos()
+        .iter()
+        .map(|todo| (todo.id, todo.blocked))
+        .collect();
+    assert_eq!(listed, vec![(c, false), (b, true), (a, false)]);
+
+    // deleting the blocker removes the dependency
+    repository.delete(user_id, c).await.unwrap();
+    assert!(!blocked(b).await);
+    let todo = link(c, a).await;
+    assert!(
+        matches!(todo, Err(RepositoryError::NotFound(id)) if id == c),
+        "unexpected result: {todo:?}"
+    );
+
+    for id in [a, b] {
+        repository.delete(user_id, id).await.unwrap();
+    }
+    repository.delete(other, foreign).await.unwrap();
+}
+
+/// 繰り返すTodoを完了にすると、次の期限で同じ内容のTodoができて規則が引き継がれる。完了にしたTodoも規則を残す
+async fn recurrence<R: TodoRepository, L: LabelRepository>(
+    repository: &R,
+    label_repository: &L,
+    user_id: i32,
+) {
+    let label = label_repository
+        .create(
+            user_id,
+            CreateLabel::new("[todo::recurrence] chore".to_string()),
+        )
+        .await
+        .expect("fail create label");
+    let all = || async {
+        repository
+            .all(user_id, &TodoQuery::default())
+            .await
+            .expect("fail fetch all todos")
+            .todos()
+            .to_vec()
+    };
+
+    let due_at: DateTime<Utc> = "2030-01-07T09:00:00Z".parse().unwrap();
+    let weekly = Recurrence::new(Frequency::Weekly).with_count(2);
+    let todo = repository
+        .create(
+            user_id,
+            CreateTodo::new("[todo::recurrence] weekly".to_string(), vec![label.id()])
+                .with_due_at(due_at)
+                .with_recurrence(weekly.clone())
+                .with_priority(Priority::P1)
+                .with_estimate(2),
+        )
+        .await
+        .expect("fail create todo");
+    assert_eq!(todo.recurrence, Some(weekly));
+
+    // the rule is kept and the next occurrence is recorded
+    let completed = complete(repository, user_id, todo.id).await;
+    assert_eq!(completed.recurrence, todo.recurrence);
+    let todos = all().await;
+    assert_eq!(todos.len(), 2);
+    let next = &todos[0];
+    assert_ne!(next.id, todo.id);
+    assert_eq!(completed.next_occurrence_id, Some(next.id));
+    assert_eq!(next.next_occurrence_id, None);
+    assert_eq!(next.text, todo.text);
+    assert_eq!(next.status, TodoStatus::Backlog);
+    assert_eq!(next.labels, todo.labels);
+    assert_eq!(next.due_at, Some(due_at + Duration::days(7)));
+    assert_eq!((next.priority, next.estimate), (Priority::P1, Some(2)));
+    assert_eq!(
+        next.recurrence,
+        Some(Recurrence::new(Frequency::Weekly).with_count(1))
+    );
+
+    // completing again does not repeat it twice, and the last occurrence ends it
+    update_status(repository, user_id, todo.id, TodoStatus::Review)
+        .await
+        .unwrap();
+    update_status(repository, user_id, todo.id, TodoStatus::Done)
+        .await
+        .unwrap();
+    assert_eq!(all().await.len(), 2);
+    let last = complete(repository, user_id, next.id).await;
+    assert_eq!(last.recurrence, next.recurrence);
+    assert_eq!(last.next_occurrence_id, None);
+    assert_eq!(all().await.len(), 2);
+
+    // deleting the next occurrence clears the reference
+    repository.delete(user_id, next.id).await.unwrap();
+    let found = repository
+        .find(user_id, todo.id)
+        .await
+        .expect("fail find todo");
+    assert_eq!(found.next_occurrence_id, None);
+
+    // the rule can be set and cleared by update, and without a due date it starts from completion
+    let daily = Recurrence::new(Frequency::Daily);
+    let undated = repository
+        .create(
+            user_id,
+            CreateTodo::new("[todo::recurrence] daily".to_string(), vec![]),
+        )
+        .await
+        .expect("fail create todo");
+    let updated = repository
+        .update(
+            user_id,
+            undated.id,
+            UpdateTodo {
+                text: None,
+                status: None,
+                labels: None,
+                due_at: None,
+                recurrence: Some(Some(daily.clone())),
+                priority: None,
+                estimate: None,
+            },
+        )
+        .await
+        .expect("fail update todo");
+    assert_eq!(updated.recurrence, Some(daily.clone()));
+    let before = Utc::now();
+    complete(repository, user_id, undated.id).await;
+    let after = Utc::now();
+    let todos = all().await;
+    assert_eq!(todos.len(), 3);
+    let next = &todos[0];
+    assert_eq!(next.recurrence, Some(daily));
+    let next_due = next.due_at.expect("no due date");
+    assert!(
+        before + Duration::days(1) - Duration::seconds(1) <= next_due
+            && next_due <= after + Duration::days(1),
+        "unexpected due date: {next_due}"
+    );
+    let cleared = repository
+        .update(
+            user_id,
+            next.id,
+            UpdateTodo {
+                text: None,
+                status: None,
+                labels: None,
+                due_at: None,
+                recurrence: Some(None),
+                priority: None,
+                estimate: None,
+            },
+        )
+        .await
+        .expect("fail update todo");
+    assert_eq!(cleared.recurrence, None);
+
+    for todo in todos {
+        repository.delete(user_id, todo.id).await.unwrap();
+    }
+    label_repository
+        .delete(user_id, label.id(), false)
+        .await
+        .unwrap();
+}
+
+/// 優先度と見積もりは作成時と更新時に指定でき、期限と組み合わせて並び替えられる
+async fn priorities<R: TodoRepository>(repository: &R, user_id: i32) {
+    let day =
+        |day: u32| -> DateTime<Utc> { format!("2030-01-{day:02}T09:00:00Z").parse().unwrap() };
+    let create = |text: &str, priority: Priority, due_at: Option<u32>, estimate: Option<u32>| {
+        let mut payload =
+            CreateTodo::new(format!("[todo::priorities] {text}"), vec![]).with_priority(priority);
+        if let Some(due_at) = due_at {
+            payload = payload.with_due_at(day(due_at));
+        }
+        if let Some(estimate) = estimate {
+            payload = payload.with_estimate(estimate);
+        }
+        async move {
+            repository
+                .create(user_id, payload)
+                .await
+                .expect("fail create todo")
+        }
+    };
+    let sorted = |sort: Vec<TodoSort>, order: Option<SortOrder>| async move {
+        let query = TodoQuery {
+            sort,
+            order,
+            ..Default::default()
+        };
+        repository
+            .all(user_id, &query)
+            .await
+            .expect("fail fetch all todos")
+            .todos()
+            .iter()
+            .map(|todo| todo.id)
+            .collect::<Vec<_>>()
+    };
+
+    let a = create("a", Priority::P1, Some(3), Some(3)).await;
+    assert_eq!((a.priority, a.estimate), (Priority::P1, Some(3)));
+    let b = create("b", Priority::P0, None, None).await.id;
+    let c = create("c", Priority::P1, Some(2), Some(5)).await.id;
+    let d = create("d", Priority::P0, Some(5), Some(1)).await.id;
+    let a = a.id;
+    let default = repository
+        .create(
+            user_id,
+            CreateTodo::new("[todo::priorities] default".to_string(), vec![]),
+        )
+        .await
+        .expect("fail create todo");
+    assert_eq!((default.priority, default.estimate), (Priority::P2, None));
+    repository.delete(user_id, default.id).await.unwrap();
+
+    // todos without a due date or an estimate come last in both orders
+    let by_priority = vec![TodoSort::Priority, TodoSort::DueAt];
+    assert_eq!(sorted(by_priority.clone(), None).await, vec![d, b, c, a]);
+    assert_eq!(
+        sorted(by_priority, Some(SortOrder::Desc)).await,
+        vec![a, c, d, b]
+    );
+    assert_eq!(
+        sorted(vec![TodoSort::Estimate], None).await,
+        vec![d, a, c, b]
+    );
+    assert_eq!(
+        sorted(vec![TodoSort::Estimate], Some(SortOrder::Desc)).await,
+        vec![c, a, d, b]
+    );
+
+    let todo = repository
+        .update(
+            user_id,
+            a,
+            UpdateTodo {
+                text: None,
+                status: None,
+                labels: None,
+                due_at: None,
+                recurrence: None,
+                priority: Some(Priority::P3),
+                estimate: Some(None),
+            },
+        )
+        .await
+        .expect("fail update todo");
+    assert_eq!((todo.priority, todo.estimate), (Priority::P3, None));
+    assert_eq!(
+        sorted(vec![TodoSort::Priority], None).await,
+        vec![b, d, c, a]
+    );
+
+    for id in [a, b, c, d] {
+        repository.delete(user_id, id).await.unwrap();
+    }
+}
+
+async fn label_usage<R: TodoRepository, L: LabelRepository>(
+    repository: &R,
+    label_repository: &L,
+    user_id: i32,
+) {
+    let create_label = |name: &str| {
+        let payload = CreateLabel::new(format!("[todo::label_usage] {name}"));
+        async move {
+            label_repository
+                .create(user_id, payload)
+                .await
+                .expect("fail create label")
+        }
+    };
+    let create = |text: &str, labels: Vec<i32>| {
+        let payload = CreateTodo::new(format!("[todo::label_usage] {text}"), labels);
+        async move {
+            repository
+                .create(user_id, payload)
+                .await
+                .expect("fail create todo")
+                .id
+        }
+    };
+    let first = create_label("first").await;
+    let second = create_label("second").await;
+    let unused = create_label("unused").await;
+    let open = create("open", vec![first.id(), second.id()]).await;
+    let closed = create("closed", vec![first.id()]).await;
+    for status in [TodoStatus::InProgress, TodoStatus::Review, TodoStatus::Done] {
+        update_status(repository, user_id, closed, status)
+            .await
+            .expect("fail update todo");
+    }
+    let deleted = create("deleted", vec![second.id()]).await;
+    repository.delete(user_id, deleted).await.unwrap();
+
+    let usage = label_repository
+        .usage(user_id)
+        .await
+        .expect("fail get label usage");
+    assert_eq!(
+        usage,
+        vec![
+            LabelUsage::new(first.clone(), 1, 1),
+            LabelUsage::new(second.clone(), 1, 0),
+            LabelUsage::new(unused.clone(), 0, 0),
+        ]
+    );
+
+    // label in use is kept
+    let result = label_repository.delete(user_id, first.id(), false).await;
+    assert!(
+        matches!(result, Err(RepositoryError::LabelInUse { id, todos: 2 }) if id == first.id()),
+        "unexpected result: {result:?}"
+    );
+    assert_eq!(
+        label_repository.find(user_id, first.id()).await.unwrap(),
+        first
+    );
+    label_repository
+        .delete(user_id, unused.id(), false)
+        .await
+        .unwrap();
+
+    // forced deletion detaches the label
+    label_repository
+        .delete(user_id, first.id(), true)
+        .await
+        .unwrap();
+    let todo = repository.find(user_id, open).await.unwrap();
+    assert_eq!(todo.labels, vec![second.clone()]);
+    let usage = label_repository
+        .usage(user_id)
+        .await
+        .expect("fail get label usage");
+    assert_eq!(usage, vec![LabelUsage::new(second.clone(), 1, 0)]);
+
+    repository.delete(user_id, open).await.unwrap();
+    repository.delete(user_id, closed).await.unwrap();
+    label_repository
+        .delete(user_id, second.id(), false)
+        .await
+        .unwrap();
+}
+
+async fn label_merge<R: TodoRepository, L: LabelRepository>(
+    repository: &R,
+    label_repository: &L,
+    user_id: i32,
+) {
+    let create_label = |name: &str| {
+        let payload = CreateLabel::new(format!("[todo::label_merge] {name}"));
+        async move {
+            label_repository
+                .create(user_id, payload)
+                .await
+                .expect("fail create label")
+        }
+    };
+    let create = |text: &str, labels: Vec<i32>| {
+        let payload = CreateTodo::new(format!("[todo::label_merge] {text}"), labels);
+        async move {
+            repository
+                .create(user_id, payload)
+                .await
+                .expect("fail create todo")
+                .id
+        }
+    };
+    let labels = |id: u32| async move {
+        repository
+            .find(user_id, id)
+            .await
+            .expect("fail find todo")
+            .labels
+    };
+    let target = create_label("bug").await;
+    let source = create_label("bugs").await;
+    let other = create_label("feature").await;
+    let both = create("both", vec![target.id(), source.id()]).await;
+    let source_only = create("source only", vec![source.id(), other.id()]).await;
+    let target_only = create("target only", vec![target.id()]).await;
+
+    let merged = label_repository
+        .merge(user_id, target.id(), MergeLabel::new(source.id()))
+        .await
+        .expect("fail merge labels");
+    assert_eq!(merged, target);
+    assert_eq!(labels(both).await, vec![target.clone()]);
+    assert_eq!(
+        labels(source_only).await,
+        vec![target.clone(), other.clone()]
+    );
+    assert_eq!(labels(target_only).await, vec![target.clone()]);
+    let result = label_repository.find(user_id, source.id()).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == source.id() as u32),
+        "unexpected result: {result:?}"
+    );
+    let usage = label_repository
+        .usage(user_id)
+        .await
+        .expect("fail get label usage");
+    assert_eq!(
+        usage,
+        vec![
+            LabelUsage::new(target.clone(), 3, 0),
+            LabelUsage::new(other.clone(), 1, 0),
+        ]
+    );
+
+    for id in [both, source_only, target_only] {
+        repository.delete(user_id, id).await.unwrap();
+    }
+    label_repository
+        .delete(user_id, target.id(), false)
+        .await
+        .unwrap();
+    label_repository
+        .delete(user_id, other.id(), false)
+        .await
+        .unwrap();
+}
+
+/// ラベルで絞り込むと子孫のラベルが付いたTodoも含まれる
+async fn label_hierarchy<R: TodoRepository, L: LabelRepository>(
+    repository: &R,
+    label_repository: &L,
+    user_id: i32,
+) {
+    let create_label = |name: &str, parent_id: Option<i32>| {
+        let payload = CreateLabel::new(format!("[todo::label_hierarchy] {name}"));
+        let payload = match parent_id {
+            Some(parent_id) => payload.with_parent(parent_id),
+            None => payload,
+        };
+        async move {
+            label_repository
+                .create(user_id, payload)
+                .await
+                .expect("fail create label")
+        }
+    };
+    let create = |text: &str, labels: Vec<i32>| {
+        let payload = CreateTodo::new(format!("[todo::label_hierarchy] {text}"), labels);
+        async move {
+            repository
+                .create(user_id, payload)
+                .await
+                .expect("fail create todo")
+                .id
+        }
+    };
+    let search = |labels: Vec<i32>| async move {
+        let query = TodoQuery {
+            labels,
+            ..Default::default()
+        };
+        let page = repository
+            .all(user_id, &query)
+            .await
+            .expect("fail fetch all todos");
+        page.todos()
+            .iter()
+            .map(|todo| todo.id)
+            .collect::<Vec<u32>>()
+    };
+    let area = create_label("area", None).await;
+    let backend = create_label("area/backend", Some(area.id())).await;
+    let db = create_label("area/backend/db", Some(backend.id())).await;
+    let misc = create_label("misc", None).await;
+    let schema = create("schema", vec![db.id()]).await;
+    let api = create("api", vec![backend.id(), misc.id()]).await;
+    let roadmap = create("roadmap", vec![area.id()]).await;
+    let chores = create("chores", vec![misc.id()]).await;
+
+    assert_eq!(search(vec![area.id()]).await, vec![roadmap, api, schema]);
+    assert_eq!(search(vec![backend.id()]).await, vec![api, schema]);
+    assert_eq!(search(vec![db.id()]).await, vec![schema]);
+    assert_eq!(search(vec![misc.id()]).await, vec![chores, api]);
+    assert_eq!(search(vec![area.id(), misc.id()]).await, vec![api]);
+    // 同じ部分木のラベルを重ねて指定してもよい
+    assert_eq!(search(vec![area.id(), db.id()]).await, vec![schema]);
+
+    for id in [schema, api, roadmap, chores] {
+        repository.delete(user_id, id).await.unwrap();
+    }
+    for label in [db, backend, area, misc] {
+        label_repository
+            .delete(user_id, label.id(), false)
+            .await
+            .unwrap();
+    }
+}
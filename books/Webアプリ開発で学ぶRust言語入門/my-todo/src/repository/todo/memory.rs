@@ -1,128 +1,670 @@
 use std::{
+    cmp::Ordering,
     collections::HashMap,
     sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
 };
 
-use crate::repository::RepositoryError;
+use chrono::{DateTime, Utc};
 
-use super::{CreateTodo, Todo, TodoRepository, UpdateTodo};
+use crate::repository::{
+    label::{Label, LabelRepositoryForMemory},
+    reminder::Reminder,
+    RepositoryError,
+};
+
+use super::{
+    check_unknown_labels, invalid_position, normalize_label_ids, position::key_between,
+    push_escaped, words, Board, CreateDependency, CreateTodo, MoveTodo, Priority, Recurrence,
+    SortOrder, SubtaskCompletion, Todo, TodoPage, TodoQuery, TodoRepository, TodoSearch,
+    TodoSearchHit, TodoSort, TodoStatus, UpdateTodo, HIGHLIGHT_END, HIGHLIGHT_START,
+};
 
-type TodoData = HashMap<u32, Todo>;
+#[derive(Debug, Clone)]
+struct TodoRecord {
+    id: u32,
+    user_id: i32,
+    text: String,
+    status: TodoStatus,
+    due_at: Option<DateTime<Utc>>,
+    position: String,
+    parent_id: Option<u32>,
+    blocked_by: Vec<u32>,
+    recurrence: Option<Recurrence>,
+    next_occurrence_id: Option<u32>,
+    priority: Priority,
+    estimate: Option<u32>,
+    comment_count: u32,
+}
+
+#[derive(Debug, Default)]
+struct TodoData {
+    todos: HashMap<u32, TodoRecord>,
+    last_id: u32,
+}
 
 #[derive(Debug, Clone, Default)]
 pub struct TodoRepositoryForMemory {
     store: Arc<RwLock<TodoData>>,
+    labels: LabelRepositoryForMemory,
+    subtask_completion: SubtaskCompletion,
 }
 
 impl TodoRepositoryForMemory {
-    pub fn new() -> Self {
-        Self::default()
+    pub fn new(labels: LabelRepositoryForMemory) -> Self {
+        Self {
+            store: Default::default(),
+            labels,
+            subtask_completion: SubtaskCompletion::default(),
+        }
     }
 
-    fn write_store_ref(&self) -> RwLockWriteGuard<TodoData> {
+    pub fn with_subtask_completion(self, subtask_completion: SubtaskCompletion) -> Self {
+        Self {
+            subtask_completion,
+            ..self
+        }
+    }
+
+    fn write_store_ref(&self) -> RwLockWriteGuard<'_, TodoData> {
         self.store.write().unwrap()
     }
 
-    fn read_store_ref(&self) -> RwLockReadGuard<TodoData> {
+    fn read_store_ref(&self) -> RwLockReadGuard<'_, TodoData> {
         self.store.read().unwrap()
     }
+
+    /// ラベルのリポジトリにも完了したかを知らせて保存する
+    fn save(&self, store: &mut TodoData, record: TodoRecord) {
+        self.labels
+            .set_done(record.id, record.status == TodoStatus::Done);
+        store.todos.insert(record.id, record);
+    }
+
+    /// 削除済みのラベルは除外してTodoに変換する
+    fn to_todo(&self, store: &TodoData, record: &TodoRecord) -> Todo {
+        Todo {
+            id: record.id,
+            text: record.text.clone(),
+            status: record.status,
+            labels: self.labels.todo_labels(record.user_id, record.id),
+            due_at: record.due_at,
+            parent_id: record.parent_id,
+            blocked: record.blocked_by.iter().any(|id| {
+                store
+                    .todos
+                    .get(id)
+                    .is_some_and(|blocker| blocker.status != TodoStatus::Done)
+            }),
+            recurrence: record.recurrence.clone(),
+            next_occurrence_id: record.next_occurrence_id,
+            priority: record.priority,
+            estimate: record.estimate,
+            comment_count: record.comment_count,
+        }
+    }
+
+    /// `user_id`のユーザーが所有するTodoでなければNotFoundにする
+    pub(crate) fn check_owner(&self, user_id: i32, id: u32) -> Result<(), RepositoryError> {
+        self.read_store_ref()
+            .todos
+            .get(&id)
+            .filter(|record| record.user_id == user_id)
+            .map(|_| ())
+            .ok_or(RepositoryError::NotFound(id))
+    }
+
+    /// コメントの投稿や削除に合わせてコメント数を更新する
+    pub(crate) fn set_comment_count(&self, id: u32, count: u32) {
+        if let Some(record) = self.write_store_ref().todos.get_mut(&id) {
+            record.comment_count = count;
+        }
+    }
+
+    /// 全てのユーザーの未完了のTodoのうち、`now`までに期限を迎えたものを期限の古い順に返す
+    pub(crate) fn due(&self, now: DateTime<Utc>) -> Vec<Reminder> {
+        let store = self.read_store_ref();
+        let mut reminders: Vec<Reminder> = store
+            .todos
+            .values()
+            .filter(|record| record.status != TodoStatus::Done)
+            .filter_map(|record| {
+                let due_at = record.due_at.filter(|due_at| *due_at <= now)?;
+                Some(Reminder::new(
+                    record.id as i32,
+                    record.user_id,
+                    record.text.clone(),
+                    due_at,
+                ))
+            })
+            .collect();
+        reminders.sort_by_key(|reminder| (reminder.due_at(), reminder.todo_id()));
+        reminders
+    }
+
+    /// `status`の列に並ぶ`user_id`のユーザーのTodoを並び順に返す
+    fn column(
+        store: &TodoData,
+        user_id: i32,
+        status: TodoStatus,
+    ) -> impl Iterator<Item = &TodoRecord> {
+        let mut records: Vec<&TodoRecord> = store
+            .todos
+            .values()
+            .filter(|record| record.user_id == user_id && record.status == status)
+            .collect();
+        records.sort_by(|a, b| a.position.cmp(&b.position).then(a.id.cmp(&b.id)));
+        records.into_iter()
+    }
+
+    /// `id`のTodoの子孫を全て返す
+    fn descendants(store: &TodoData, id: u32) -> Vec<u32> {
+        let mut descendants = Vec::new();
+        let mut parents = vec![id];
+        while let Some(parent) = parents.pop() {
+            let children = store
+                .todos
+                .values()
+                .filter(|record| record.parent_id == Some(parent))
+                .map(|record| record.id);
+            for child in children {
+                descendants.push(child);
+                parents.push(child);
+            }
+        }
+        descendants
+    }
+
+    fn has_open_subtasks(store: &TodoData, id: u32) -> bool {
+        Self::descendants(store, id)
+            .iter()
+            .any(|child| store.todos[child].status != TodoStatus::Done)
+    }
+
+    /// 未着手の列の末尾に並べるキー
+    fn last_position(store: &TodoData, user_id: i32) -> Result<String, RepositoryError> {
+        let last = Self::column(store, user_id, TodoStatus::default())
+            .last()
+            .map(|record| record.position.clone());
+        key_between(last.as_deref(), None).ok_or_else(invalid_position)
+    }
+
+    /// 完了にした`record`のTodoを保存する前に呼び出し、完了にできるか確かめる。
+    /// 繰り返すTodoであれば次のTodoを作って規則を引き継ぎ、必要であれば親のTodoも完了にする
+    fn complete(
+        &self,
+        store: &mut TodoData,
+        record: &mut TodoRecord,
+    ) -> Result<(), RepositoryError> {
+        let id = record.id;
+        if Self::has_open_subtasks(store, id) {
+            return Err(RepositoryError::OpenSubtasks(id));
+        }
+        // 既に次のTodoを作っていれば、完了にし直しても作らない
+        let next = record
+            .recurrence
+            .as_ref()
+            .filter(|_| record.next_occurrence_id.is_none())
+            .and_then(|recurrence| recurrence.next(record.due_at.unwrap_or_else(Utc::now)));
+        if let Some((due_at, recurrence)) = next {
+            let position = Self::last_position(store, record.user_id)?;
+            store.last_id += 1;
+            let next = TodoRecord {
+                id: store.last_id,
+                status: TodoStatus::default(),
+                due_at: Some(due_at),
+                position,
+                blocked_by: Vec::new(),
+                recurrence: Some(recurrence),
+                next_occurrence_id: None,
+                comment_count: 0,
+                ..record.clone()
+            };
+            record.next_occurrence_id = Some(next.id);
+            let labels = self.labels.todo_labels(record.user_id, id);
+            self.labels
+                .set_labels(next.id, labels.iter().map(Label::id).collect());
+            self.save(store, next);
+        }
+        if self.subtask_completion == SubtaskCompletion::AutoComplete {
+            let mut parent_id = store.todos[&id].parent_id;
+            while let Some(parent) = parent_id {
+                let open = Self::descendants(store, parent)
+                    .iter()
+                    .any(|child| *child != id && store.todos[child].status != TodoStatus::Done);
+                let record = store.todos.get_mut(&parent).unwrap();
+                if open || record.status == TodoStatus::Done {
+                    break;
+                }
+                record.status = TodoStatus::Done;
+                self.labels.set_done(record.id, true);
+                parent_id = record.parent_id;
+            }
+        }
+        Ok(())
+    }
+
+    /// `id`のTodoが`blocker`のTodoに (間接的にでも) ブロックされているか。自身もブロックしているものとみなす
+    fn is_blocked_by(store: &TodoData, id: u32, blocker: u32) -> bool {
+        let mut visited = Vec::new();
+        let mut ids = vec![id];
+        while let Some(id) = ids.pop() {
+            if id == blocker {
+                return true;
+            }
+            if visited.contains(&id) {
+                continue;
+            }
+            visited.push(id);
+            ids.extend(
+                store
+                    .todos
+                    .get(&id)
+                    .into_iter()
+                    .flat_map(|record| &record.blocked_by),
+            );
+        }
+        false
+    }
+
+    /// 他のユーザーのラベルは存在しないラベルと同様に扱う
+    fn validate_labels(&self, user_id: i32, ids: Vec<i32>) -> Result<Vec<i32>, RepositoryError> {
+        let ids = normalize_label_ids(ids);
+        let found: Vec<i32> = self
+            .labels
+            .select(user_id, &ids)
+            .iter()
+            .map(|label| label.id())
+            .collect();
+        check_unknown_labels(&ids, &found)?;
+        Ok(ids)
+    }
 }
 
 #[axum::async_trait]
 impl TodoRepository for TodoRepositoryForMemory {
-    async fn all(&self) -> Result<Vec<Todo>, RepositoryError> {
+    async fn all(&self, user_id: i32, query: &TodoQuery) -> Result<TodoPage, RepositoryError> {
         let store = self.read_store_ref();
-        Ok(store.values().cloned().collect())
+        let text = query.text.as_ref().map(|text| text.to_lowercase());
+        let trees: Vec<Vec<i32>> = normalize_label_ids(query.labels.clone())
+            .into_iter()
+            .map(|id| self.labels.subtree_ids(id))
+            .collect();
+        let now = Utc::now();
+        let mut todos: Vec<Todo> = store
+            .todos
+            .values()
+            .filter(|record| record.user_id == user_id)
+            .map(|record| self.to_todo(&store, record))
+            .filter(|todo| query.status.is_none_or(|status| todo.status == status))
+            .filter(|todo| {
+                text.as_ref()
+                    .is_none_or(|text| todo.text.to_lowercase().contains(text))
+            })
+            .filter(|todo| {
+                trees
+                    .iter()
+                    .all(|tree| todo.labels.iter().any(|label| tree.contains(&label.id())))
+            })
+            .filter(|todo| {
+                query
+                    .due_before
+                    .is_none_or(|due_before| todo.due_at.is_some_and(|due_at| due_at < due_before))
+            })
+            .filter(|todo| {
+                query
+                    .overdue
+                    .is_none_or(|overdue| todo.is_overdue(now) == overdue)
+            })
+            .collect();
+
+        let order = query.order();
+        todos.sort_by(|a, b| {
+            query
+                .sorts()
+                .iter()
+                .chain([&TodoSort::Id])
+                .fold(Ordering::Equal, |ordering, sort| {
+                    ordering.then_with(|| compare(*sort, order, a, b))
+                })
+        });
+
+        let todos = todos
+            .into_iter()
+            .skip(query.offset as usize)
+            .take(query.fetch_limit() as usize)
+            .collect();
+        Ok(TodoPage::new(todos, query))
     }
 
-    async fn find(&self, id: u32) -> Result<Todo, RepositoryError> {
+    /// 単語の一致だけで判定する簡易的な全文検索。関連度は本文の単語のうち検索語が占める割合
+    async fn search(
+        &self,
+        user_id: i32,
+        search: &TodoSearch,
+    ) -> Result<Vec<TodoSearchHit>, RepositoryError> {
+        let terms = search.terms();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
         let store = self.read_store_ref();
-        let todo = store
+        let mut hits: Vec<TodoSearchHit> = store
+            .todos
+            .values()
+            .filter(|record| record.user_id == user_id)
+            .filter_map(|record| {
+                let words: Vec<String> = words(&record.text).map(str::to_lowercase).collect();
+                if !terms.iter().all(|term| words.contains(term)) {
+                    return None;
+                }
+                let matched = words.iter().filter(|word| terms.contains(word)).count();
+                Some(TodoSearchHit {
+                    todo: self.to_todo(&store, record),
+                    rank: matched as f32 / words.len() as f32,
+                    snippet: highlight(&record.text, &terms),
+                })
+            })
+            .collect();
+
+        hits.sort_by(|a, b| b.rank.total_cmp(&a.rank).then(b.todo.id.cmp(&a.todo.id)));
+        hits.truncate(search.limit() as usize);
+        Ok(hits)
+    }
+
+    async fn find(&self, user_id: i32, id: u32) -> Result<Todo, RepositoryError> {
+        let store = self.read_store_ref();
+        let record = store
+            .todos
             .get(&id)
-            .cloned()
+            .filter(|record| record.user_id == user_id)
             .ok_or(RepositoryError::NotFound(id))?;
-        Ok(todo)
+        Ok(self.to_todo(&store, record))
     }
 
-    async fn create(&self, payload: CreateTodo) -> Result<Todo, RepositoryError> {
+    async fn create(&self, user_id: i32, payload: CreateTodo) -> Result<Todo, RepositoryError> {
+        let labels = self.validate_labels(user_id, payload.labels)?;
+
         let mut store = self.write_store_ref();
-        let id = (store.len() + 1) as u32;
-        let todo = Todo::new(id, payload.text);
-        store.insert(id, todo.clone());
-        Ok(todo)
+        if let Some(parent_id) = payload.parent_id {
+            store
+                .todos
+                .get(&parent_id)
+                .filter(|record| record.user_id == user_id)
+                .ok_or(RepositoryError::NotFound(parent_id))?;
+        }
+        let position = Self::last_position(&store, user_id)?;
+        store.last_id += 1;
+        let id = store.last_id;
+        let record = TodoRecord {
+            id,
+            user_id,
+            text: payload.text,
+            status: TodoStatus::default(),
+            due_at: payload.due_at,
+            position,
+            parent_id: payload.parent_id,
+            blocked_by: Vec::new(),
+            recurrence: payload.recurrence,
+            next_occurrence_id: None,
+            priority: payload.priority,
+            estimate: payload.estimate,
+            comment_count: 0,
+        };
+        self.save(&mut store, record.clone());
+        self.labels.set_labels(id, labels);
+        Ok(self.to_todo(&store, &record))
     }
 
-    async fn update(&self, id: u32, payload: UpdateTodo) -> Result<Todo, RepositoryError> {
+    async fn update(
+        &self,
+        user_id: i32,
+        id: u32,
+        payload: UpdateTodo,
+    ) -> Result<Todo, RepositoryError> {
         let mut store = self.write_store_ref();
-        let todo = store.get(&id).ok_or(RepositoryError::NotFound(id))?;
+        let todo = store
+            .todos
+            .get(&id)
+            .filter(|record| record.user_id == user_id)
+            .ok_or(RepositoryError::NotFound(id))?;
+        let status = payload
+            .status
+            .map(|status| todo.status.transition(id, status))
+            .transpose()?;
+        let labels = payload
+            .labels
+            .map(|labels| self.validate_labels(user_id, labels))
+            .transpose()?;
 
-        let text = payload.text.unwrap_or_else(|| todo.text.clone());
-        let completed = payload.completed.unwrap_or(todo.completed);
-        let todo = Todo {
-            id,
-            text,
-            completed,
+        let mut record = TodoRecord {
+            text: payload.text.unwrap_or_else(|| todo.text.clone()),
+            status: status.unwrap_or(todo.status),
+            due_at: payload.due_at.unwrap_or(todo.due_at),
+            recurrence: payload
+                .recurrence
+                .unwrap_or_else(|| todo.recurrence.clone()),
+            priority: payload.priority.unwrap_or(todo.priority),
+            estimate: payload.estimate.unwrap_or(todo.estimate),
+            ..todo.clone()
         };
-        store.insert(id, todo.clone());
-        Ok(todo)
+        // 繰り返すTodoの次のTodoにも変更後のラベルを引き継ぐ
+        let previous = labels.map(|labels| self.labels.set_labels(id, labels));
+        if status == Some(TodoStatus::Done) {
+            if let Err(error) = self.complete(&mut store, &mut record) {
+                if let Some(previous) = previous {
+                    self.labels.set_labels(id, previous);
+                }
+                return Err(error);
+            }
+        }
+        self.save(&mut store, record.clone());
+        Ok(self.to_todo(&store, &record))
     }
 
-    async fn delete(&self, id: u32) -> Result<(), RepositoryError> {
+    async fn delete(&self, user_id: i32, id: u32) -> Result<(), RepositoryError> {
         let mut store = self.write_store_ref();
-        store.remove(&id).ok_or(RepositoryError::NotFound(id))?;
+        store
+            .todos
+            .get(&id)
+            .filter(|record| record.user_id == user_id)
+            .ok_or(RepositoryError::NotFound(id))?;
+        let mut ids = Self::descendants(&store, id);
+        ids.push(id);
+        for id in &ids {
+            store.todos.remove(id);
+        }
+        self.labels.untrack(&ids);
+        for record in store.todos.values_mut() {
+            record.blocked_by.retain(|blocker| !ids.contains(blocker));
+            if record
+                .next_occurrence_id
+                .is_some_and(|next| ids.contains(&next))
+            {
+                record.next_occurrence_id = None;
+            }
+        }
         Ok(())
     }
+
+    async fn children(&self, user_id: i32, id: u32) -> Result<Vec<Todo>, RepositoryError> {
+        let store = self.read_store_ref();
+        store
+            .todos
+            .get(&id)
+            .filter(|record| record.user_id == user_id)
+            .ok_or(RepositoryError::NotFound(id))?;
+        let mut children: Vec<Todo> = store
+            .todos
+            .values()
+            .filter(|record| record.parent_id == Some(id))
+            .map(|record| self.to_todo(&store, record))
+            .collect();
+        children.sort_by_key(|todo| todo.id);
+        Ok(children)
+    }
+
+    async fn board(&self, user_id: i32) -> Result<Board, RepositoryError> {
+        let store = self.read_store_ref();
+        let todos = TodoStatus::ALL
+            .into_iter()
+            .flat_map(|status| Self::column(&store, user_id, status))
+            .map(|record| self.to_todo(&store, record))
+            .collect();
+        Ok(Board::new(todos))
+    }
+
+    async fn move_todo(
+        &self,
+        user_id: i32,
+        id: u32,
+        payload: MoveTodo,
+    ) -> Result<Todo, RepositoryError> {
+        let mut store = self.write_store_ref();
+        let todo = store
+            .todos
+            .get(&id)
+            .filter(|record| record.user_id == user_id)
+            .ok_or(RepositoryError::NotFound(id))?;
+        let status = match payload.status {
+            Some(status) => todo.status.transition(id, status)?,
+            None => todo.status,
+        };
+
+        let column: Vec<&TodoRecord> = Self::column(&store, user_id, status)
+            .filter(|record| record.id != id)
+            .collect();
+        let lower = match payload.after {
+            Some(after) => Some(
+                column
+                    .iter()
+                    .find(|record| record.id == after)
+                    .map(|record| record.position.as_str())
+                    .ok_or(RepositoryError::NotFound(after))?,
+            ),
+            None => None,
+        };
+        let upper = column
+            .iter()
+            .map(|record| record.position.as_str())
+            .find(|position| lower.is_none_or(|lower| *position > lower));
+        let position = key_between(lower, upper).ok_or_else(invalid_position)?;
+
+        let mut record = TodoRecord {
+            status,
+            position,
+            ..todo.clone()
+        };
+        if payload.status == Some(TodoStatus::Done) {
+            self.complete(&mut store, &mut record)?;
+        }
+        self.save(&mut store, record.clone());
+        Ok(self.to_todo(&store, &record))
+    }
+
+    async fn add_dependency(
+        &self,
+        user_id: i32,
+        id: u32,
+        payload: CreateDependency,
+    ) -> Result<Todo, RepositoryError> {
+        let blocked_by = payload.blocked_by;
+        let mut store = self.write_store_ref();
+        for todo_id in [id, blocked_by] {
+            store
+                .todos
+                .get(&todo_id)
+                .filter(|record| record.user_id == user_id)
+                .ok_or(RepositoryError::NotFound(todo_id))?;
+        }
+        if Self::is_blocked_by(&store, blocked_by, id) {
+            return Err(RepositoryError::DependencyCycle { id, blocked_by });
+        }
+
+        let record = store.todos.get_mut(&id).unwrap();
+        if !record.blocked_by.contains(&blocked_by) {
+            record.blocked_by.push(blocked_by);
+        }
+        Ok(self.to_todo(&store, &store.todos[&id]))
+    }
+}
+
+/// `sort`の項目を`order`の順に比べる
+fn compare(sort: TodoSort, order: SortOrder, a: &Todo, b: &Todo) -> Ordering {
+    match sort {
+        TodoSort::Id => directed(a.id.cmp(&b.id), order),
+        TodoSort::Text => directed(a.text.cmp(&b.text), order),
+        TodoSort::Priority => directed(a.priority.cmp(&b.priority), order),
+        TodoSort::DueAt => nulls_last(a.due_at, b.due_at, order),
+        TodoSort::Estimate => nulls_last(a.estimate, b.estimate, order),
+    }
+}
+
+fn directed(ordering: Ordering, order: SortOrder) -> Ordering {
+    match order {
+        SortOrder::Asc => ordering,
+        SortOrder::Desc => ordering.reverse(),
+    }
+}
+
+/// 値のないものは並び順に関わらず後ろにする
+fn nulls_last<T: Ord>(a: Option<T>, b: Option<T>, order: SortOrder) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => directed(a.cmp(&b), order),
+        (a, b) => a.is_none().cmp(&b.is_none()),
+    }
+}
+
+/// 検索語に一致した単語を強調し、それ以外の文字はエスケープする
+fn highlight(text: &str, terms: &[String]) -> String {
+    let mut snippet = String::with_capacity(text.len());
+    let mut word = String::new();
+    let flush = |word: &mut String, snippet: &mut String| {
+        if terms.contains(&word.to_lowercase()) {
+            snippet.push_str(HIGHLIGHT_START);
+            snippet.push_str(word);
+            snippet.push_str(HIGHLIGHT_END);
+        } else {
+            snippet.push_str(word);
+        }
+        word.clear();
+    };
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            word.push(c);
+        } else {
+            flush(&mut word, &mut snippet);
+            push_escaped(&mut snippet, c);
+        }
+    }
+    flush(&mut word, &mut snippet);
+    snippet
 }
 
 #[cfg(test)]
-pub(crate) mod tests {
+mod tests {
+    use crate::repository::{todo::conformance, user::UserRepositoryForMemory};
+
     use super::*;
 
     #[tokio::test]
-    async fn todo_crud_scenario() {
-        let id = 1;
-        let text = "todo text".to_string();
-        let expected = Todo::new(id, text.clone());
-
-        // 1. create
-        let repository = TodoRepositoryForMemory::new();
-        let todo = repository
-            .create(CreateTodo { text })
-            .await
-            .expect("failed create todo.");
-        assert_eq!(expected, todo);
-
-        // 2. find
-        let todo = repository.find(id).await.expect("failed find todo.");
-        assert_eq!(expected, todo);
-
-        // 3. all
-        let todo = repository.all().await.unwrap();
-        assert_eq!(vec![expected], todo);
-
-        // 4. update
-        let text = "update todo text".to_string();
-        let todo = repository
-            .update(
-                id,
-                UpdateTodo {
-                    text: Some(text.clone()),
-                    completed: Some(true),
-                },
-            )
-            .await
-            .expect("failed update todo.");
-        assert_eq!(
-            Todo {
-                id,
-                text,
-                completed: true
-            },
-            todo
-        );
-
-        // 5. delete
-        let result = repository.delete(id).await;
-        assert!(result.is_ok(), "failed delete todo: {result:?}")
+    async fn conformance() {
+        let label_repository = LabelRepositoryForMemory::new();
+
+        conformance::run(
+            TodoRepositoryForMemory::new(label_repository.clone()),
+            label_repository,
+            UserRepositoryForMemory::new(),
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn conformance_auto_complete() {
+        conformance::run_auto_complete(
+            TodoRepositoryForMemory::new(LabelRepositoryForMemory::new())
+                .with_subtask_completion(SubtaskCompletion::AutoComplete),
+            UserRepositoryForMemory::new(),
+        )
+        .await;
     }
 }
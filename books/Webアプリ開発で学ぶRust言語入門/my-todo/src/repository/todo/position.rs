@@ -0,0 +1,246 @@
+//! ボード上の並び順を表す文字列のキー (fractional indexing)
+//!
+//! キーはバイト列として比較し、任意の2つのキーの間に新しいキーを作れるため、
+//! 並べ替えるときに他のTodoのキーを振り直す必要がない。
+//! キーは長さを表す先頭の文字と整数部、末尾が`0`でない小数部からなる。
+
+/// ASCIIの順に並んだ62進数の数字
+const DIGITS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const INTEGER_ZERO: &[u8] = b"a0";
+const SMALLEST_INTEGER: &[u8] = b"A00000000000000000000000000";
+
+/// `before`と`after`の間に並ぶキーを返す。`None`はそれぞれ先頭と末尾を表す。
+/// キーが不正な場合や`before`が`after`より前でない場合は`None`を返す
+pub(super) fn key_between(before: Option<&str>, after: Option<&str>) -> Option<String> {
+    let a = before.map(str::as_bytes);
+    let b = after.map(str::as_bytes);
+    for key in a.iter().chain(b.iter()) {
+        validate(key)?;
+    }
+
+    let key = match (a, b) {
+        (None, None) => INTEGER_ZERO.to_vec(),
+        (None, Some(b)) => {
+            let ib = integer_part(b)?;
+            let fb = &b[ib.len()..];
+            if ib == SMALLEST_INTEGER {
+                [ib, &midpoint(b"", Some(fb))?].concat()
+            } else if ib.len() < b.len() {
+                ib.to_vec()
+            } else {
+                decrement_integer(ib)?
+            }
+        }
+        (Some(a), None) => {
+            let ia = integer_part(a)?;
+            let fa = &a[ia.len()..];
+            match increment_integer(ia) {
+                Some(i) => i,
+                None => [ia, &midpoint(fa, None)?].concat(),
+            }
+        }
+        (Some(a), Some(b)) => {
+            if a >= b {
+                return None;
+            }
+            let ia = integer_part(a)?;
+            let fa = &a[ia.len()..];
+            let ib = integer_part(b)?;
+            let fb = &b[ib.len()..];
+            if ia == ib {
+                [ia, &midpoint(fa, Some(fb))?].concat()
+            } else {
+                match increment_integer(ia) {
+                    Some(i) if i.as_slice() < b => i,
+                    _ => [ia, &midpoint(fa, None)?].concat(),
+                }
+            }
+        }
+    };
+    String::from_utf8(key).ok()
+}
+
+fn digit(c: u8) -> Option<usize> {
+    DIGITS.iter().position(|d| *d == c)
+}
+
+fn validate(key: &[u8]) -> Option<()> {
+    let integer = integer_part(key)?;
+    if !integer.iter().skip(1).all(|c| digit(*c).is_some()) {
+        return None;
+    }
+    let fraction = &key[integer.len()..];
+    if fraction.last() == Some(&b'0') || !fraction.iter().all(|c| digit(*c).is_some()) {
+        return None;
+    }
+    Some(())
+}
+
+/// 先頭の文字で整数部の長さが決まる (`a`は2文字、`b`は3文字…、`Z`は2文字、`Y`は3文字…)
+fn integer_part(key: &[u8]) -> Option<&[u8]> {
+    let length = match *key.first()? {
+        head @ b'a'..=b'z' => (head - b'a') as usize + 2,
+        head @ b'A'..=b'Z' => (b'Z' - head) as usize + 2,
+        _ => return None,
+    };
+    key.get(..length)
+}
+
+/// `a`と`b`の間の小数部。`b`が`None`の場合は1として扱う
+fn midpoint(a: &[u8], b: Option<&[u8]>) -> Option<Vec<u8>> {
+    if let Some(b) = b {
+        let common = b
+            .iter()
+            .enumerate()
+            .take_while(|(i, c)| a.get(*i).copied().unwrap_or(b'0') == **c)
+            .count();
+        if common > 0 {
+            let rest = midpoint(a.get(common..).unwrap_or_default(), Some(&b[common..]))?;
+            return Some([&b[..common], &rest].concat());
+        }
+    }
+
+    let digit_a = match a.first() {
+        Some(c) => digit(*c)?,
+        None => 0,
+    };
+    let digit_b = match b {
+        Some(b) => digit(*b.first()?)?,
+        None => DIGITS.len(),
+    };
+    if digit_b <= digit_a {
+        None
+    } else if digit_b - digit_a > 1 {
+        Some(vec![DIGITS[(digit_a + digit_b).div_ceil(2)]])
+    } else if let Some(b) = b.filter(|b| b.len() > 1) {
+        Some(b[..1].to_vec())
+    } else {
+        let rest = midpoint(a.get(1..).unwrap_or_default(), None)?;
+        Some([&[DIGITS[digit_a]], rest.as_slice()].concat())
+    }
+}
+
+/// 整数部に1を足す。最大値を超える場合は`None`
+fn increment_integer(integer: &[u8]) -> Option<Vec<u8>> {
+    let head = integer[0];
+    let mut digits = integer[1..].to_vec();
+    let mut carry = true;
+    for d in digits.iter_mut().rev() {
+        let next = digit(*d)? + 1;
+        if next == DIGITS.len() {
+            *d = b'0';
+        } else {
+            *d = DIGITS[next];
+            carry = false;
+            break;
+        }
+    }
+    if !carry {
+        return Some([&[head], digits.as_slice()].concat());
+    }
+    match head {
+        b'Z' => Some(INTEGER_ZERO.to_vec()),
+        b'z' => None,
+        _ => {
+            let head = head + 1;
+            if head > b'a' {
+                digits.push(b'0');
+            } else {
+                digits.pop();
+            }
+            Some([&[head], digits.as_slice()].concat())
+        }
+    }
+}
+
+/// 整数部から1を引く。最小値を下回る場合は`None`
+fn decrement_integer(integer: &[u8]) -> Option<Vec<u8>> {
+    let head = integer[0];
+    let mut digits = integer[1..].to_vec();
+    let mut borrow = true;
+    for d in digits.iter_mut().rev() {
+        match digit(*d)? {
+            0 => *d = DIGITS[DIGITS.len() - 1],
+            i => {
+                *d = DIGITS[i - 1];
+                borrow = false;
+                break;
+            }
+        }
+    }
+    if !borrow {
+        return Some([&[head], digits.as_slice()].concat());
+    }
+    match head {
+        b'a' => Some(b"Zz".to_vec()),
+        b'A' => None,
+        _ => {
+            let head = head - 1;
+            if head < b'Z' {
+                digits.push(DIGITS[DIGITS.len() - 1]);
+            } else {
+                digits.pop();
+            }
+            Some([&[head], digits.as_slice()].concat())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn between(a: Option<&str>, b: Option<&str>) -> String {
+        key_between(a, b).unwrap_or_else(|| panic!("no key between {a:?} and {b:?}"))
+    }
+
+    #[test]
+    fn should_generate_keys() {
+        assert_eq!(between(None, None), "a0");
+        assert_eq!(between(Some("a0"), None), "a1");
+        assert_eq!(between(Some("az"), None), "b00");
+        assert_eq!(between(None, Some("a0")), "Zz");
+        assert_eq!(between(None, Some("b00")), "az");
+        assert_eq!(between(Some("a0"), Some("a1")), "a0V");
+        assert_eq!(between(Some("a0"), Some("a0V")), "a0G");
+        assert_eq!(between(Some("a0V"), Some("a1")), "a0l");
+        assert_eq!(between(Some("Zz"), Some("a0")), "ZzV");
+        assert_eq!(between(None, Some("a0V")), "a0");
+    }
+
+    #[test]
+    fn should_keep_keys_ordered() {
+        // appending
+        let mut keys = vec![between(None, None)];
+        for _ in 0..1000 {
+            keys.push(between(keys.last().map(String::as_str), None));
+        }
+        assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(keys.iter().all(|key| key.len() <= 3), "{keys:?}");
+
+        // prepending
+        let mut keys = vec![between(None, None)];
+        for _ in 0..1000 {
+            keys.insert(0, between(None, keys.first().map(String::as_str)));
+        }
+        assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+
+        // inserting into the same gap
+        let (low, mut high) = ("a0".to_string(), "a1".to_string());
+        for _ in 0..100 {
+            let key = between(Some(&low), Some(&high));
+            assert!(low < key && key < high, "{low} < {key} < {high}");
+            high = key;
+        }
+    }
+
+    #[test]
+    fn should_reject_invalid_keys() {
+        assert_eq!(key_between(Some("a1"), Some("a0")), None);
+        assert_eq!(key_between(Some("a0"), Some("a0")), None);
+        assert_eq!(key_between(Some(""), None), None);
+        assert_eq!(key_between(Some("a"), None), None);
+        assert_eq!(key_between(Some("a00"), None), None);
+        assert_eq!(key_between(Some("a0!"), None), None);
+    }
+}
@@ -1,132 +1,836 @@
-use sqlx::{FromRow, PgPool};
+use chrono::{DateTime, Utc};
+use sqlx::{types::Json, PgConnection, PgPool};
 
 use crate::repository::RepositoryError;
 
-use super::{CreateTodo, Todo, TodoRepository, UpdateTodo};
+use super::{
+    check_unknown_labels, fold_search_hits, fold_todos, invalid_position, normalize_label_ids,
+    position::key_between, Board, CreateDependency, CreateTodo, MoveTodo, Recurrence,
+    SubtaskCompletion, Todo, TodoPage, TodoQuery, TodoRepository, TodoSearch, TodoSearchHit,
+    TodoSearchHitDto, TodoStatus, TodoWithLabelDto, UpdateTodo, MARKER_END, MARKER_START,
+};
 
 #[derive(Debug, Clone)]
 pub struct TodoRepositoryForPostgres {
     pool: PgPool,
+    subtask_completion: SubtaskCompletion,
 }
 
 impl TodoRepositoryForPostgres {
     pub fn new(pool: PgPool) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            subtask_completion: SubtaskCompletion::default(),
+        }
     }
-}
 
-#[derive(Debug, Clone, FromRow)]
-struct TodoDto {
-    id: i32,
-    text: String,
-    completed: bool,
-}
-
-impl From<TodoDto> for Todo {
-    fn from(dto: TodoDto) -> Self {
+    pub fn with_subtask_completion(self, subtask_completion: SubtaskCompletion) -> Self {
         Self {
-            id: dto.id as u32,
-            text: dto.text,
-            completed: dto.completed,
+            subtask_completion,
+            ..self
+        }
+    }
+
+    /// 完了にした`id`のTodoを保存したあとに呼び出し、未完了のサブタスクがあればエラーにする。
+    /// 繰り返すTodoであれば次のTodoを作り、必要であれば親のTodoも完了にする
+    async fn complete(
+        &self,
+        conn: &mut PgConnection,
+        user_id: i32,
+        id: u32,
+    ) -> Result<(), RepositoryError> {
+        if open_subtasks(&mut *conn, id).await? > 0 {
+            return Err(RepositoryError::OpenSubtasks(id));
+        }
+        create_next_occurrence(&mut *conn, user_id, id).await?;
+        if self.subtask_completion == SubtaskCompletion::Block {
+            return Ok(());
+        }
+
+        let mut child = id;
+        while let Some(parent) = parent_of(&mut *conn, child).await? {
+            if open_subtasks(&mut *conn, parent).await? > 0 {
+                break;
+            }
+            let completed = sqlx::query(
+                r#"
+                    UPDATE todo
+                    SET status = $1
+                    WHERE id = $2
+                      AND status <> $1;
+                "#,
+            )
+            .bind(TodoStatus::Done)
+            .bind(parent as i32)
+            .execute(&mut *conn)
+            .await
+            .map_err(handle_sqlx_error)?;
+            if completed.rows_affected() == 0 {
+                break;
+            }
+            child = parent;
         }
+        Ok(())
     }
 }
 
 #[axum::async_trait]
 impl TodoRepository for TodoRepositoryForPostgres {
-    async fn all(&self) -> Result<Vec<Todo>, RepositoryError> {
-        let todos = sqlx::query_as::<_, TodoDto>(
+    async fn all(&self, user_id: i32, query: &TodoQuery) -> Result<TodoPage, RepositoryError> {
+        let labels = normalize_label_ids(query.labels.clone());
+        let sql = format!(
             r#"
-                SELECT *
-                FROM todo
-                ORDER BY id DESC;
+                WITH RECURSIVE label_tree (root_id, id) AS (
+                    SELECT id, id
+                    FROM label
+                    WHERE id = ANY($4)
+                    UNION
+                    SELECT label_tree.root_id, label.id
+                    FROM label
+                        INNER JOIN label_tree ON label.parent_id = label_tree.id
+                ), page AS (
+                    SELECT *
+                    FROM todo
+                    WHERE user_id = $1
+                      AND ($2::todo_status IS NULL OR status = $2)
+                      AND ($3::TEXT IS NULL OR text ILIKE $3 ESCAPE '\')
+                      AND (
+                          SELECT COUNT(DISTINCT label_tree.root_id)
+                          FROM todo_labels
+                              INNER JOIN label_tree ON label_tree.id = todo_labels.label_id
+                          WHERE todo_labels.todo_id = todo.id
+                      ) = CARDINALITY($4)
+                      AND ($7::TIMESTAMPTZ IS NULL OR due_at < $7)
+                      AND ($8::BOOLEAN IS NULL OR (due_at IS NOT NULL AND due_at < $9 AND status <> 'done') = $8)
+                    ORDER BY {}
+                    LIMIT $5 OFFSET $6
+                )
+                SELECT page.*,
+                       page.id IN (SELECT id FROM blocked_todo) AS blocked,
+                       (SELECT COUNT(*) FROM comments WHERE comments.todo_id = page.id) AS comment_count,
+                       label.id AS label_id,
+                       label.name AS label_name,
+                       label.color AS label_color,
+                       label.parent_id AS label_parent_id
+                FROM page
+                    LEFT OUTER JOIN todo_labels ON page.id = todo_labels.todo_id
+                    LEFT OUTER JOIN label ON label.id = todo_labels.label_id
+                ORDER BY {}, label.id ASC;
+            "#,
+            query.order_by("todo", r#""C""#),
+            query.order_by("page", r#""C""#),
+        );
+        let rows = sqlx::query_as::<_, TodoWithLabelDto>(&sql)
+            .bind(user_id)
+            .bind(query.status)
+            .bind(query.text_pattern())
+            .bind(&labels)
+            .bind(query.fetch_limit() as i64)
+            .bind(query.offset as i64)
+            .bind(query.due_before)
+            .bind(query.overdue)
+            .bind(Utc::now())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(handle_sqlx_error)?;
+
+        Ok(TodoPage::new(fold_todos(rows), query))
+    }
+
+    async fn search(
+        &self,
+        user_id: i32,
+        search: &TodoSearch,
+    ) -> Result<Vec<TodoSearchHit>, RepositoryError> {
+        // 検索語が全て記号の場合、空のtsqueryは何にも一致しない
+        let terms = search.terms();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let options = format!("StartSel={MARKER_START}, StopSel={MARKER_END}, HighlightAll=true");
+        let rows = sqlx::query_as::<_, TodoSearchHitDto>(
+            r#"
+                WITH hits AS (
+                    SELECT todo.*,
+                           ts_rank(todo.search, query, 1)              AS rank,
+                           ts_headline('simple', todo.text, query, $3) AS snippet
+                    FROM todo, plainto_tsquery('simple', $2) AS query
+                    WHERE todo.user_id = $1
+                      AND todo.search @@ query
+                    ORDER BY rank DESC, todo.id DESC
+                    LIMIT $4
+                )
+                SELECT hits.*,
+                       hits.id IN (SELECT id FROM blocked_todo) AS blocked,
+                       (SELECT COUNT(*) FROM comments WHERE comments.todo_id = hits.id) AS comment_count,
+                       label.id AS label_id,
+                       label.name AS label_name,
+                       label.color AS label_color,
+                       label.parent_id AS label_parent_id
+                FROM hits
+                    LEFT OUTER JOIN todo_labels ON hits.id = todo_labels.todo_id
+                    LEFT OUTER JOIN label ON label.id = todo_labels.label_id
+                ORDER BY hits.rank DESC, hits.id DESC, label.id ASC;
             "#,
         )
+        .bind(user_id)
+        .bind(terms.join(" "))
+        .bind(options)
+        .bind(search.limit() as i64)
         .fetch_all(&self.pool)
         .await
         .map_err(handle_sqlx_error)?;
 
-        let todos = todos.into_iter().map(Todo::from).collect();
-        Ok(todos)
+        Ok(fold_search_hits(rows))
     }
 
-    async fn find(&self, id: u32) -> Result<Todo, RepositoryError> {
-        let todo = sqlx::query_as::<_, TodoDto>(
+    async fn find(&self, user_id: i32, id: u32) -> Result<Todo, RepositoryError> {
+        let rows = sqlx::query_as::<_, TodoWithLabelDto>(
             r#"
-                SELECT *
+                SELECT todo.*,
+                       todo.id IN (SELECT id FROM blocked_todo) AS blocked,
+                       (SELECT COUNT(*) FROM comments WHERE comments.todo_id = todo.id) AS comment_count,
+                       label.id AS label_id,
+                       label.name AS label_name,
+                       label.color AS label_color,
+                       label.parent_id AS label_parent_id
                 FROM todo
-                WHERE id = $1;
+                    LEFT OUTER JOIN todo_labels ON todo.id = todo_labels.todo_id
+                    LEFT OUTER JOIN label ON label.id = todo_labels.label_id
+                WHERE todo.id = $1
+                  AND todo.user_id = $2
+                ORDER BY label.id ASC;
             "#,
         )
         .bind(id as i32)
-        .fetch_optional(&self.pool)
+        .bind(user_id)
+        .fetch_all(&self.pool)
         .await
-        .map_err(handle_sqlx_error)?
-        .ok_or_else(|| RepositoryError::NotFound(id))?;
+        .map_err(handle_sqlx_error)?;
 
-        let todo = Todo::from(todo);
+        let todo = fold_todos(rows)
+            .into_iter()
+            .next()
+            .ok_or(RepositoryError::NotFound(id))?;
         Ok(todo)
     }
 
-    async fn create(&self, payload: CreateTodo) -> Result<Todo, RepositoryError> {
-        let todo = sqlx::query_as::<_, TodoDto>(
+    async fn create(&self, user_id: i32, payload: CreateTodo) -> Result<Todo, RepositoryError> {
+        let labels = normalize_label_ids(payload.labels);
+
+        let mut tx = self.pool.begin().await.map_err(handle_sqlx_error)?;
+
+        lock_labels(&mut tx, user_id, &labels).await?;
+        if let Some(parent_id) = payload.parent_id {
+            lock_parent(&mut tx, user_id, parent_id).await?;
+        }
+
+        let position = last_position(&mut tx, user_id).await?;
+
+        let id = sqlx::query_scalar::<_, i32>(
             r#"
-                INSERT INTO todo (text, completed)
-                VALUES ($1, false)
-                returning *;
+                INSERT INTO todo
+                    (text, user_id, due_at, position, parent_id, recurrence, priority, estimate)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                RETURNING id;
             "#,
         )
         .bind(payload.text)
-        .fetch_one(&self.pool)
+        .bind(user_id)
+        .bind(payload.due_at)
+        .bind(position)
+        .bind(payload.parent_id.map(|id| id as i32))
+        .bind(payload.recurrence.map(Json))
+        .bind(payload.priority)
+        .bind(payload.estimate.map(|estimate| estimate as i32))
+        .fetch_one(&mut *tx)
         .await
-        .unwrap();
+        .map_err(handle_sqlx_error)?;
 
-        let todo = Todo::from(todo);
-        Ok(todo)
+        attach_labels(&mut tx, id, &labels).await?;
+
+        tx.commit().await.map_err(handle_sqlx_error)?;
+
+        self.find(user_id, id as u32).await
     }
 
-    async fn update(&self, id: u32, payload: UpdateTodo) -> Result<Todo, RepositoryError> {
-        let before_todo = self.find(id).await?;
+    async fn update(
+        &self,
+        user_id: i32,
+        id: u32,
+        payload: UpdateTodo,
+    ) -> Result<Todo, RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(handle_sqlx_error)?;
+
+        let status = lock_status(&mut tx, user_id, id, payload.status).await?;
 
-        let todo = sqlx::query_as::<_, TodoDto>(
+        sqlx::query_scalar::<_, i32>(
             r#"
                 UPDATE todo
-                set text     = $1,
-                    completed= $2
+                SET text       = COALESCE($1, text),
+                    status     = $2,
+                    due_at     = CASE WHEN $5 THEN $6 ELSE due_at END,
+                    recurrence = CASE WHEN $7 THEN $8 ELSE recurrence END,
+                    priority   = COALESCE($9, priority),
+                    estimate   = CASE WHEN $10 THEN $11 ELSE estimate END
                 WHERE id = $3
-                RETURNING *;
+                  AND user_id = $4
+                RETURNING id;
             "#,
         )
-        .bind(payload.text.unwrap_or(before_todo.text))
-        .bind(payload.completed.unwrap_or(before_todo.completed))
+        .bind(payload.text)
+        .bind(status)
         .bind(id as i32)
-        .fetch_one(&self.pool)
+        .bind(user_id)
+        .bind(payload.due_at.is_some())
+        .bind(payload.due_at.flatten())
+        .bind(payload.recurrence.is_some())
+        .bind(payload.recurrence.flatten().map(Json))
+        .bind(payload.priority)
+        .bind(payload.estimate.is_some())
+        .bind(payload.estimate.flatten().map(|estimate| estimate as i32))
+        .fetch_optional(&mut *tx)
         .await
-        .map_err(handle_sqlx_error)?;
+        .map_err(handle_sqlx_error)?
+        .ok_or(RepositoryError::NotFound(id))?;
 
-        let todo = Todo::from(todo);
-        Ok(todo)
+        if let Some(labels) = payload.labels {
+            let labels = normalize_label_ids(labels);
+            lock_labels(&mut tx, user_id, &labels).await?;
+
+            sqlx::query(
+                r#"
+                    DELETE
+                    FROM todo_labels
+                    WHERE todo_id = $1;
+                "#,
+            )
+            .bind(id as i32)
+            .execute(&mut *tx)
+            .await
+            .map_err(handle_sqlx_error)?;
+
+            attach_labels(&mut tx, id as i32, &labels).await?;
+        }
+
+        if payload.status == Some(TodoStatus::Done) {
+            self.complete(&mut tx, user_id, id).await?;
+        }
+
+        tx.commit().await.map_err(handle_sqlx_error)?;
+
+        self.find(user_id, id).await
     }
 
-    async fn delete(&self, id: u32) -> Result<(), RepositoryError> {
-        let result = sqlx::query(
+    async fn delete(&self, user_id: i32, id: u32) -> Result<(), RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(handle_sqlx_error)?;
+
+        // サブタスクもまとめて削除する
+        let ids = sqlx::query_scalar::<_, i32>(
             r#"
+                WITH RECURSIVE subtree (id) AS (
+                    SELECT id
+                    FROM todo
+                    WHERE id = $1
+                      AND user_id = $2
+                    UNION ALL
+                    SELECT todo.id
+                    FROM todo
+                        INNER JOIN subtree ON todo.parent_id = subtree.id
+                )
                 DELETE
                 FROM todo
-                WHERE id = $1;
+                WHERE id IN (SELECT id FROM subtree)
+                RETURNING id;
             "#,
         )
         .bind(id as i32)
-        .execute(&self.pool)
+        .bind(user_id)
+        .fetch_all(&mut *tx)
         .await
         .map_err(handle_sqlx_error)?;
 
-        if result.rows_affected() == 0 {
+        if ids.is_empty() {
             return Err(RepositoryError::NotFound(id));
         }
 
+        sqlx::query(
+            r#"
+                DELETE
+                FROM todo_labels
+                WHERE todo_id = ANY($1);
+            "#,
+        )
+        .bind(&ids)
+        .execute(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        tx.commit().await.map_err(handle_sqlx_error)?;
+
         Ok(())
     }
+
+    async fn children(&self, user_id: i32, id: u32) -> Result<Vec<Todo>, RepositoryError> {
+        let rows = sqlx::query_as::<_, TodoWithLabelDto>(
+            r#"
+                SELECT todo.*,
+                       todo.id IN (SELECT id FROM blocked_todo) AS blocked,
+                       (SELECT COUNT(*) FROM comments WHERE comments.todo_id = todo.id) AS comment_count,
+                       label.id AS label_id,
+                       label.name AS label_name,
+                       label.color AS label_color,
+                       label.parent_id AS label_parent_id
+                FROM todo
+                    LEFT OUTER JOIN todo_labels ON todo.id = todo_labels.todo_id
+                    LEFT OUTER JOIN label ON label.id = todo_labels.label_id
+                WHERE todo.parent_id = $1
+                  AND todo.user_id = $2
+                ORDER BY todo.id ASC, label.id ASC;
+            "#,
+        )
+        .bind(id as i32)
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        let children = fold_todos(rows);
+        if children.is_empty() {
+            // 親のTodoが存在しない場合はNotFoundにする
+            self.find(user_id, id).await?;
+        }
+        Ok(children)
+    }
+
+    async fn board(&self, user_id: i32) -> Result<Board, RepositoryError> {
+        let rows = sqlx::query_as::<_, TodoWithLabelDto>(
+            r#"
+                SELECT todo.*,
+                       todo.id IN (SELECT id FROM blocked_todo) AS blocked,
+                       (SELECT COUNT(*) FROM comments WHERE comments.todo_id = todo.id) AS comment_count,
+                       label.id AS label_id,
+                       label.name AS label_name,
+                       label.color AS label_color,
+                       label.parent_id AS label_parent_id
+                FROM todo
+                    LEFT OUTER JOIN todo_labels ON todo.id = todo_labels.todo_id
+                    LEFT OUTER JOIN label ON label.id = todo_labels.label_id
+                WHERE todo.user_id = $1
+                ORDER BY todo.position ASC, todo.id ASC, label.id ASC;
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        Ok(Board::new(fold_todos(rows)))
+    }
+
+    async fn move_todo(
+        &self,
+        user_id: i32,
+        id: u32,
+        payload: MoveTodo,
+    ) -> Result<Todo, RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(handle_sqlx_error)?;
+
+        let status = lock_status(&mut tx, user_id, id, payload.status).await?;
+        let position = position_after(&mut tx, user_id, id, status, payload.after).await?;
+
+        sqlx::query(
+            r#"
+                UPDATE todo
+                SET status   = $1,
+                    position = $2
+                WHERE id = $3;
+            "#,
+        )
+        .bind(status)
+        .bind(position)
+        .bind(id as i32)
+        .execute(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        if payload.status == Some(TodoStatus::Done) {
+            self.complete(&mut tx, user_id, id).await?;
+        }
+
+        tx.commit().await.map_err(handle_sqlx_error)?;
+
+        self.find(user_id, id).await
+    }
+
+    async fn add_dependency(
+        &self,
+        user_id: i32,
+        id: u32,
+        payload: CreateDependency,
+    ) -> Result<Todo, RepositoryError> {
+        let blocked_by = payload.blocked_by;
+        let mut tx = self.pool.begin().await.map_err(handle_sqlx_error)?;
+
+        // 同じユーザーの依存関係の追加を直列化し、同時に追加した依存関係で循環が生じないようにする
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext('todo_dependencies'), $1);")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(handle_sqlx_error)?;
+
+        let found = sqlx::query_scalar::<_, i32>(
+            r#"
+                SELECT id
+                FROM todo
+                WHERE id = ANY($1)
+                  AND user_id = $2
+                FOR SHARE;
+            "#,
+        )
+        .bind([id as i32, blocked_by as i32])
+        .bind(user_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?;
+        if let Some(missing) = [id, blocked_by]
+            .into_iter()
+            .find(|todo_id| !found.contains(&(*todo_id as i32)))
+        {
+            return Err(RepositoryError::NotFound(missing));
+        }
+
+        // `blocked_by`のTodoが既に`id`のTodoに (間接的にでも) ブロックされていれば循環になる
+        let cycle = sqlx::query_scalar::<_, bool>(
+            r#"
+                WITH RECURSIVE blockers (id) AS (
+                    SELECT $1::INTEGER
+                    UNION
+                    SELECT todo_dependencies.blocker_id
+                    FROM todo_dependencies
+                        INNER JOIN blockers ON todo_dependencies.todo_id = blockers.id
+                )
+                SELECT EXISTS (SELECT * FROM blockers WHERE id = $2);
+            "#,
+        )
+        .bind(blocked_by as i32)
+        .bind(id as i32)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?;
+        if cycle {
+            return Err(RepositoryError::DependencyCycle { id, blocked_by });
+        }
+
+        sqlx::query(
+            r#"
+                INSERT INTO todo_dependencies (todo_id, blocker_id)
+                VALUES ($1, $2)
+                ON CONFLICT DO NOTHING;
+            "#,
+        )
+        .bind(id as i32)
+        .bind(blocked_by as i32)
+        .execute(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        tx.commit().await.map_err(handle_sqlx_error)?;
+
+        self.find(user_id, id).await
+    }
+}
+
+/// 現在の状態から`next`に変更できることを確認して変更後の状態を返し、トランザクション中に変更されないようロックする
+async fn lock_status(
+    conn: &mut PgConnection,
+    user_id: i32,
+    id: u32,
+    next: Option<TodoStatus>,
+) -> Result<TodoStatus, RepositoryError> {
+    let current = sqlx::query_scalar::<_, TodoStatus>(
+        r#"
+            SELECT status
+            FROM todo
+            WHERE id = $1
+              AND user_id = $2
+            FOR UPDATE;
+        "#,
+    )
+    .bind(id as i32)
+    .bind(user_id)
+    .fetch_optional(conn)
+    .await
+    .map_err(handle_sqlx_error)?
+    .ok_or(RepositoryError::NotFound(id))?;
+
+    match next {
+        Some(next) => current.transition(id, next),
+        None => Ok(current),
+    }
+}
+
+/// 親にするTodoが`user_id`のユーザーのものであることを確認し、トランザクション中に削除されないようロックする
+async fn lock_parent(
+    conn: &mut PgConnection,
+    user_id: i32,
+    parent_id: u32,
+) -> Result<(), RepositoryError> {
+    sqlx::query_scalar::<_, i32>(
+        r#"
+            SELECT id
+            FROM todo
+            WHERE id = $1
+              AND user_id = $2
+            FOR SHARE;
+        "#,
+    )
+    .bind(parent_id as i32)
+    .bind(user_id)
+    .fetch_optional(conn)
+    .await
+    .map_err(handle_sqlx_error)?
+    .ok_or(RepositoryError::NotFound(parent_id))?;
+
+    Ok(())
+}
+
+async fn parent_of(conn: &mut PgConnection, id: u32) -> Result<Option<u32>, RepositoryError> {
+    let parent_id = sqlx::query_scalar::<_, Option<i32>>(
+        r#"
+            SELECT parent_id
+            FROM todo
+            WHERE id = $1
+            FOR UPDATE;
+        "#,
+    )
+    .bind(id as i32)
+    .fetch_one(conn)
+    .await
+    .map_err(handle_sqlx_error)?;
+
+    Ok(parent_id.map(|id| id as u32))
+}
+
+/// `id`のTodoの子孫のうち未完了のものを数える
+async fn open_subtasks(conn: &mut PgConnection, id: u32) -> Result<i64, RepositoryError> {
+    sqlx::query_scalar::<_, i64>(
+        r#"
+            WITH RECURSIVE subtree (id, status) AS (
+                SELECT id, status
+                FROM todo
+                WHERE parent_id = $1
+                UNION ALL
+                SELECT todo.id, todo.status
+                FROM todo
+                    INNER JOIN subtree ON todo.parent_id = subtree.id
+            )
+            SELECT COUNT(*)
+            FROM subtree
+            WHERE status <> $2;
+        "#,
+    )
+    .bind(id as i32)
+    .bind(TodoStatus::Done)
+    .fetch_one(conn)
+    .await
+    .map_err(handle_sqlx_error)
+}
+
+/// 新しいTodoを未着手の列の末尾に並べるキー
+async fn last_position(conn: &mut PgConnection, user_id: i32) -> Result<String, RepositoryError> {
+    let last = sqlx::query_scalar::<_, Option<String>>(
+        r#"
+            SELECT MAX(position)
+            FROM todo
+            WHERE user_id = $1
+              AND status = $2;
+        "#,
+    )
+    .bind(user_id)
+    .bind(TodoStatus::default())
+    .fetch_one(conn)
+    .await
+    .map_err(handle_sqlx_error)?;
+
+    key_between(last.as_deref(), None).ok_or_else(invalid_position)
+}
+
+/// 繰り返すTodoであれば、次の期限で同じ内容とラベルのTodoを作り、繰り返しの規則を引き継ぐ。
+/// 完了にしたTodoは規則を残し、作ったTodoを`next_occurrence_id`に記録する。
+/// 記録があれば何もしないため、完了にし直しても次のTodoは一つしかできない
+async fn create_next_occurrence(
+    conn: &mut PgConnection,
+    user_id: i32,
+    id: u32,
+) -> Result<(), RepositoryError> {
+    let (due_at, recurrence, next_occurrence_id) =
+        sqlx::query_as::<_, (Option<DateTime<Utc>>, Option<Json<Recurrence>>, Option<i32>)>(
+            r#"
+            SELECT due_at, recurrence, next_occurrence_id
+            FROM todo
+            WHERE id = $1;
+        "#,
+        )
+        .bind(id as i32)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(handle_sqlx_error)?;
+    // 既に次のTodoを作っていれば、完了にし直しても作らない
+    let (Some(Json(recurrence)), None) = (recurrence, next_occurrence_id) else {
+        return Ok(());
+    };
+
+    let Some((due_at, recurrence)) = recurrence.next(due_at.unwrap_or_else(Utc::now)) else {
+        return Ok(());
+    };
+
+    let position = last_position(&mut *conn, user_id).await?;
+    let next_id = sqlx::query_scalar::<_, i32>(
+        r#"
+            INSERT INTO todo
+                (text, user_id, due_at, position, parent_id, recurrence, priority, estimate)
+            SELECT text, user_id, $2, $3, parent_id, $4, priority, estimate
+            FROM todo
+            WHERE id = $1
+            RETURNING id;
+        "#,
+    )
+    .bind(id as i32)
+    .bind(due_at)
+    .bind(position)
+    .bind(Json(recurrence))
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(handle_sqlx_error)?;
+
+    sqlx::query(
+        r#"
+            UPDATE todo
+            SET next_occurrence_id = $2
+            WHERE id = $1;
+        "#,
+    )
+    .bind(id as i32)
+    .bind(next_id)
+    .execute(&mut *conn)
+    .await
+    .map_err(handle_sqlx_error)?;
+
+    sqlx::query(
+        r#"
+            INSERT INTO todo_labels (todo_id, label_id)
+            SELECT $1, label_id
+            FROM todo_labels
+            WHERE todo_id = $2;
+        "#,
+    )
+    .bind(next_id)
+    .bind(id as i32)
+    .execute(conn)
+    .await
+    .map_err(handle_sqlx_error)?;
+
+    Ok(())
+}
+
+/// `status`の列で`after`の直後 (省略した場合は列の先頭) に`id`のTodoを並べるキーを求める
+async fn position_after(
+    conn: &mut PgConnection,
+    user_id: i32,
+    id: u32,
+    status: TodoStatus,
+    after: Option<u32>,
+) -> Result<String, RepositoryError> {
+    let lower = match after {
+        Some(after) => Some(
+            sqlx::query_scalar::<_, String>(
+                r#"
+                    SELECT position
+                    FROM todo
+                    WHERE id = $1
+                      AND user_id = $2
+                      AND status = $3
+                      AND id <> $4;
+                "#,
+            )
+            .bind(after as i32)
+            .bind(user_id)
+            .bind(status)
+            .bind(id as i32)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(handle_sqlx_error)?
+            .ok_or(RepositoryError::NotFound(after))?,
+        ),
+        None => None,
+    };
+
+    let upper = sqlx::query_scalar::<_, Option<String>>(
+        r#"
+            SELECT MIN(position)
+            FROM todo
+            WHERE user_id = $1
+              AND status = $2
+              AND id <> $3
+              AND ($4::TEXT IS NULL OR position > $4);
+        "#,
+    )
+    .bind(user_id)
+    .bind(status)
+    .bind(id as i32)
+    .bind(&lower)
+    .fetch_one(conn)
+    .await
+    .map_err(handle_sqlx_error)?;
+
+    key_between(lower.as_deref(), upper.as_deref()).ok_or_else(invalid_position)
+}
+
+/// 紐付けるラベルが全て`user_id`のユーザーのものであることを確認し、トランザクション中に削除されないようロックする
+async fn lock_labels(
+    conn: &mut PgConnection,
+    user_id: i32,
+    labels: &[i32],
+) -> Result<(), RepositoryError> {
+    let found = sqlx::query_scalar::<_, i32>(
+        r#"
+            SELECT id
+            FROM label
+            WHERE id = ANY($1)
+              AND user_id = $2
+            FOR SHARE;
+        "#,
+    )
+    .bind(labels)
+    .bind(user_id)
+    .fetch_all(conn)
+    .await
+    .map_err(handle_sqlx_error)?;
+
+    check_unknown_labels(labels, &found)
+}
+
+async fn attach_labels(
+    conn: &mut PgConnection,
+    todo_id: i32,
+    labels: &[i32],
+) -> Result<(), RepositoryError> {
+    sqlx::query(
+        r#"
+            INSERT INTO todo_labels (todo_id, label_id)
+            SELECT $1, id
+            FROM UNNEST($2) AS t (id);
+        "#,
+    )
+    .bind(todo_id)
+    .bind(labels)
+    .execute(conn)
+    .await
+    .map_err(handle_sqlx_error)?;
+
+    Ok(())
 }
 
 fn handle_sqlx_error(error: sqlx::Error) -> RepositoryError {
@@ -135,60 +839,32 @@ fn handle_sqlx_error(error: sqlx::Error) -> RepositoryError {
 
 #[cfg(test)]
 mod tests {
+    use crate::repository::{
+        label::LabelRepositoryForPostgres, todo::conformance, user::UserRepositoryForPostgres,
+    };
+
     use super::*;
 
     #[ignore = "Dependence of database"]
     #[tokio::test]
-    async fn crud_scenario() {
+    async fn conformance() {
         let database_url = std::env::var("DATABASE_URL").expect("undefined [DATABASE_URL]");
         let pool = PgPool::connect(&database_url)
             .await
             .unwrap_or_else(|_| panic!("fail connect database"));
-        let repository = TodoRepositoryForPostgres::new(pool);
 
-        let todo_text = "[crud_scenario] text";
+        conformance::run(
+            TodoRepositoryForPostgres::new(pool.clone()),
+            LabelRepositoryForPostgres::new(pool.clone()),
+            UserRepositoryForPostgres::new(pool.clone()),
+        )
+        .await;
 
-        // create
-        let created = repository
-            .create(CreateTodo {
-                text: todo_text.to_string(),
-            })
-            .await
-            .expect("fail create todo");
-        assert_eq!(created.text, todo_text);
-        assert!(!created.completed);
-
-        // find
-        let todo = repository.find(created.id).await.expect("fail find todo");
-        assert_eq!(created, todo);
-
-        // all
-        let todos = repository.all().await.expect("fail fetch all todos");
-        let todo = todos.into_iter().next().unwrap();
-        assert_eq!(created, todo);
-
-        // update
-        let updated_text = "[crud_scenario] updated text";
-        let todo = repository
-            .update(
-                created.id,
-                UpdateTodo {
-                    text: Some(updated_text.to_string()),
-                    completed: Some(true),
-                },
-            )
-            .await
-            .expect("fail update todo");
-        assert_eq!(created.id, todo.id);
-        assert_eq!(todo.text, updated_text);
-        assert!(todo.completed);
-
-        // delete
-        repository
-            .delete(created.id)
-            .await
-            .expect("fail delete todo");
-        let res = repository.find(created.id).await;
-        assert!(res.is_err())
+        conformance::run_auto_complete(
+            TodoRepositoryForPostgres::new(pool.clone())
+                .with_subtask_completion(SubtaskCompletion::AutoComplete),
+            UserRepositoryForPostgres::new(pool),
+        )
+        .await;
     }
 }
@@ -0,0 +1,285 @@
+//! 繰り返すTodoの規則 (iCalendarのRRULEのうち、よく使う部分だけを扱う)
+//!
+//! 完了にしたTodoの期限を起点に次の期限を求める。曜日はUTCで判定する。
+
+use chrono::{DateTime, Datelike, Days, Months, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use validator::ValidationError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Recurrence {
+    freq: Frequency,
+    /// 何日 (週、月) 毎に繰り返すか
+    #[serde(default = "default_interval")]
+    interval: u32,
+    /// 繰り返す曜日 (`daily`と`weekly`のみ)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    by_weekday: Vec<Weekday>,
+    /// このTodoを含めて残りの回数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    count: Option<u32>,
+    /// この日時より後には繰り返さない
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    until: Option<DateTime<Utc>>,
+}
+
+fn default_interval() -> u32 {
+    1
+}
+
+impl Recurrence {
+    pub fn new(freq: Frequency) -> Self {
+        Self {
+            freq,
+            interval: default_interval(),
+            by_weekday: Vec::new(),
+            count: None,
+            until: None,
+        }
+    }
+
+    pub fn with_interval(self, interval: u32) -> Self {
+        Self { interval, ..self }
+    }
+
+    pub fn with_weekdays(self, by_weekday: Vec<Weekday>) -> Self {
+        Self { by_weekday, ..self }
+    }
+
+    pub fn with_count(self, count: u32) -> Self {
+        Self {
+            count: Some(count),
+            ..self
+        }
+    }
+
+    pub fn with_until(self, until: DateTime<Utc>) -> Self {
+        Self {
+            until: Some(until),
+            ..self
+        }
+    }
+
+    /// `current`の次の日時と、次のTodoに引き継ぐ規則を返す。繰り返しが終わった場合は`None`
+    pub(super) fn next(&self, current: DateTime<Utc>) -> Option<(DateTime<Utc>, Recurrence)> {
+        let count = match self.count {
+            Some(count) if count <= 1 => return None,
+            count => count.map(|count| count - 1),
+        };
+        let next = match self.freq {
+            Frequency::Daily => self.next_day(current)?,
+            Frequency::Weekly => self.next_week(current)?,
+            Frequency::Monthly => self.next_month(current)?,
+        };
+        if self.until.is_some_and(|until| next > until) {
+            return None;
+        }
+        Some((
+            next,
+            Recurrence {
+                count,
+                ..self.clone()
+            },
+        ))
+    }
+
+    fn matches(&self, date: DateTime<Utc>) -> bool {
+        self.by_weekday.is_empty() || self.by_weekday.contains(&date.weekday())
+    }
+
+    /// 曜日の並びは7日で一巡するため、それまでに一致しなければ一致する日はない
+    fn next_day(&self, current: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut date = current;
+        for _ in 0..7 {
+            date = date.checked_add_days(Days::new(self.interval.into()))?;
+            if self.matches(date) {
+                return Some(date);
+            }
+        }
+        None
+    }
+
+    /// 同じ週 (月曜始まり) の残りの曜日から探し、なければ`interval`週後の最初の曜日にする
+    fn next_week(&self, current: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if self.by_weekday.is_empty() {
+            return current.checked_add_days(Days::new(7 * u64::from(self.interval)));
+        }
+
+        let rest = 6 - current.weekday().num_days_from_monday();
+        if let Some(date) = (1..=rest)
+            .filter_map(|days| current.checked_add_days(Days::new(days.into())))
+            .find(|date| self.matches(*date))
+        {
+            return Some(date);
+        }
+
+        let monday = current
+            .checked_sub_days(Days::new(current.weekday().num_days_from_monday().into()))?
+            .checked_add_days(Days::new(7 * u64::from(self.interval)))?;
+        (0..7)
+            .filter_map(|days| monday.checked_add_days(Days::new(days)))
+            .find(|date| self.matches(*date))
+    }
+
+    /// RRULEと同様に、同じ日がない月 (2月30日など) は飛ばす。間隔が大きすぎて表せない日付は作らない
+    fn next_month(&self, current: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        (1..=12)
+            .filter_map(|n| {
+                let months = self.interval.checked_mul(n)?;
+                current.checked_add_months(Months::new(months))
+            })
+            .find(|date| date.day() == current.day())
+    }
+}
+
+/// 回数と間隔は1以上で、曜日は日毎か週毎の繰り返しにだけ指定できる
+pub(super) fn validate_recurrence(recurrence: &Recurrence) -> Result<(), ValidationError> {
+    let message = if recurrence.interval == 0 {
+        "Interval must be at least 1"
+    } else if recurrence.count == Some(0) {
+        "Count must be at least 1"
+    } else if recurrence.freq == Frequency::Monthly && !recurrence.by_weekday.is_empty() {
+        "Weekdays can not be used with monthly recurrence"
+    } else {
+        return Ok(());
+    };
+    let mut error = ValidationError::new("recurrence");
+    error.message = Some(message.into());
+    Err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, 9, 0, 0).unwrap()
+    }
+
+    /// `count`回まで繰り返した日付
+    fn occurrences(
+        recurrence: Recurrence,
+        start: DateTime<Utc>,
+        count: usize,
+    ) -> Vec<DateTime<Utc>> {
+        let mut dates = vec![start];
+        let mut current = (start, recurrence);
+        while dates.len() < count {
+            match current.1.next(current.0) {
+                Some(next) => {
+                    dates.push(next.0);
+                    current = next;
+                }
+                None => break,
+            }
+        }
+        dates
+    }
+
+    #[test]
+    fn daily() {
+        let recurrence = Recurrence::new(Frequency::Daily).with_interval(2);
+        assert_eq!(
+            occurrences(recurrence, date(2026, 10, 30), 3),
+            vec![date(2026, 10, 30), date(2026, 11, 1), date(2026, 11, 3)]
+        );
+
+        // weekdays only, starting on Friday 2026-10-16
+        let recurrence = Recurrence::new(Frequency::Daily).with_weekdays(vec![
+            Weekday::Mon,
+            Weekday::Tue,
+            Weekday::Wed,
+            Weekday::Thu,
+            Weekday::Fri,
+        ]);
+        assert_eq!(
+            occurrences(recurrence, date(2026, 10, 16), 3),
+            vec![date(2026, 10, 16), date(2026, 10, 19), date(2026, 10, 20)]
+        );
+
+        // a weekly interval never reaches another weekday
+        let recurrence = Recurrence::new(Frequency::Daily)
+            .with_interval(7)
+            .with_weekdays(vec![Weekday::Mon]);
+        assert_eq!(recurrence.next(date(2026, 10, 16)), None);
+    }
+
+    #[test]
+    fn weekly() {
+        let recurrence = Recurrence::new(Frequency::Weekly);
+        assert_eq!(
+            occurrences(recurrence, date(2026, 10, 16), 2),
+            vec![date(2026, 10, 16), date(2026, 10, 23)]
+        );
+
+        // Monday and Thursday every other week, starting on Monday 2026-10-12
+        let recurrence = Recurrence::new(Frequency::Weekly)
+            .with_interval(2)
+            .with_weekdays(vec![Weekday::Thu, Weekday::Mon]);
+        assert_eq!(
+            occurrences(recurrence, date(2026, 10, 12), 4),
+            vec![
+                date(2026, 10, 12),
+                date(2026, 10, 15),
+                date(2026, 10, 26),
+                date(2026, 10, 29),
+            ]
+        );
+    }
+
+    #[test]
+    fn monthly_skips_missing_days() {
+        let recurrence = Recurrence::new(Frequency::Monthly);
+        assert_eq!(
+            occurrences(recurrence, date(2027, 1, 31), 3),
+            vec![date(2027, 1, 31), date(2027, 3, 31), date(2027, 5, 31)]
+        );
+    }
+
+    #[test]
+    fn huge_interval_ends_the_recurrence() {
+        for freq in [Frequency::Daily, Frequency::Weekly, Frequency::Monthly] {
+            let recurrence = Recurrence::new(freq).with_interval(u32::MAX);
+            assert_eq!(recurrence.next(date(2026, 10, 1)), None, "{freq:?}");
+        }
+    }
+
+    #[test]
+    fn count_and_until_end_the_recurrence() {
+        let recurrence = Recurrence::new(Frequency::Daily).with_count(3);
+        let (_, next) = recurrence.next(date(2026, 10, 1)).unwrap();
+        assert_eq!(next.count, Some(2));
+        assert_eq!(occurrences(recurrence, date(2026, 10, 1), 10).len(), 3);
+
+        let recurrence = Recurrence::new(Frequency::Weekly).with_until(date(2026, 10, 15));
+        assert_eq!(
+            occurrences(recurrence, date(2026, 10, 1), 10),
+            vec![date(2026, 10, 1), date(2026, 10, 8), date(2026, 10, 15)]
+        );
+    }
+
+    #[test]
+    fn validation() {
+        assert!(validate_recurrence(&Recurrence::new(Frequency::Daily)).is_ok());
+        for recurrence in [
+            Recurrence::new(Frequency::Daily).with_interval(0),
+            Recurrence::new(Frequency::Daily).with_count(0),
+            Recurrence::new(Frequency::Monthly).with_weekdays(vec![Weekday::Mon]),
+        ] {
+            assert!(
+                validate_recurrence(&recurrence).is_err(),
+                "accepted {recurrence:?}"
+            );
+        }
+    }
+}
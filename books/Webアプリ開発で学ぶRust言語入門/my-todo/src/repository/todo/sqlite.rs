@@ -0,0 +1,865 @@
+use chrono::{DateTime, Utc};
+use sqlx::{types::Json, SqliteConnection, SqlitePool};
+
+use crate::repository::RepositoryError;
+
+use super::{
+    check_unknown_labels, fold_search_hits, fold_todos, invalid_position, normalize_label_ids,
+    position::key_between, Board, CreateDependency, CreateTodo, MoveTodo, Recurrence,
+    SubtaskCompletion, Todo, TodoPage, TodoQuery, TodoRepository, TodoSearch, TodoSearchHit,
+    TodoSearchHitDto, TodoStatus, TodoWithLabelDto, UpdateTodo, MARKER_END, MARKER_START,
+};
+
+#[derive(Debug, Clone)]
+pub struct TodoRepositoryForSqlite {
+    pool: SqlitePool,
+    subtask_completion: SubtaskCompletion,
+}
+
+impl TodoRepositoryForSqlite {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            subtask_completion: SubtaskCompletion::default(),
+        }
+    }
+
+    pub fn with_subtask_completion(self, subtask_completion: SubtaskCompletion) -> Self {
+        Self {
+            subtask_completion,
+            ..self
+        }
+    }
+
+    /// 完了にした`id`のTodoを保存したあとに呼び出し、未完了のサブタスクがあればエラーにする。
+    /// 繰り返すTodoであれば次のTodoを作り、必要であれば親のTodoも完了にする
+    async fn complete(
+        &self,
+        conn: &mut SqliteConnection,
+        user_id: i32,
+        id: u32,
+    ) -> Result<(), RepositoryError> {
+        if open_subtasks(&mut *conn, id).await? > 0 {
+            return Err(RepositoryError::OpenSubtasks(id));
+        }
+        create_next_occurrence(&mut *conn, user_id, id).await?;
+        if self.subtask_completion == SubtaskCompletion::Block {
+            return Ok(());
+        }
+
+        let mut child = id;
+        while let Some(parent) = parent_of(&mut *conn, child).await? {
+            if open_subtasks(&mut *conn, parent).await? > 0 {
+                break;
+            }
+            let completed = sqlx::query(
+                r#"
+                    UPDATE todo
+                    SET status = $1
+                    WHERE id = $2
+                      AND status <> $1;
+                "#,
+            )
+            .bind(TodoStatus::Done)
+            .bind(parent as i32)
+            .execute(&mut *conn)
+            .await
+            .map_err(handle_sqlx_error)?;
+            if completed.rows_affected() == 0 {
+                break;
+            }
+            child = parent;
+        }
+        Ok(())
+    }
+}
+
+#[axum::async_trait]
+impl TodoRepository for TodoRepositoryForSqlite {
+    async fn all(&self, user_id: i32, query: &TodoQuery) -> Result<TodoPage, RepositoryError> {
+        let labels = normalize_label_ids(query.labels.clone());
+        let sql = format!(
+            r#"
+                WITH RECURSIVE label_tree (root_id, id) AS (
+                    SELECT id, id
+                    FROM label
+                    WHERE id IN (SELECT value FROM json_each($4))
+                    UNION
+                    SELECT label_tree.root_id, label.id
+                    FROM label
+                        INNER JOIN label_tree ON label.parent_id = label_tree.id
+                ), page AS (
+                    SELECT *
+                    FROM todo
+                    WHERE user_id = $1
+                      AND ($2 IS NULL OR status = $2)
+                      AND ($3 IS NULL OR text LIKE $3 ESCAPE '\')
+                      AND (
+                          SELECT COUNT(DISTINCT label_tree.root_id)
+                          FROM todo_labels
+                              INNER JOIN label_tree ON label_tree.id = todo_labels.label_id
+                          WHERE todo_labels.todo_id = todo.id
+                      ) = json_array_length($4)
+                      AND ($7 IS NULL OR due_at < $7)
+                      AND ($8 IS NULL OR (due_at IS NOT NULL AND due_at < $9 AND status <> 'done') = $8)
+                    ORDER BY {}
+                    LIMIT $5 OFFSET $6
+                )
+                SELECT page.*,
+                       page.id IN (SELECT id FROM blocked_todo) AS blocked,
+                       (SELECT COUNT(*) FROM comments WHERE comments.todo_id = page.id) AS comment_count,
+                       label.id AS label_id,
+                       label.name AS label_name,
+                       label.color AS label_color,
+                       label.parent_id AS label_parent_id
+                FROM page
+                    LEFT OUTER JOIN todo_labels ON page.id = todo_labels.todo_id
+                    LEFT OUTER JOIN label ON label.id = todo_labels.label_id
+                ORDER BY {}, label.id ASC;
+            "#,
+            query.order_by("todo", r#"BINARY"#),
+            query.order_by("page", r#"BINARY"#),
+        );
+        let rows = sqlx::query_as::<_, TodoWithLabelDto>(&sql)
+            .bind(user_id)
+            .bind(query.status)
+            .bind(query.text_pattern())
+            .bind(Json(&labels))
+            .bind(query.fetch_limit() as i64)
+            .bind(query.offset as i64)
+            .bind(query.due_before)
+            .bind(query.overdue)
+            .bind(Utc::now())
+            .fetch_all(&self.pool)
+            .await
+            .map_err(handle_sqlx_error)?;
+
+        Ok(TodoPage::new(fold_todos(rows), query))
+    }
+
+    async fn search(
+        &self,
+        user_id: i32,
+        search: &TodoSearch,
+    ) -> Result<Vec<TodoSearchHit>, RepositoryError> {
+        // FTS5の構文として解釈されないよう、検索語はそれぞれ引用符で囲む
+        let terms = search.terms();
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        let query: Vec<String> = terms.iter().map(|term| format!(r#""{term}""#)).collect();
+
+        let rows = sqlx::query_as::<_, TodoSearchHitDto>(
+            r#"
+                WITH hits AS (
+                    SELECT todo.*,
+                           -bm25(todo_search)                AS rank,
+                           highlight(todo_search, 0, $3, $4) AS snippet
+                    FROM todo_search
+                        INNER JOIN todo ON todo.id = todo_search.rowid
+                    WHERE todo_search MATCH $2
+                      AND todo.user_id = $1
+                    ORDER BY rank DESC, todo.id DESC
+                    LIMIT $5
+                )
+                SELECT hits.*,
+                       hits.id IN (SELECT id FROM blocked_todo) AS blocked,
+                       (SELECT COUNT(*) FROM comments WHERE comments.todo_id = hits.id) AS comment_count,
+                       label.id AS label_id,
+                       label.name AS label_name,
+                       label.color AS label_color,
+                       label.parent_id AS label_parent_id
+                FROM hits
+                    LEFT OUTER JOIN todo_labels ON hits.id = todo_labels.todo_id
+                    LEFT OUTER JOIN label ON label.id = todo_labels.label_id
+                ORDER BY hits.rank DESC, hits.id DESC, label.id ASC;
+            "#,
+        )
+        .bind(user_id)
+        .bind(query.join(" "))
+        .bind(MARKER_START.to_string())
+        .bind(MARKER_END.to_string())
+        .bind(search.limit() as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        Ok(fold_search_hits(rows))
+    }
+
+    async fn find(&self, user_id: i32, id: u32) -> Result<Todo, RepositoryError> {
+        let rows = sqlx::query_as::<_, TodoWithLabelDto>(
+            r#"
+                SELECT todo.*,
+                       todo.id IN (SELECT id FROM blocked_todo) AS blocked,
+                       (SELECT COUNT(*) FROM comments WHERE comments.todo_id = todo.id) AS comment_count,
+                       label.id AS label_id,
+                       label.name AS label_name,
+                       label.color AS label_color,
+                       label.parent_id AS label_parent_id
+                FROM todo
+                    LEFT OUTER JOIN todo_labels ON todo.id = todo_labels.todo_id
+                    LEFT OUTER JOIN label ON label.id = todo_labels.label_id
+                WHERE todo.id = $1
+                  AND todo.user_id = $2
+                ORDER BY label.id ASC;
+            "#,
+        )
+        .bind(id as i32)
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        let todo = fold_todos(rows)
+            .into_iter()
+            .next()
+            .ok_or(RepositoryError::NotFound(id))?;
+        Ok(todo)
+    }
+
+    async fn create(&self, user_id: i32, payload: CreateTodo) -> Result<Todo, RepositoryError> {
+        let labels = normalize_label_ids(payload.labels);
+
+        let mut tx = self.pool.begin().await.map_err(handle_sqlx_error)?;
+
+        check_labels(&mut tx, user_id, &labels).await?;
+        if let Some(parent_id) = payload.parent_id {
+            check_parent(&mut tx, user_id, parent_id).await?;
+        }
+
+        let position = last_position(&mut tx, user_id).await?;
+
+        let id = sqlx::query_scalar::<_, i32>(
+            r#"
+                INSERT INTO todo
+                    (text, user_id, due_at, position, parent_id, recurrence, priority, estimate)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                RETURNING id;
+            "#,
+        )
+        .bind(payload.text)
+        .bind(user_id)
+        .bind(payload.due_at)
+        .bind(position)
+        .bind(payload.parent_id.map(|id| id as i32))
+        .bind(payload.recurrence.map(Json))
+        .bind(payload.priority)
+        .bind(payload.estimate.map(|estimate| estimate as i32))
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        attach_labels(&mut tx, id, &labels).await?;
+
+        tx.commit().await.map_err(handle_sqlx_error)?;
+
+        self.find(user_id, id as u32).await
+    }
+
+    async fn update(
+        &self,
+        user_id: i32,
+        id: u32,
+        payload: UpdateTodo,
+    ) -> Result<Todo, RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(handle_sqlx_error)?;
+
+        let status = check_status(&mut tx, user_id, id, payload.status).await?;
+
+        sqlx::query_scalar::<_, i32>(
+            r#"
+                UPDATE todo
+                SET text       = COALESCE($1, text),
+                    status     = $2,
+                    due_at     = CASE WHEN $5 THEN $6 ELSE due_at END,
+                    recurrence = CASE WHEN $7 THEN $8 ELSE recurrence END,
+                    priority   = COALESCE($9, priority),
+                    estimate   = CASE WHEN $10 THEN $11 ELSE estimate END
+                WHERE id = $3
+                  AND user_id = $4
+                RETURNING id;
+            "#,
+        )
+        .bind(payload.text)
+        .bind(status)
+        .bind(id as i32)
+        .bind(user_id)
+        .bind(payload.due_at.is_some())
+        .bind(payload.due_at.flatten())
+        .bind(payload.recurrence.is_some())
+        .bind(payload.recurrence.flatten().map(Json))
+        .bind(payload.priority)
+        .bind(payload.estimate.is_some())
+        .bind(payload.estimate.flatten().map(|estimate| estimate as i32))
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?
+        .ok_or(RepositoryError::NotFound(id))?;
+
+        if let Some(labels) = payload.labels {
+            let labels = normalize_label_ids(labels);
+            check_labels(&mut tx, user_id, &labels).await?;
+
+            sqlx::query(
+                r#"
+                    DELETE
+                    FROM todo_labels
+                    WHERE todo_id = $1;
+                "#,
+            )
+            .bind(id as i32)
+            .execute(&mut *tx)
+            .await
+            .map_err(handle_sqlx_error)?;
+
+            attach_labels(&mut tx, id as i32, &labels).await?;
+        }
+
+        if payload.status == Some(TodoStatus::Done) {
+            self.complete(&mut tx, user_id, id).await?;
+        }
+
+        tx.commit().await.map_err(handle_sqlx_error)?;
+
+        self.find(user_id, id).await
+    }
+
+    async fn delete(&self, user_id: i32, id: u32) -> Result<(), RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(handle_sqlx_error)?;
+
+        // サブタスクもまとめて削除する
+        let ids = sqlx::query_scalar::<_, i32>(
+            r#"
+                WITH RECURSIVE subtree (id) AS (
+                    SELECT id
+                    FROM todo
+                    WHERE id = $1
+                      AND user_id = $2
+                    UNION ALL
+                    SELECT todo.id
+                    FROM todo
+                        INNER JOIN subtree ON todo.parent_id = subtree.id
+                )
+                DELETE
+                FROM todo
+                WHERE id IN (SELECT id FROM subtree)
+                RETURNING id;
+            "#,
+        )
+        .bind(id as i32)
+        .bind(user_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        if ids.is_empty() {
+            return Err(RepositoryError::NotFound(id));
+        }
+
+        sqlx::query(
+            r#"
+                DELETE
+                FROM todo_labels
+                WHERE todo_id IN (SELECT value FROM json_each($1));
+            "#,
+        )
+        .bind(Json(&ids))
+        .execute(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        tx.commit().await.map_err(handle_sqlx_error)?;
+
+        Ok(())
+    }
+
+    async fn children(&self, user_id: i32, id: u32) -> Result<Vec<Todo>, RepositoryError> {
+        let rows = sqlx::query_as::<_, TodoWithLabelDto>(
+            r#"
+                SELECT todo.*,
+                       todo.id IN (SELECT id FROM blocked_todo) AS blocked,
+                       (SELECT COUNT(*) FROM comments WHERE comments.todo_id = todo.id) AS comment_count,
+                       label.id AS label_id,
+                       label.name AS label_name,
+                       label.color AS label_color,
+                       label.parent_id AS label_parent_id
+                FROM todo
+                    LEFT OUTER JOIN todo_labels ON todo.id = todo_labels.todo_id
+                    LEFT OUTER JOIN label ON label.id = todo_labels.label_id
+                WHERE todo.parent_id = $1
+                  AND todo.user_id = $2
+                ORDER BY todo.id ASC, label.id ASC;
+            "#,
+        )
+        .bind(id as i32)
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        let children = fold_todos(rows);
+        if children.is_empty() {
+            // 親のTodoが存在しない場合はNotFoundにする
+            self.find(user_id, id).await?;
+        }
+        Ok(children)
+    }
+
+    async fn board(&self, user_id: i32) -> Result<Board, RepositoryError> {
+        let rows = sqlx::query_as::<_, TodoWithLabelDto>(
+            r#"
+                SELECT todo.*,
+                       todo.id IN (SELECT id FROM blocked_todo) AS blocked,
+                       (SELECT COUNT(*) FROM comments WHERE comments.todo_id = todo.id) AS comment_count,
+                       label.id AS label_id,
+                       label.name AS label_name,
+                       label.color AS label_color,
+                       label.parent_id AS label_parent_id
+                FROM todo
+                    LEFT OUTER JOIN todo_labels ON todo.id = todo_labels.todo_id
+                    LEFT OUTER JOIN label ON label.id = todo_labels.label_id
+                WHERE todo.user_id = $1
+                ORDER BY todo.position ASC, todo.id ASC, label.id ASC;
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        Ok(Board::new(fold_todos(rows)))
+    }
+
+    async fn move_todo(
+        &self,
+        user_id: i32,
+        id: u32,
+        payload: MoveTodo,
+    ) -> Result<Todo, RepositoryError> {
+        let mut tx = self.pool.begin().await.map_err(handle_sqlx_error)?;
+
+        let status = check_status(&mut tx, user_id, id, payload.status).await?;
+        let position = position_after(&mut tx, user_id, id, status, payload.after).await?;
+
+        sqlx::query(
+            r#"
+                UPDATE todo
+                SET status   = $1,
+                    position = $2
+                WHERE id = $3;
+            "#,
+        )
+        .bind(status)
+        .bind(position)
+        .bind(id as i32)
+        .execute(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        if payload.status == Some(TodoStatus::Done) {
+            self.complete(&mut tx, user_id, id).await?;
+        }
+
+        tx.commit().await.map_err(handle_sqlx_error)?;
+
+        self.find(user_id, id).await
+    }
+
+    async fn add_dependency(
+        &self,
+        user_id: i32,
+        id: u32,
+        payload: CreateDependency,
+    ) -> Result<Todo, RepositoryError> {
+        let blocked_by = payload.blocked_by;
+        let mut tx = self.pool.begin().await.map_err(handle_sqlx_error)?;
+
+        let found = sqlx::query_scalar::<_, i32>(
+            r#"
+                SELECT id
+                FROM todo
+                WHERE id IN (SELECT value FROM json_each($1))
+                  AND user_id = $2;
+            "#,
+        )
+        .bind(Json([id, blocked_by]))
+        .bind(user_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?;
+        if let Some(missing) = [id, blocked_by]
+            .into_iter()
+            .find(|todo_id| !found.contains(&(*todo_id as i32)))
+        {
+            return Err(RepositoryError::NotFound(missing));
+        }
+
+        // `blocked_by`のTodoが既に`id`のTodoに (間接的にでも) ブロックされていれば循環になる
+        let cycle = sqlx::query_scalar::<_, bool>(
+            r#"
+                WITH RECURSIVE blockers (id) AS (
+                    SELECT $1
+                    UNION
+                    SELECT todo_dependencies.blocker_id
+                    FROM todo_dependencies
+                        INNER JOIN blockers ON todo_dependencies.todo_id = blockers.id
+                )
+                SELECT EXISTS (SELECT * FROM blockers WHERE id = $2);
+            "#,
+        )
+        .bind(blocked_by as i32)
+        .bind(id as i32)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?;
+        if cycle {
+            return Err(RepositoryError::DependencyCycle { id, blocked_by });
+        }
+
+        sqlx::query(
+            r#"
+                INSERT INTO todo_dependencies (todo_id, blocker_id)
+                VALUES ($1, $2)
+                ON CONFLICT DO NOTHING;
+            "#,
+        )
+        .bind(id as i32)
+        .bind(blocked_by as i32)
+        .execute(&mut *tx)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        tx.commit().await.map_err(handle_sqlx_error)?;
+
+        self.find(user_id, id).await
+    }
+}
+
+/// 現在の状態から`next`に変更できることを確認して変更後の状態を返す
+async fn check_status(
+    conn: &mut SqliteConnection,
+    user_id: i32,
+    id: u32,
+    next: Option<TodoStatus>,
+) -> Result<TodoStatus, RepositoryError> {
+    let current = sqlx::query_scalar::<_, TodoStatus>(
+        r#"
+            SELECT status
+            FROM todo
+            WHERE id = $1
+              AND user_id = $2;
+        "#,
+    )
+    .bind(id as i32)
+    .bind(user_id)
+    .fetch_optional(conn)
+    .await
+    .map_err(handle_sqlx_error)?
+    .ok_or(RepositoryError::NotFound(id))?;
+
+    match next {
+        Some(next) => current.transition(id, next),
+        None => Ok(current),
+    }
+}
+
+/// 親にするTodoが`user_id`のユーザーのものであることを確認する
+async fn check_parent(
+    conn: &mut SqliteConnection,
+    user_id: i32,
+    parent_id: u32,
+) -> Result<(), RepositoryError> {
+    sqlx::query_scalar::<_, i32>(
+        r#"
+            SELECT id
+            FROM todo
+            WHERE id = $1
+              AND user_id = $2;
+        "#,
+    )
+    .bind(parent_id as i32)
+    .bind(user_id)
+    .fetch_optional(conn)
+    .await
+    .map_err(handle_sqlx_error)?
+    .ok_or(RepositoryError::NotFound(parent_id))?;
+
+    Ok(())
+}
+
+async fn parent_of(conn: &mut SqliteConnection, id: u32) -> Result<Option<u32>, RepositoryError> {
+    let parent_id = sqlx::query_scalar::<_, Option<i32>>(
+        r#"
+            SELECT parent_id
+            FROM todo
+            WHERE id = $1;
+        "#,
+    )
+    .bind(id as i32)
+    .fetch_one(conn)
+    .await
+    .map_err(handle_sqlx_error)?;
+
+    Ok(parent_id.map(|id| id as u32))
+}
+
+/// `id`のTodoの子孫のうち未完了のものを数える
+async fn open_subtasks(conn: &mut SqliteConnection, id: u32) -> Result<i64, RepositoryError> {
+    sqlx::query_scalar::<_, i64>(
+        r#"
+            WITH RECURSIVE subtree (id, status) AS (
+                SELECT id, status
+                FROM todo
+                WHERE parent_id = $1
+                UNION ALL
+                SELECT todo.id, todo.status
+                FROM todo
+                    INNER JOIN subtree ON todo.parent_id = subtree.id
+            )
+            SELECT COUNT(*)
+            FROM subtree
+            WHERE status <> $2;
+        "#,
+    )
+    .bind(id as i32)
+    .bind(TodoStatus::Done)
+    .fetch_one(conn)
+    .await
+    .map_err(handle_sqlx_error)
+}
+
+/// 新しいTodoを未着手の列の末尾に並べるキー
+async fn last_position(
+    conn: &mut SqliteConnection,
+    user_id: i32,
+) -> Result<String, RepositoryError> {
+    let last = sqlx::query_scalar::<_, Option<String>>(
+        r#"
+            SELECT MAX(position)
+            FROM todo
+            WHERE user_id = $1
+              AND status = $2;
+        "#,
+    )
+    .bind(user_id)
+    .bind(TodoStatus::default())
+    .fetch_one(conn)
+    .await
+    .map_err(handle_sqlx_error)?;
+
+    key_between(last.as_deref(), None).ok_or_else(invalid_position)
+}
+
+/// 繰り返すTodoであれば、次の期限で同じ内容とラベルのTodoを作り、繰り返しの規則を引き継ぐ。
+/// 完了にしたTodoは規則を残し、作ったTodoを`next_occurrence_id`に記録する。
+/// 記録があれば何もしないため、完了にし直しても次のTodoは一つしかできない
+async fn create_next_occurrence(
+    conn: &mut SqliteConnection,
+    user_id: i32,
+    id: u32,
+) -> Result<(), RepositoryError> {
+    let (due_at, recurrence, next_occurrence_id) =
+        sqlx::query_as::<_, (Option<DateTime<Utc>>, Option<Json<Recurrence>>, Option<i32>)>(
+            r#"
+            SELECT due_at, recurrence, next_occurrence_id
+            FROM todo
+            WHERE id = $1;
+        "#,
+        )
+        .bind(id as i32)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(handle_sqlx_error)?;
+    // 既に次のTodoを作っていれば、完了にし直しても作らない
+    let (Some(Json(recurrence)), None) = (recurrence, next_occurrence_id) else {
+        return Ok(());
+    };
+
+    let Some((due_at, recurrence)) = recurrence.next(due_at.unwrap_or_else(Utc::now)) else {
+        return Ok(());
+    };
+
+    let position = last_position(&mut *conn, user_id).await?;
+    let next_id = sqlx::query_scalar::<_, i32>(
+        r#"
+            INSERT INTO todo
+                (text, user_id, due_at, position, parent_id, recurrence, priority, estimate)
+            SELECT text, user_id, $2, $3, parent_id, $4, priority, estimate
+            FROM todo
+            WHERE id = $1
+            RETURNING id;
+        "#,
+    )
+    .bind(id as i32)
+    .bind(due_at)
+    .bind(position)
+    .bind(Json(recurrence))
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(handle_sqlx_error)?;
+
+    sqlx::query(
+        r#"
+            UPDATE todo
+            SET next_occurrence_id = $2
+            WHERE id = $1;
+        "#,
+    )
+    .bind(id as i32)
+    .bind(next_id)
+    .execute(&mut *conn)
+    .await
+    .map_err(handle_sqlx_error)?;
+
+    sqlx::query(
+        r#"
+            INSERT INTO todo_labels (todo_id, label_id)
+            SELECT $1, label_id
+            FROM todo_labels
+            WHERE todo_id = $2;
+        "#,
+    )
+    .bind(next_id)
+    .bind(id as i32)
+    .execute(conn)
+    .await
+    .map_err(handle_sqlx_error)?;
+
+    Ok(())
+}
+
+/// `status`の列で`after`の直後 (省略した場合は列の先頭) に`id`のTodoを並べるキーを求める
+async fn position_after(
+    conn: &mut SqliteConnection,
+    user_id: i32,
+    id: u32,
+    status: TodoStatus,
+    after: Option<u32>,
+) -> Result<String, RepositoryError> {
+    let lower = match after {
+        Some(after) => Some(
+            sqlx::query_scalar::<_, String>(
+                r#"
+                    SELECT position
+                    FROM todo
+                    WHERE id = $1
+                      AND user_id = $2
+                      AND status = $3
+                      AND id <> $4;
+                "#,
+            )
+            .bind(after as i32)
+            .bind(user_id)
+            .bind(status)
+            .bind(id as i32)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(handle_sqlx_error)?
+            .ok_or(RepositoryError::NotFound(after))?,
+        ),
+        None => None,
+    };
+
+    let upper = sqlx::query_scalar::<_, Option<String>>(
+        r#"
+            SELECT MIN(position)
+            FROM todo
+            WHERE user_id = $1
+              AND status = $2
+              AND id <> $3
+              AND ($4 IS NULL OR position > $4);
+        "#,
+    )
+    .bind(user_id)
+    .bind(status)
+    .bind(id as i32)
+    .bind(&lower)
+    .fetch_one(conn)
+    .await
+    .map_err(handle_sqlx_error)?;
+
+    key_between(lower.as_deref(), upper.as_deref()).ok_or_else(invalid_position)
+}
+
+/// 紐付けるラベルが全て`user_id`のユーザーのものであることを確認する
+async fn check_labels(
+    conn: &mut SqliteConnection,
+    user_id: i32,
+    labels: &[i32],
+) -> Result<(), RepositoryError> {
+    let found = sqlx::query_scalar::<_, i32>(
+        r#"
+            SELECT id
+            FROM label
+            WHERE id IN (SELECT value FROM json_each($1))
+              AND user_id = $2;
+        "#,
+    )
+    .bind(Json(labels))
+    .bind(user_id)
+    .fetch_all(conn)
+    .await
+    .map_err(handle_sqlx_error)?;
+
+    check_unknown_labels(labels, &found)
+}
+
+async fn attach_labels(
+    conn: &mut SqliteConnection,
+    todo_id: i32,
+    labels: &[i32],
+) -> Result<(), RepositoryError> {
+    sqlx::query(
+        r#"
+            INSERT INTO todo_labels (todo_id, label_id)
+            SELECT $1, value
+            FROM json_each($2);
+        "#,
+    )
+    .bind(todo_id)
+    .bind(Json(labels))
+    .execute(conn)
+    .await
+    .map_err(handle_sqlx_error)?;
+
+    Ok(())
+}
+
+fn handle_sqlx_error(error: sqlx::Error) -> RepositoryError {
+    RepositoryError::Unexpected(error.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repository::{
+        label::LabelRepositoryForSqlite, todo::conformance, user::UserRepositoryForSqlite,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn conformance() {
+        let pool = SqlitePool::connect("sqlite::memory:")
+            .await
+            .expect("fail connect database");
+        sqlx::migrate!("./migrations/sqlite")
+            .run(&pool)
+            .await
+            .expect("fail migrate database");
+
+        conformance::run(
+            TodoRepositoryForSqlite::new(pool.clone()),
+            LabelRepositoryForSqlite::new(pool.clone()),
+            UserRepositoryForSqlite::new(pool.clone()),
+        )
+        .await;
+
+        conformance::run_auto_complete(
+            TodoRepositoryForSqlite::new(pool.clone())
+                .with_subtask_completion(SubtaskCompletion::AutoComplete),
+            UserRepositoryForSqlite::new(pool),
+        )
+        .await;
+    }
+}
@@ -0,0 +1,139 @@
+#[cfg(test)]
+pub(crate) mod conformance;
+mod memory;
+mod postgres;
+mod sqlite;
+
+use std::sync::LazyLock;
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use axum::async_trait;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use validator::Validate;
+
+use super::{validate_not_blank, RepositoryError};
+
+pub use memory::UserRepositoryForMemory;
+pub use postgres::UserRepositoryForPostgres;
+pub use sqlite::UserRepositoryForSqlite;
+
+#[async_trait]
+pub trait UserRepository: Send + Sync + 'static {
+    async fn all(&self) -> Result<Vec<User>, RepositoryError>;
+    async fn find(&self, id: i32) -> Result<User, RepositoryError>;
+    async fn create(&self, payload: CreateUser) -> Result<User, RepositoryError>;
+    /// ユーザー名とパスワードが一致するユーザーを返す
+    async fn authenticate(&self, payload: Credentials) -> Result<User, RepositoryError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Validate)]
+pub struct CreateUser {
+    #[validate(length(min = 1, message = "Can not be empty"))]
+    #[validate(length(max = 50, message = "Over username length"))]
+    #[validate(custom = "validate_not_blank")]
+    username: String,
+    /// Argon2でハッシュ化する負荷を抑えるため、長さに上限を設ける
+    #[validate(length(min = 8, message = "Too short password"))]
+    #[validate(length(max = 128, message = "Over password length"))]
+    password: String,
+}
+
+impl CreateUser {
+    pub fn new(username: String, password: String) -> Self {
+        Self { username, password }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Credentials {
+    username: String,
+    password: String,
+}
+
+impl Credentials {
+    pub fn new(username: String, password: String) -> Self {
+        Self { username, password }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct User {
+    id: i32,
+    name: String,
+}
+
+impl User {
+    pub fn new(id: i32, name: String) -> Self {
+        Self { id, name }
+    }
+
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+}
+
+#[derive(Debug, Clone, FromRow)]
+struct UserWithPasswordDto {
+    id: i32,
+    name: String,
+    password_hash: String,
+}
+
+impl From<UserWithPasswordDto> for User {
+    fn from(dto: UserWithPasswordDto) -> Self {
+        Self {
+            id: dto.id,
+            name: dto.name,
+        }
+    }
+}
+
+/// Argon2の計算は重いため、非同期ランタイムのワーカーを塞がないよう`spawn_blocking`で実行する
+async fn hash_password(password: String) -> Result<String, RepositoryError> {
+    tokio::task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|error| RepositoryError::Unexpected(error.to_string().into()))?;
+        Ok(hash.to_string())
+    })
+    .await
+    .map_err(|error| RepositoryError::Unexpected(error.into()))?
+}
+
+/// 存在しないユーザーの照合に使うハッシュ
+static DUMMY_PASSWORD_HASH: LazyLock<String> = LazyLock::new(|| {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(b"dummy password", &salt)
+        .expect("fail hash dummy password")
+        .to_string()
+});
+
+/// ハッシュがない (ユーザーが存在しない) 場合やハッシュとして解釈できない値は不一致とみなす。
+/// 応答時間からユーザー名の有無を推測されないよう、その場合もダミーのハッシュと照合する
+async fn verify_password(
+    password_hash: Option<String>,
+    password: String,
+) -> Result<bool, RepositoryError> {
+    tokio::task::spawn_blocking(move || {
+        let argon2 = Argon2::default();
+        match password_hash
+            .as_deref()
+            .and_then(|hash| PasswordHash::new(hash).ok())
+        {
+            Some(hash) => argon2.verify_password(password.as_bytes(), &hash).is_ok(),
+            None => {
+                let dummy = PasswordHash::new(&DUMMY_PASSWORD_HASH).expect("invalid dummy hash");
+                let _ = argon2.verify_password(password.as_bytes(), &dummy);
+                false
+            }
+        }
+    })
+    .await
+    .map_err(|error| RepositoryError::Unexpected(error.into()))
+}
@@ -0,0 +1,136 @@
+//! 全ての`UserRepository`の実装が満たすべき振る舞い
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::repository::RepositoryError;
+
+use super::{CreateUser, Credentials, User, UserRepository};
+
+pub(crate) async fn run<R: UserRepository>(repository: R) {
+    crud(&repository).await;
+    ordering(&repository).await;
+    not_found(&repository).await;
+    duplicate(&repository).await;
+    authenticate(&repository).await;
+}
+
+/// ユーザーは削除できないため、実行毎に異なるユーザー名を生成する
+fn unique_name(name: &str) -> String {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_nanos();
+    format!("{name} {nanos}")
+}
+
+/// 他のリポジトリのテストでデータの所有者にするユーザーを作成する
+pub(crate) async fn create_user<R: UserRepository>(repository: &R, name: &str) -> User {
+    repository
+        .create(CreateUser::new(unique_name(name), "password".to_string()))
+        .await
+        .expect("fail create user")
+}
+
+async fn crud<R: UserRepository>(repository: &R) {
+    let name = unique_name("[user::crud]");
+
+    // create
+    let created = repository
+        .create(CreateUser::new(name.clone(), "password".to_string()))
+        .await
+        .expect("fail create user");
+    assert_eq!(created.name, name);
+
+    // find
+    let user = repository.find(created.id).await.expect("fail find user");
+    assert_eq!(created, user);
+
+    // all
+    let users = repository.all().await.expect("fail fetch all users");
+    assert!(users.contains(&created), "created user is not listed");
+}
+
+/// `all`はIDの昇順で返す
+async fn ordering<R: UserRepository>(repository: &R) {
+    let first = repository
+        .create(CreateUser::new(
+            unique_name("[user::ordering] first"),
+            "password".to_string(),
+        ))
+        .await
+        .expect("fail create user");
+    let second = repository
+        .create(CreateUser::new(
+            unique_name("[user::ordering] second"),
+            "password".to_string(),
+        ))
+        .await
+        .expect("fail create user");
+    assert!(first.id < second.id);
+
+    let users = repository.all().await.expect("fail fetch all users");
+    assert!(
+        users.windows(2).all(|pair| pair[0].id < pair[1].id),
+        "users are not ordered by id ascending: {users:?}"
+    );
+}
+
+async fn not_found<R: UserRepository>(repository: &R) {
+    let result = repository.find(i32::MAX).await;
+    assert!(
+        matches!(result, Err(RepositoryError::NotFound(id)) if id == i32::MAX as u32),
+        "unexpected result: {result:?}"
+    );
+}
+
+/// 同名のユーザーは既存のユーザーのIDで重複エラーになる
+async fn duplicate<R: UserRepository>(repository: &R) {
+    let name = unique_name("[user::duplicate]");
+    let user = repository
+        .create(CreateUser::new(name.clone(), "password".to_string()))
+        .await
+        .expect("fail create user");
+
+    let result = repository
+        .create(CreateUser::new(name, "password".to_string()))
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::Duplicate(id)) if id == user.id),
+        "unexpected result: {result:?}"
+    );
+}
+
+/// パスワードが一致しない場合は存在しないユーザーと区別せずに認証エラーになる
+async fn authenticate<R: UserRepository>(repository: &R) {
+    let name = unique_name("[user::authenticate]");
+    let password = "correct horse battery staple";
+    let user = repository
+        .create(CreateUser::new(name.clone(), password.to_string()))
+        .await
+        .expect("fail create user");
+
+    let authenticated = repository
+        .authenticate(Credentials::new(name.clone(), password.to_string()))
+        .await
+        .expect("fail authenticate user");
+    assert_eq!(user, authenticated);
+
+    let result = repository
+        .authenticate(Credentials::new(name, "wrong password".to_string()))
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::Unauthorized)),
+        "unexpected result: {result:?}"
+    );
+
+    let result = repository
+        .authenticate(Credentials::new(
+            unique_name("[user::authenticate] unknown"),
+            password.to_string(),
+        ))
+        .await;
+    assert!(
+        matches!(result, Err(RepositoryError::Unauthorized)),
+        "unexpected result: {result:?}"
+    );
+}
@@ -0,0 +1,108 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use axum::async_trait;
+
+use crate::repository::RepositoryError;
+
+use super::{
+    hash_password, verify_password, CreateUser, Credentials, User, UserRepository,
+    UserWithPasswordDto,
+};
+
+#[derive(Debug, Default)]
+struct UserData {
+    users: HashMap<i32, UserWithPasswordDto>,
+    last_id: i32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UserRepositoryForMemory {
+    store: Arc<RwLock<UserData>>,
+}
+
+impl UserRepositoryForMemory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn write_store_ref(&self) -> RwLockWriteGuard<'_, UserData> {
+        self.store.write().unwrap()
+    }
+
+    fn read_store_ref(&self) -> RwLockReadGuard<'_, UserData> {
+        self.store.read().unwrap()
+    }
+}
+
+#[async_trait]
+impl UserRepository for UserRepositoryForMemory {
+    async fn all(&self) -> Result<Vec<User>, RepositoryError> {
+        let store = self.read_store_ref();
+        let mut users: Vec<User> = store.users.values().cloned().map(User::from).collect();
+        users.sort_by_key(|user| user.id);
+        Ok(users)
+    }
+
+    async fn find(&self, id: i32) -> Result<User, RepositoryError> {
+        let store = self.read_store_ref();
+        let user = store
+            .users
+            .get(&id)
+            .cloned()
+            .ok_or(RepositoryError::NotFound(id as u32))?;
+        Ok(User::from(user))
+    }
+
+    async fn create(&self, payload: CreateUser) -> Result<User, RepositoryError> {
+        let password_hash = hash_password(payload.password).await?;
+
+        let mut store = self.write_store_ref();
+        if let Some(user) = store
+            .users
+            .values()
+            .find(|user| user.name == payload.username)
+        {
+            return Err(RepositoryError::Duplicate(user.id));
+        }
+
+        store.last_id += 1;
+        let id = store.last_id;
+        let user = UserWithPasswordDto {
+            id,
+            name: payload.username,
+            password_hash,
+        };
+        store.users.insert(id, user.clone());
+        Ok(User::from(user))
+    }
+
+    async fn authenticate(&self, payload: Credentials) -> Result<User, RepositoryError> {
+        let user = self
+            .read_store_ref()
+            .users
+            .values()
+            .find(|user| user.name == payload.username)
+            .cloned();
+        let password_hash = user.as_ref().map(|user| user.password_hash.clone());
+        let verified = verify_password(password_hash, payload.password).await?;
+        match user {
+            Some(user) if verified => Ok(User::from(user)),
+            _ => Err(RepositoryError::Unauthorized),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repository::user::conformance;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn conformance() {
+        conformance::run(UserRepositoryForMemory::new()).await;
+    }
+}
@@ -0,0 +1,135 @@
+use axum::async_trait;
+use sqlx::SqlitePool;
+
+use crate::repository::RepositoryError;
+
+use super::{
+    hash_password, verify_password, CreateUser, Credentials, User, UserRepository,
+    UserWithPasswordDto,
+};
+
+#[derive(Debug, Clone)]
+pub struct UserRepositoryForSqlite {
+    pool: SqlitePool,
+}
+
+impl UserRepositoryForSqlite {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl UserRepository for UserRepositoryForSqlite {
+    async fn all(&self) -> Result<Vec<User>, RepositoryError> {
+        let users = sqlx::query_as::<_, User>(
+            r#"
+                SELECT *
+                FROM users
+                ORDER BY id ASC;
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        Ok(users)
+    }
+
+    async fn find(&self, id: i32) -> Result<User, RepositoryError> {
+        let user = sqlx::query_as::<_, User>(
+            r#"
+                SELECT *
+                FROM users
+                WHERE id = $1;
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?
+        .ok_or(RepositoryError::NotFound(id as u32))?;
+
+        Ok(user)
+    }
+
+    async fn create(&self, payload: CreateUser) -> Result<User, RepositoryError> {
+        let password_hash = hash_password(payload.password).await?;
+
+        let user = sqlx::query_as::<_, User>(
+            r#"
+                INSERT INTO users (name, password_hash)
+                VALUES ($1, $2)
+                ON CONFLICT (name) DO NOTHING
+                RETURNING *;
+            "#,
+        )
+        .bind(&payload.username)
+        .bind(password_hash)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        if let Some(user) = user {
+            return Ok(user);
+        }
+
+        let id = sqlx::query_scalar::<_, i32>(
+            r#"
+                SELECT id
+                FROM users
+                WHERE name = $1;
+            "#,
+        )
+        .bind(&payload.username)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?;
+
+        Err(RepositoryError::Duplicate(id))
+    }
+
+    async fn authenticate(&self, payload: Credentials) -> Result<User, RepositoryError> {
+        let user = sqlx::query_as::<_, UserWithPasswordDto>(
+            r#"
+                SELECT *
+                FROM users
+                WHERE name = $1;
+            "#,
+        )
+        .bind(&payload.username)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(handle_sqlx_error)?;
+        let password_hash = user.as_ref().map(|user| user.password_hash.clone());
+        let verified = verify_password(password_hash, payload.password).await?;
+        match user {
+            Some(user) if verified => Ok(User::from(user)),
+            _ => Err(RepositoryError::Unauthorized),
+        }
+    }
+}
+
+fn handle_sqlx_error(error: sqlx::Error) -> RepositoryError {
+    RepositoryError::Unexpected(error.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::repository::user::conformance;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn conformance() {
+        let pool = SqlitePool::connect("sqlite::memory:")
+            .await
+            .expect("fail connect database");
+        sqlx::migrate!("./migrations/sqlite")
+            .run(&pool)
+            .await
+            .expect("fail migrate database");
+
+        conformance::run(UserRepositoryForSqlite::new(pool)).await;
+    }
+}